@@ -0,0 +1,385 @@
+//! Load test of example_server: `BOTS` simulated players against `WEB_SERVER_URL`.
+//!
+//! Each bot sets a name, gets the existing moles from replication, and clicks at new moles
+//! after `REACTION_DELAY_MS`, hitting them with probability `ACCURACY`.
+//! Throughput, latencies from a hit to its `DeadMole` or `MoleDamaged`, and disconnects are reported
+//! every `REPORT_INTERVAL_SECS`, until `DURATION_SECS`.
+//!
+//! The server accepts a limited number of connections per IP, run bots from several machines
+//! or raise `max_connections_per_ip` of the server under test.
+
+use std::{
+    env,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bevy::{app::ScheduleRunnerPlugin, prelude::*, utils::HashMap};
+use example_client_logic::{GameNetPlugin, GameReceiveSet, LocalPlayer};
+use example_shared::{ClientMessage, Mole, MoleCatalog, RoundPhase, ServerMessage};
+use litlnet_client_bevy::{
+    ClientSet, ConnectionState, ConnectionStateChanged, MessagesToRead, MessagesToSend, NetStats,
+    ServerTime, Session,
+};
+use litlnet_trait::Traffic;
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+#[derive(Clone)]
+struct Config {
+    /// Read by [`GameNetPlugin`] too.
+    url: String,
+    bots: usize,
+    /// Probability of a click to be on the mole.
+    accuracy: f64,
+    reaction_delay: Duration,
+    duration: Duration,
+    report_interval: Duration,
+    /// Bots are started evenly over this duration, the server limits accepts per second.
+    ramp_up: Duration,
+}
+
+fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+impl Config {
+    fn from_env() -> Self {
+        Self {
+            url: env::var("WEB_SERVER_URL").unwrap_or_else(|_| "ws://127.0.0.1:8083".to_string()),
+            bots: env_or("BOTS", 10),
+            accuracy: env_or("ACCURACY", 0.8f64).clamp(0f64, 1f64),
+            reaction_delay: Duration::from_millis(env_or("REACTION_DELAY_MS", 400)),
+            duration: Duration::from_secs(env_or("DURATION_SECS", 60)),
+            report_interval: Duration::from_secs(env_or("REPORT_INTERVAL_SECS", 5).max(1)),
+            ramp_up: Duration::from_secs(env_or("RAMP_UP_SECS", 10)),
+        }
+    }
+}
+
+/// Shared by the bots and the reporting thread.
+#[derive(Default)]
+struct Report {
+    /// Latest total traffic of each bot.
+    traffic: HashMap<usize, Traffic>,
+    connected: HashMap<usize, bool>,
+    rtts: HashMap<usize, Duration>,
+    disconnects: u64,
+    hits: u64,
+    kills: u64,
+    /// From a hit to the `DeadMole` or `MoleDamaged` crediting the bot, since the last report.
+    latencies: Vec<Duration>,
+}
+
+#[derive(Resource, Clone)]
+struct SharedReport(Arc<Mutex<Report>>);
+
+/// What a bot accumulates between two flushes to the [`SharedReport`].
+#[derive(Default)]
+struct Pending {
+    disconnects: u64,
+    hits: u64,
+    kills: u64,
+    latencies: Vec<Duration>,
+}
+
+#[derive(Resource)]
+struct Bot {
+    index: usize,
+    config: Config,
+    rng: SmallRng,
+    /// Moles to click at, when their timer finishes.
+    targets: Vec<(usize, Timer)>,
+    in_flight: HashMap<usize, Instant>,
+    pending: Pending,
+    flush_timer: Timer,
+}
+
+fn main() {
+    let config = Config::from_env();
+    let report = SharedReport(Arc::new(Mutex::new(Report::default())));
+    println!(
+        "{} bots against {}, accuracy {}, reaction delay {:?}",
+        config.bots, config.url, config.accuracy, config.reaction_delay
+    );
+    let start = Instant::now();
+    let spawn_interval = config
+        .ramp_up
+        .checked_div(config.bots as u32)
+        .unwrap_or_default();
+    let mut last_report = Duration::ZERO;
+    let mut previous_traffic = Traffic::default();
+    let mut spawned = 0;
+    while start.elapsed() < config.duration {
+        if spawned < config.bots && start.elapsed() >= spawn_interval * spawned as u32 {
+            let config = config.clone();
+            let report = report.clone();
+            let index = spawned;
+            std::thread::spawn(move || run_bot(index, config, report));
+            spawned += 1;
+            continue;
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= last_report + config.report_interval {
+            previous_traffic =
+                print_report(&report, elapsed, elapsed - last_report, previous_traffic);
+            last_report = elapsed;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let elapsed = start.elapsed();
+    print_report(&report, elapsed, elapsed - last_report, previous_traffic);
+    let report = report.0.lock().unwrap();
+    println!(
+        "total: {} hits, {} kills, {} disconnects",
+        report.hits, report.kills, report.disconnects
+    );
+}
+
+/// Returns the total traffic, to compute the throughput of the next report.
+fn print_report(
+    report: &SharedReport,
+    elapsed: Duration,
+    interval: Duration,
+    previous_traffic: Traffic,
+) -> Traffic {
+    let mut report = report.0.lock().unwrap();
+    let traffic = report
+        .traffic
+        .values()
+        .fold(Traffic::default(), |total, traffic| total + *traffic);
+    let delta = traffic.saturating_sub(previous_traffic);
+    let seconds = interval.as_secs_f64().max(f64::EPSILON);
+    let mut latencies = std::mem::take(&mut report.latencies);
+    let mut rtts: Vec<_> = report.rtts.values().copied().collect();
+    println!(
+        "[{:>4}s] {}/{} connected, {} disconnects | sent {:.0} msg/s {:.1} KB/s, received {:.0} msg/s {:.1} KB/s | hits {} kills {} | latency {} | rtt {}",
+        elapsed.as_secs(),
+        report.connected.values().filter(|connected| **connected).count(),
+        report.connected.len(),
+        report.disconnects,
+        delta.messages_sent as f64 / seconds,
+        delta.bytes_sent as f64 / seconds / 1024f64,
+        delta.messages_received as f64 / seconds,
+        delta.bytes_received as f64 / seconds / 1024f64,
+        report.hits,
+        report.kills,
+        percentiles(&mut latencies),
+        percentiles(&mut rtts),
+    );
+    traffic
+}
+
+fn percentiles(samples: &mut [Duration]) -> String {
+    if samples.is_empty() {
+        return "-".to_string();
+    }
+    samples.sort();
+    let at = |percent: usize| samples[(samples.len() - 1) * percent / 100].as_millis();
+    format!(
+        "p50 {}ms p90 {}ms p99 {}ms max {}ms",
+        at(50),
+        at(90),
+        at(99),
+        at(100)
+    )
+}
+
+fn run_bot(index: usize, config: Config, report: SharedReport) {
+    App::new()
+        .add_plugins(
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+                1.0 / 60.0,
+            ))),
+        )
+        .add_plugins(GameNetPlugin)
+        .insert_resource(Bot {
+            index,
+            config,
+            rng: SmallRng::from_entropy(),
+            targets: vec![],
+            in_flight: HashMap::default(),
+            pending: Pending::default(),
+            flush_timer: Timer::from_seconds(1f32, TimerMode::Repeating),
+        })
+        .insert_resource(report)
+        // Sent on connection.
+        .add_systems(Startup, move |mut local_player: ResMut<LocalPlayer>| {
+            local_player.name = format!("bot{}", index);
+            local_player.is_final = true;
+        })
+        .add_systems(
+            Update,
+            receive_messages
+                .after(ClientSet::Receive)
+                .before(GameReceiveSet),
+        )
+        .add_systems(
+            Update,
+            (
+                aim_at_new_moles,
+                click_moles,
+                count_disconnects,
+                flush_report,
+            ),
+        )
+        .run();
+}
+
+impl Bot {
+    fn aim(&mut self, mole_id: usize) {
+        // Players are not all as fast.
+        let delay = self
+            .config
+            .reaction_delay
+            .mul_f64(self.rng.gen_range(0.5..1.5));
+        self.targets
+            .push((mole_id, Timer::new(delay, TimerMode::Once)));
+    }
+}
+
+fn aim_at_new_moles(mut bot: ResMut<Bot>, moles: Query<&Mole, Added<Mole>>) {
+    for mole in moles.iter() {
+        bot.aim(mole.id);
+    }
+}
+
+fn click_moles(
+    time: Res<Time>,
+    real_time: Res<Time<Real>>,
+    server_time: Res<ServerTime>,
+    mut bot: ResMut<Bot>,
+    mut send: ResMut<MessagesToSend<ClientMessage>>,
+    catalog: Option<Res<MoleCatalog>>,
+    moles: Query<&Mole>,
+) {
+    let bot = bot.as_mut();
+    for (_, timer) in bot.targets.iter_mut() {
+        timer.tick(time.delta());
+    }
+    let (ready, waiting) = std::mem::take(&mut bot.targets)
+        .into_iter()
+        .partition(|(_, timer)| timer.finished());
+    bot.targets = waiting;
+    for (mole_id, _) in ready {
+        let mole = match moles.iter().find(|mole| mole.id == mole_id) {
+            Some(mole) => mole,
+            // Already dead.
+            None => continue,
+        };
+        // Where it is now, the server checks the hit at that time.
+        let now = server_time.now(&real_time);
+        let mut position = mole.def.position_at(now.unwrap_or(mole.def.start_time));
+        if bot.rng.gen_bool(bot.config.accuracy) {
+            bot.in_flight.insert(mole_id, Instant::now());
+        } else {
+            // Out of the hit radius of the server.
+            let hit_radius = catalog
+                .as_ref()
+                .and_then(|catalog| catalog.get(mole.def.catalog_id))
+                .map_or(50f32, |kind| kind.hit_radius);
+            let angle = bot.rng.gen_range(0f32..std::f32::consts::TAU);
+            position += Vec2::from_angle(angle) * (hit_radius + 30f32);
+        }
+        send.push(ClientMessage::hit(position, now));
+        bot.pending.hits += 1;
+    }
+}
+
+fn receive_messages(
+    session: Res<Session>,
+    mut bot: ResMut<Bot>,
+    recv: Res<MessagesToRead<ServerMessage>>,
+) {
+    // Popped by `GameNetPlugin`.
+    for message in recv.iter().cloned() {
+        match message {
+            ServerMessage::DeadMole {
+                mole_id,
+                player_killer_id,
+            } => {
+                bot.targets.retain(|(target, _)| *target != mole_id);
+                if let Some(sent_at) = bot.in_flight.remove(&mole_id) {
+                    if session.id.is_some_and(|id| id.0 == player_killer_id) {
+                        bot.pending.kills += 1;
+                        bot.pending.latencies.push(sent_at.elapsed());
+                    }
+                }
+            }
+            ServerMessage::MoleDamaged { mole_id, by, .. } => {
+                if session.id.is_some_and(|id| id.0 == by) {
+                    if let Some(sent_at) = bot.in_flight.remove(&mole_id) {
+                        bot.pending.latencies.push(sent_at.elapsed());
+                    }
+                }
+                // Tough moles take several hits, from anyone.
+                if bot.targets.iter().all(|(target, _)| *target != mole_id) {
+                    bot.aim(mole_id);
+                }
+            }
+            // The hit arrived too late, it's not a latency sample.
+            ServerMessage::EscapedMole(mole_id) => {
+                bot.targets.retain(|(target, _)| *target != mole_id);
+                bot.in_flight.remove(&mole_id);
+            }
+            // The moles left are despawned.
+            ServerMessage::RoundState {
+                phase: RoundPhase::Results,
+                ..
+            } => {
+                bot.targets.clear();
+                bot.in_flight.clear();
+            }
+            // Moles and scores are replicated.
+            ServerMessage::RoundState { .. }
+            | ServerMessage::Spawn(_)
+            | ServerMessage::UpdateScores(_)
+            | ServerMessage::RoundWinners(_)
+            | ServerMessage::OwnRank { .. }
+            | ServerMessage::NameAccepted(_)
+            | ServerMessage::NameRejected(_)
+            | ServerMessage::Challenge(_)
+            | ServerMessage::Authenticated { .. }
+            | ServerMessage::AuthenticationFailed => {}
+        }
+    }
+}
+
+fn count_disconnects(mut bot: ResMut<Bot>, mut events: EventReader<ConnectionStateChanged>) {
+    for event in events.read() {
+        if event.previous == ConnectionState::Connected {
+            bot.pending.disconnects += 1;
+            // Their responses are lost with the connection.
+            bot.in_flight.clear();
+        }
+    }
+}
+
+fn flush_report(
+    time: Res<Time>,
+    mut bot: ResMut<Bot>,
+    stats: Res<NetStats>,
+    state: Res<ConnectionState>,
+    report: Res<SharedReport>,
+) {
+    bot.flush_timer.tick(time.delta());
+    if !bot.flush_timer.just_finished() {
+        return;
+    }
+    let pending = std::mem::take(&mut bot.pending);
+    let mut report = report.0.lock().unwrap();
+    report.traffic.insert(bot.index, stats.total);
+    report
+        .connected
+        .insert(bot.index, *state == ConnectionState::Connected);
+    if let Some(rtt) = stats.rtt {
+        report.rtts.insert(bot.index, rtt);
+    }
+    report.disconnects += pending.disconnects;
+    report.hits += pending.hits;
+    report.kills += pending.kills;
+    report.latencies.extend(pending.latencies);
+}
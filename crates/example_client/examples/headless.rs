@@ -0,0 +1,64 @@
+//! Bots without window nor GPU, `BOTS` of them hit a mole every second.
+
+use std::time::Duration;
+
+use bevy::{app::ScheduleRunnerPlugin, prelude::*};
+use example_client_logic::{GameNetPlugin, LocalPlayer};
+use example_shared::{ClientMessage, Mole};
+use litlnet_client_bevy::{MessagesToSend, ServerTime};
+
+#[derive(Resource)]
+struct Bot {
+    timer: Timer,
+}
+
+pub fn main() {
+    let bots = std::env::var("BOTS")
+        .ok()
+        .and_then(|bots| bots.parse().ok())
+        .unwrap_or(1);
+    let threads: Vec<_> = (0..bots)
+        .map(|index| std::thread::spawn(move || run_bot(index)))
+        .collect();
+    for thread in threads {
+        let _ = thread.join();
+    }
+}
+
+fn run_bot(index: usize) {
+    App::new()
+        .add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(
+            Duration::from_secs_f64(1.0 / 60.0),
+        )))
+        .add_plugins(GameNetPlugin)
+        .insert_resource(Bot {
+            // Spread the hits of the bots.
+            timer: Timer::from_seconds(1f32 + index as f32 * 0.01, TimerMode::Repeating),
+        })
+        // Sent on connection.
+        .add_systems(Startup, move |mut local_player: ResMut<LocalPlayer>| {
+            local_player.name = format!("bot{}", index);
+            local_player.is_final = true;
+        })
+        .add_systems(Update, hit_moles)
+        .run();
+}
+
+fn hit_moles(
+    time: Res<Time>,
+    mut bot: ResMut<Bot>,
+    real_time: Res<Time<Real>>,
+    server_time: Res<ServerTime>,
+    mut send: ResMut<MessagesToSend<ClientMessage>>,
+    moles: Query<&Mole>,
+) {
+    bot.timer.tick(time.delta());
+    if !bot.timer.just_finished() {
+        return;
+    }
+    if let Some(mole) = moles.iter().next() {
+        let now = server_time.now(&real_time);
+        let position = mole.def.position_at(now.unwrap_or(mole.def.start_time));
+        send.push(ClientMessage::hit(position, now));
+    }
+}
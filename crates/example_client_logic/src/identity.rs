@@ -0,0 +1,81 @@
+use bevy::prelude::*;
+use example_shared::{public_key, sign_challenge, to_hex, ClientMessage};
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+
+/// Where the identity is kept, a file next to the game or a key of the browser's local storage.
+const STORAGE_KEY: &str = "whack_a_bevy_identity.json";
+
+/// The secret key of the player's account, kept between games. Without it, the player is a guest.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+pub struct Identity {
+    /// Ed25519, in hex, never sent: the server only gets its public key and signatures.
+    pub key: String,
+    /// Given by the server once registered.
+    pub account: Option<u64>,
+}
+
+impl Identity {
+    /// The saved one, or a new one.
+    pub fn load_or_create() -> Self {
+        read()
+            .and_then(|json| serde_json::from_str::<Self>(&json).ok())
+            .filter(|identity| public_key(&identity.key).is_some())
+            .unwrap_or_else(|| {
+                let identity = Self {
+                    key: to_hex(&thread_rng().gen::<[u8; 32]>()),
+                    account: None,
+                };
+                identity.save();
+                identity
+            })
+    }
+
+    /// Signs `challenge` to play as the account, or to register one.
+    pub fn answer(&self, challenge: &str) -> Option<ClientMessage> {
+        let signature = sign_challenge(&self.key, challenge)?;
+        Some(match self.account {
+            Some(account) => ClientMessage::Authenticate { account, signature },
+            None => ClientMessage::Register {
+                public_key: public_key(&self.key)?,
+                signature,
+            },
+        })
+    }
+
+    pub fn save(&self) {
+        if let Ok(json) = serde_json::to_string(self) {
+            if let Err(e) = write(&json) {
+                dbg!("identity not saved", e);
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read() -> Option<String> {
+    std::fs::read_to_string(STORAGE_KEY).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write(json: &str) -> Result<(), String> {
+    std::fs::write(STORAGE_KEY, json).map_err(|e| e.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read() -> Option<String> {
+    local_storage()?.get_item(STORAGE_KEY).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write(json: &str) -> Result<(), String> {
+    local_storage()
+        .ok_or("no local storage")?
+        .set_item(STORAGE_KEY, json)
+        .map_err(|e| format!("{:?}", e))
+}
@@ -1,13 +1,22 @@
 mod cheatbook;
+mod identity;
 
 use bevy::{
-    math::Vec3Swizzles,
     prelude::*,
     window::{PrimaryWindow, WindowResolution},
 };
 
-use example_shared::{AllExistingMoles, ClientMessage, ServerMessage};
-use litlnet_client_bevy::{ClientPlugin, MessagesToRead, MessagesToSend, RComClient};
+pub use identity::Identity;
+
+use example_shared::{
+    ClientMessage, Mole, MoleCatalog, MoleKind, MoleKindDef, NameRejection, PlayerRank, RoundPhase,
+    Scores, ServerMessage,
+};
+use litlnet_client_bevy::{
+    just_connected, Backoff, ClientPlugin, ClientSet, MessagesToRead, MessagesToSend, RComClient,
+    ReconnectPlugin, ReplicationAppExt, ServerTime, Session,
+};
+use litlnet_trait::{Compression, DecodeLimits};
 
 #[cfg(target_arch = "wasm32")]
 type ComClient = litlnet_websocket_web::WebsocketClient;
@@ -18,15 +27,6 @@ type ComClient = litlnet_websocket::WebsocketClient;
 #[derive(Component)]
 pub struct MainCamera;
 
-#[derive(Component)]
-struct VisualMole {
-    id: usize,
-}
-
-#[derive(Resource)]
-struct AssetsVisualPlayer {
-    pub sprite_handles: Vec<Handle<Image>>,
-}
 #[derive(Default, Resource)]
 struct AssetsExplosions {
     pub explosion_local: Vec<Handle<Image>>,
@@ -40,18 +40,6 @@ struct ExplosionData {
     kind: ExplosionKind,
 }
 
-#[derive(Resource)]
-enum WantToRequestExisting {
-    No,
-    Yes(Timer),
-}
-
-#[derive(Resource)]
-pub struct ReconnectState {
-    timer: Timer,
-    attempt: usize,
-}
-
 #[derive(PartialEq, Clone)]
 enum ExplosionKind {
     LocalPlayer,
@@ -64,53 +52,143 @@ pub struct SpawnExplosionEvent {
     kind: ExplosionKind,
 }
 
+#[derive(Event)]
+pub struct SpawnEscapeEvent {
+    position: Vec2,
+    catalog_id: usize,
+}
+
+/// A mole nobody hit, sinking in its hole.
+#[derive(Component)]
+struct EscapeData {
+    timer: Timer,
+    from: Vec2,
+}
+
+#[derive(Event)]
+pub struct MoleDamagedEvent {
+    mole_id: usize,
+    kind: ExplosionKind,
+}
+
+/// Tints a tough mole after a hit.
+#[derive(Component)]
+struct DamageFlash {
+    timer: Timer,
+    color: Color,
+    tint: Color,
+}
+
+/// Remaining hits of a tough mole, child of its sprite.
+#[derive(Component)]
+struct HitCountBadge;
+
 #[derive(Resource)]
 pub struct LocalPlayer {
-    // used to map score (we can have multiple players bringing score to the same rank,
-    // it's ok because I won't dev a full authentication system for a jam yet.)
-    name: String,
-    is_final: bool,
-    // used to know who killed which mole
-    id: Option<usize>,
-    score: Option<u32>,
+    pub name: String,
+    pub is_final: bool,
+    /// Why the server refused the last name sent, it can be edited again.
+    name_rejection: Option<NameRejection>,
+    /// In the current round, from 1, as told by the server.
+    rank: Option<usize>,
+    score: usize,
 }
 
 #[derive(Resource)]
 pub struct RemotePlayers {
-    players: Vec<(String, u32)>,
+    /// Best first.
+    players: Vec<PlayerRank>,
+}
+
+/// As last announced by the server.
+#[derive(Resource, Default)]
+pub struct RoundInfo {
+    pub phase: RoundPhase,
+    /// Server time the phase ends at.
+    pub ends_at: Option<f64>,
+    /// Of the last round.
+    pub winners: Vec<PlayerRank>,
 }
 
 mod ui {
     use bevy::{prelude::*, reflect::List};
     use bevy_egui::{EguiContext, EguiContexts, EguiPlugin};
     use egui::{Color32, RichText, Vec2};
-    use example_shared::ClientMessage;
-    use litlnet_client_bevy::{MessagesToSend, RComClient};
+    use example_shared::{ClientMessage, Mole, MAX_NAME_LENGTH};
+    use litlnet_client_bevy::{ConnectionState, MessagesToSend, Reconnection, ServerTime, Session};
 
-    use crate::{ComClient, LocalPlayer, ReconnectState, RemotePlayers, VisualMole};
+    use crate::{LocalPlayer, RemotePlayers, RoundInfo};
+    use example_shared::RoundPhase;
     pub struct GameUI;
 
     impl Plugin for GameUI {
         fn build(&self, app: &mut App) {
             app.add_plugins(EguiPlugin);
-            app.insert_resource(LocalPlayer {
-                name: "Newbie".to_string(),
-                is_final: false,
-                id: None,
-                score: None,
-            });
-            app.insert_resource(RemotePlayers { players: vec![] });
             app.add_systems(Update, show_name);
             app.add_systems(Update, display_connection);
+            app.add_systems(Update, show_round);
+        }
+    }
+
+    fn show_round(
+        mut contexts: EguiContexts,
+        round: Res<RoundInfo>,
+        server_time: Res<ServerTime>,
+        real_time: Res<Time<Real>>,
+    ) {
+        let seconds_left = round
+            .ends_at
+            .zip(server_time.now(&real_time))
+            .map(|(ends_at, now)| (ends_at - now).max(0f64).ceil() as u32);
+        let seconds_left = seconds_left.map_or(String::new(), |s| format!("{}s", s));
+        match round.phase {
+            RoundPhase::Lobby => {
+                egui::Window::new("Round")
+                    .anchor(egui::Align2::CENTER_TOP, Vec2::default())
+                    .show(contexts.ctx_mut(), |ui| {
+                        ui.label("Waiting for players...");
+                    });
+            }
+            RoundPhase::Countdown => {
+                egui::Window::new("Get ready!")
+                    .anchor(egui::Align2::CENTER_CENTER, Vec2::default())
+                    .show(contexts.ctx_mut(), |ui| {
+                        ui.heading(seconds_left);
+                    });
+            }
+            RoundPhase::Playing => {
+                egui::Window::new("Round")
+                    .anchor(egui::Align2::CENTER_TOP, Vec2::default())
+                    .show(contexts.ctx_mut(), |ui| {
+                        ui.label(format!("Ends in {}", seconds_left));
+                    });
+            }
+            RoundPhase::Results => {
+                egui::Window::new("Results")
+                    .anchor(egui::Align2::CENTER_CENTER, Vec2::default())
+                    .show(contexts.ctx_mut(), |ui| {
+                        if round.winners.is_empty() {
+                            ui.label("Nobody whacked a bevy!");
+                        }
+                        for (rank, player) in round.winners.iter().enumerate() {
+                            ui.label(format!("{}. {}: {}", rank + 1, player.name, player.score));
+                        }
+                        ui.label("");
+                        ui.label(format!("Next round in {}", seconds_left));
+                    });
+            }
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn show_name(
+        session: Res<Session>,
         mut send: ResMut<MessagesToSend<ClientMessage>>,
         mut contexts: EguiContexts,
         mut local_player: ResMut<LocalPlayer>,
         remote_players: Res<RemotePlayers>,
-        moles: Query<Entity, With<VisualMole>>,
+        moles: Query<Entity, With<Mole>>,
+        server_time: Res<ServerTime>,
     ) {
         egui::Window::new("Info")
             .fixed_size(Vec2::new(150f32, 400f32))
@@ -120,7 +198,14 @@ mod ui {
                     ui.label(format!("Nickname: {}", local_player.name));
                 } else {
                     ui.text_edit_singleline(&mut local_player.name);
-                    local_player.name = local_player.name.chars().take(6).collect::<String>();
+                    local_player.name = local_player
+                        .name
+                        .chars()
+                        .take(MAX_NAME_LENGTH)
+                        .collect::<String>();
+                    if let Some(rejection) = local_player.name_rejection {
+                        ui.colored_label(Color32::LIGHT_RED, rejection.to_string());
+                    }
                     let is_send_name_clicked =
                         if !local_player.is_final && local_player.name != "Newbie" {
                             ui.button(RichText::new("send nickname").color(Color32::LIGHT_GREEN))
@@ -139,27 +224,41 @@ mod ui {
                     "Number of bevies to whack: {}",
                     moles.iter().count()
                 ));
+                if let Some(rtt) = server_time.rtt {
+                    ui.label(format!("Ping: {} ms", rtt.as_millis()));
+                }
                 ui.label("");
                 ui.label("SCORES:");
                 egui::ScrollArea::vertical().show(ui, |ui| {
-                    let mut current_player_found = false;
-                    for (name, score) in &remote_players.players {
-                        if !current_player_found && name == &local_player.name {
-                            ui.colored_label(Color32::LIGHT_GREEN, format!("{}: {}", name, score));
-                            current_player_found = true;
+                    let local_id = session.id.map(usize::from);
+                    for (i, player) in remote_players.players.iter().enumerate() {
+                        let text = format!("{}. {}: {}", i + 1, player.name, player.score);
+                        if local_id.is_some() && player.player_id == local_id {
+                            ui.colored_label(Color32::LIGHT_GREEN, text);
                         } else {
-                            ui.label(format!("{}: {}", name, score));
+                            ui.label(text);
                         }
                     }
+                    // Out of the top sent to everyone.
+                    if let Some(rank) = local_player
+                        .rank
+                        .filter(|rank| *rank > remote_players.players.len())
+                    {
+                        ui.label("...");
+                        ui.colored_label(
+                            Color32::LIGHT_GREEN,
+                            format!("{}. {}: {}", rank, local_player.name, local_player.score),
+                        );
+                    }
                 });
             });
     }
     fn display_connection(
         mut contexts: EguiContexts,
-        client: Option<Res<RComClient<ComClient>>>,
-        reconnect_state: Res<ReconnectState>,
+        state: Res<ConnectionState>,
+        reconnection: Res<Reconnection>,
     ) {
-        if client.is_some() {
+        if *state == ConnectionState::Connected {
             return;
         }
         egui::Window::new("Connection to server...")
@@ -167,10 +266,9 @@ mod ui {
             .show(contexts.ctx_mut(), |ui| {
                 ui.label(format!(
                     "retrying in {} seconds",
-                    (reconnect_state.timer.duration().as_secs_f32()
-                        - reconnect_state.timer.elapsed_secs()) as i32
+                    reconnection.retry_in().as_secs_f32() as i32
                 ));
-                match reconnect_state.attempt {
+                match reconnection.attempt() {
                     0 => {}
                     1..=2 => {
                         ui.label("Connecting to server...");
@@ -204,6 +302,7 @@ mod ui {
     }
 }
 
+/// The whole game, with a window, the player keeps their account.
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
@@ -216,26 +315,82 @@ impl Plugin for GamePlugin {
             }),
             ..default()
         }));
+        app.add_plugins(GameNetPlugin);
+        app.add_plugins(GameRenderPlugin);
+        app.insert_resource(Identity::load_or_create());
+    }
+}
+
+/// Where [`GameNetPlugin`] pops the server messages, other systems read them before it.
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GameReceiveSet;
+
+/// Connection to the server and game state, runs under `MinimalPlugins` for headless clients.
+///
+/// Hits are sent by pushing [`ClientMessage::hit`] to [`MessagesToSend`], at the current [`ServerTime`].
+/// The player is a guest unless an [`Identity`] is inserted.
+pub struct GameNetPlugin;
+
+impl Plugin for GameNetPlugin {
+    fn build(&self, app: &mut App) {
         app.add_plugins(ClientPlugin::<
             RComClient<ComClient>,
             ClientMessage,
             ServerMessage,
         >::default());
-        app.add_plugins(ui::GameUI);
-        app.insert_resource(WantToRequestExisting::No);
-        app.insert_resource(ReconnectState {
-            timer: Timer::from_seconds(0.01f32, TimerMode::Repeating),
-            attempt: 0,
+        app.replicate::<Mole>();
+        app.replicate_resource::<Scores>();
+        app.replicate_resource::<MoleCatalog>();
+        app.add_plugins(ReconnectPlugin::new(connect).with_backoff(Backoff {
+            initial: std::time::Duration::from_secs(1),
+            max: std::time::Duration::from_secs(20),
+            ..default()
+        }));
+        app.insert_resource(LocalPlayer {
+            name: "Newbie".to_string(),
+            is_final: false,
+            name_rejection: None,
+            rank: None,
+            score: 0,
         });
-        app.insert_resource(AssetsExplosions::default());
+        app.insert_resource(RemotePlayers { players: vec![] });
+        app.init_resource::<RoundInfo>();
         app.add_event::<SpawnExplosionEvent>();
+        app.add_event::<SpawnEscapeEvent>();
+        app.add_event::<MoleDamagedEvent>();
+        app.add_systems(Update, restore_session.run_if(just_connected));
+        // Dead moles are still there, they are despawned with the replicated state.
+        app.add_systems(
+            Update,
+            receive_messages
+                .in_set(GameReceiveSet)
+                .after(ClientSet::Receive),
+        );
+        app.add_systems(Update, update_rankings);
+    }
+}
+
+/// Sprites, UI and mouse input, on top of [`GameNetPlugin`] and `DefaultPlugins`.
+pub struct GameRenderPlugin;
+
+impl Plugin for GameRenderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ui::GameUI);
+        app.insert_resource(AssetsExplosions::default());
         app.add_systems(Startup, setup);
-        app.add_systems(Update, reconnect);
-        app.add_systems(Update, check_request_existing);
         app.add_systems(Update, send_messages);
-        app.add_systems(Update, receive_messages);
+        app.add_systems(Update, (spawn_moles, move_moles).chain());
         app.add_systems(Update, spawn_explosions);
         app.add_systems(Update, explosion_lifecycle);
+        app.add_systems(Update, (spawn_escapes, escape_lifecycle));
+        app.add_systems(
+            Update,
+            (
+                update_hit_count_badges.after(spawn_moles),
+                flash_damaged_moles,
+                damage_flash_lifecycle,
+            ),
+        );
     }
 }
 
@@ -245,10 +400,6 @@ fn setup(
     mut explosions: ResMut<AssetsExplosions>,
 ) {
     commands.spawn(Camera2dBundle::default()).insert(MainCamera);
-    let sprite_handle = assets.load("players/icon_bevy.png");
-    commands.insert_resource(AssetsVisualPlayer {
-        sprite_handles: vec![sprite_handle],
-    });
     explosions.explosion_local = vec![
         assets.load("explosions/local_1.png"),
         assets.load("explosions/local_2.png"),
@@ -261,56 +412,28 @@ fn setup(
     ];
 }
 
-fn reconnect(
-    time: Res<Time>,
-    mut commands: Commands,
-    client: Option<ResMut<RComClient<ComClient>>>,
-    mut want_request_existing: ResMut<WantToRequestExisting>,
-    mut reconnect_state: ResMut<ReconnectState>,
-) {
-    if client.is_some() {
-        return;
-    }
-    reconnect_state.timer.tick(time.delta());
-    if !reconnect_state.timer.just_finished() {
-        return;
-    }
-    let current_duration = reconnect_state.timer.duration().as_secs_f32();
-    reconnect_state
-        .timer
-        .set_duration(std::time::Duration::from_secs_f32(current_duration + 2f32));
+fn connect() -> Result<RComClient<ComClient>, std::io::Error> {
     #[cfg(target_arch = "wasm32")]
     let server_url = option_env!("WEB_SERVER_URL").unwrap_or("ws://127.0.0.1:8083");
     #[cfg(not(target_arch = "wasm32"))]
     let server_url =
         &std::env::var("WEB_SERVER_URL").unwrap_or_else(|_| "ws://127.0.0.1:8083".to_string());
-    reconnect_state.attempt += 1;
-    if let Ok(ws) = ComClient::connect(server_url) {
-        commands.insert_resource(RComClient { com: ws });
-        reconnect_state.attempt = 0;
-        reconnect_state
-            .timer
-            .set_duration(std::time::Duration::from_secs_f32(2f32));
-        *want_request_existing =
-            WantToRequestExisting::Yes(Timer::from_seconds(0.5f32, TimerMode::Once))
-    }
+    // Snapshots of moles and scores are repetitive JSON, worth compressing for mobile players.
+    ComClient::connect_with_compression(
+        server_url,
+        DecodeLimits::default(),
+        Some(Compression::default()),
+    )
+    .map(|com| RComClient { com })
 }
 
-fn check_request_existing(
-    time: Res<Time>,
+/// The session is not resumed if the server restarted, or if the client was away for too long.
+fn restore_session(
+    local_player: Res<LocalPlayer>,
     mut send: ResMut<MessagesToSend<ClientMessage>>,
-    mut want_request_existing: ResMut<WantToRequestExisting>,
 ) {
-    match want_request_existing.as_mut() {
-        WantToRequestExisting::Yes(ref mut timer) => {
-            timer.tick(time.delta());
-            if timer.just_finished() {
-                send.push(ClientMessage::RequestAllExistingMoles);
-                timer.set_duration(std::time::Duration::from_secs_f32(2.5f32));
-                timer.reset();
-            }
-        }
-        _ => (),
+    if local_player.is_final {
+        send.push(ClientMessage::SetName(local_player.name.clone()));
     }
 }
 
@@ -321,6 +444,8 @@ fn send_messages(
     q_window: Query<&Window, With<PrimaryWindow>>,
     // query to get camera transform
     q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    server_time: Res<ServerTime>,
+    real_time: Res<Time<Real>>,
 ) {
     if buttons.just_pressed(MouseButton::Left) {
         // get the camera info and transform
@@ -330,101 +455,201 @@ fn send_messages(
         // There is only one primary window, so we can similarly get it from the query:
         let window = q_window.single();
         if let Some(world_position) = cheatbook::cursor_to_world(window, q_camera.single()) {
-            send.push(ClientMessage::HitPosition(Vec2::new(
-                world_position.x,
-                world_position.y,
-            )));
+            // The server checks it against the moles as they were shown.
+            send.push(ClientMessage::hit(
+                Vec2::new(world_position.x, world_position.y),
+                server_time.now(&real_time),
+            ));
         }
     }
 }
+#[allow(clippy::too_many_arguments)]
 fn receive_messages(
-    mut commands: Commands,
-    sprites: Res<AssetsVisualPlayer>,
+    session: Res<Session>,
+    mut local_player: ResMut<LocalPlayer>,
     mut spawn_explosions_events: EventWriter<SpawnExplosionEvent>,
-    mut want_request_existing: ResMut<WantToRequestExisting>,
+    mut spawn_escapes_events: EventWriter<SpawnEscapeEvent>,
+    mut mole_damaged_events: EventWriter<MoleDamagedEvent>,
     mut recv: ResMut<MessagesToRead<ServerMessage>>,
-    mut moles: Query<(Entity, &Transform, &VisualMole)>,
-    mut local_player: ResMut<LocalPlayer>,
-    mut rankings: ResMut<RemotePlayers>,
+    server_time: Res<ServerTime>,
+    real_time: Res<Time<Real>>,
+    mut round: ResMut<RoundInfo>,
+    mut identity: Option<ResMut<Identity>>,
+    mut send: ResMut<MessagesToSend<ClientMessage>>,
+    moles: Query<&Mole>,
 ) {
     while let Some(message) = recv.pop() {
         match message {
-            ServerMessage::Spawn(spawn) => {
-                dbg!("new mole: {}", &spawn);
-                spawn_mole(&mut commands, &sprites, spawn);
-            }
             ServerMessage::DeadMole {
                 mole_id: dead_id,
                 player_killer_id,
             } => {
                 dbg!("?dead mole: {}", dead_id);
-                for (e, t, v) in moles.iter() {
-                    if v.id == dead_id {
-                        if local_player.id.is_some() && local_player.id.unwrap() == player_killer_id
-                        {
+                for mole in moles.iter() {
+                    if mole.id == dead_id {
+                        if session.id.is_some() && session.id.unwrap().0 == player_killer_id {
                             spawn_explosions_events.send(SpawnExplosionEvent {
                                 kind: ExplosionKind::LocalPlayer,
-                                position: t.translation.xy(),
+                                position: mole_position(mole, &server_time, &real_time),
                             });
                         } else {
                             spawn_explosions_events.send(SpawnExplosionEvent {
                                 kind: ExplosionKind::RemotePlayer,
-                                position: t.translation.xy(),
+                                position: mole_position(mole, &server_time, &real_time),
                             });
                         }
                         dbg!("dead mole: {}", dead_id);
-                        commands.entity(e).despawn();
                         break;
                     }
                 }
             }
-            ServerMessage::EscapedMole(_) => {
-                todo!("escaped mole");
+            ServerMessage::RoundState { phase, ends_at } => {
+                round.phase = phase;
+                round.ends_at = ends_at;
+            }
+            ServerMessage::RoundWinners(winners) => round.winners = winners,
+            ServerMessage::NameAccepted(name) => {
+                local_player.name = name;
+                local_player.name_rejection = None;
+            }
+            ServerMessage::NameRejected(rejection) => {
+                local_player.is_final = false;
+                local_player.name_rejection = Some(rejection);
+            }
+            ServerMessage::Challenge(challenge) => {
+                if let Some(message) = identity.as_ref().and_then(|i| i.answer(&challenge)) {
+                    send.push(message);
+                }
             }
-            ServerMessage::AllExistingMoles(AllExistingMoles {
-                local_player_id,
-                moles: existing_moles,
-            }) => {
-                for (e, _, _) in moles.iter() {
-                    commands.entity(e).despawn();
+            ServerMessage::Authenticated { account, name } => {
+                if let Some(identity) = identity.as_mut() {
+                    if identity.account != Some(account) {
+                        identity.account = Some(account);
+                        identity.save();
+                    }
                 }
-                local_player.id = Some(local_player_id);
-                dbg!("{} existing moles", existing_moles.len());
-                for m in existing_moles {
-                    spawn_mole(&mut commands, &sprites, m);
+                if let Some(name) = name {
+                    local_player.name = name;
+                    local_player.is_final = true;
+                    local_player.name_rejection = None;
                 }
-                *want_request_existing = WantToRequestExisting::No;
             }
-            ServerMessage::UpdateScores(update) => {
-                rankings.players = update
-                    .best_players
-                    .iter()
-                    .map(|v| (v.name.clone(), v.score as u32))
-                    .collect();
-                rankings
-                    .players
-                    .sort_by(|p1, p2| p2.1.partial_cmp(&p1.1).unwrap());
+            // The server lost the account, it's registered again with the next challenge.
+            ServerMessage::AuthenticationFailed => {
+                if let Some(identity) = identity.as_mut() {
+                    if identity.account.take().is_some() {
+                        identity.save();
+                    }
+                }
+            }
+            ServerMessage::OwnRank { rank, score } => {
+                local_player.rank = rank;
+                local_player.score = score;
+            }
+            ServerMessage::MoleDamaged { mole_id, by, .. } => {
+                // The remaining hits come with the replicated mole.
+                mole_damaged_events.send(MoleDamagedEvent {
+                    mole_id,
+                    kind: if session.id.is_some_and(|id| id.0 == by) {
+                        ExplosionKind::LocalPlayer
+                    } else {
+                        ExplosionKind::RemotePlayer
+                    },
+                });
+            }
+            // Moles and scores are replicated.
+            ServerMessage::Spawn(_) | ServerMessage::UpdateScores(_) => {}
+            ServerMessage::EscapedMole(escaped_id) => {
+                if let Some(mole) = moles.iter().find(|mole| mole.id == escaped_id) {
+                    spawn_escapes_events.send(SpawnEscapeEvent {
+                        position: mole_position(mole, &server_time, &real_time),
+                        catalog_id: mole.def.catalog_id,
+                    });
+                }
             }
         }
     }
 }
 
-fn spawn_mole(
-    commands: &mut Commands,
-    sprites: &Res<AssetsVisualPlayer>,
-    spawn: example_shared::SpawnMole,
+fn update_rankings(scores: Option<Res<Scores>>, mut rankings: ResMut<RemotePlayers>) {
+    if let Some(scores) = scores.filter(|scores| scores.is_changed()) {
+        rankings.players = scores.best_players.clone();
+    }
+}
+
+/// From the replicated catalog, the default kind until it's received.
+fn mole_kind(catalog: Option<&MoleCatalog>, catalog_id: usize) -> MoleKindDef {
+    catalog
+        .and_then(|catalog| catalog.get(catalog_id))
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn tint(kind: &MoleKindDef) -> Color {
+    Color::rgb(kind.tint[0], kind.tint[1], kind.tint[2])
+}
+
+/// Where the mole is now, at the start of its path until the server clock is known.
+fn mole_position(mole: &Mole, server_time: &ServerTime, real_time: &Time<Real>) -> Vec2 {
+    mole.def
+        .position_at(server_time.now(real_time).unwrap_or(mole.def.start_time))
+}
+
+fn move_moles(
+    server_time: Res<ServerTime>,
+    real_time: Res<Time<Real>>,
+    mut moles: Query<(&Mole, &mut Transform)>,
+) {
+    for (mole, mut transform) in moles.iter_mut() {
+        let position = mole_position(mole, &server_time, &real_time);
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+/// Visuals of moles spawned by replication.
+fn spawn_moles(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    catalog: Option<Res<MoleCatalog>>,
+    server_time: Res<ServerTime>,
+    real_time: Res<Time<Real>>,
+    moles: Query<(Entity, &Mole), Added<Mole>>,
 ) {
-    commands
-        .spawn(SpriteBundle {
-            texture: sprites.sprite_handles[0].clone(),
-            transform: Transform::from_translation(spawn.def.position.extend(0f32)),
+    for (entity, mole) in moles.iter() {
+        dbg!("new mole: {}", mole);
+        let kind = mole_kind(catalog.as_deref(), mole.def.catalog_id);
+        commands.entity(entity).insert(SpriteBundle {
+            texture: assets.load(kind.sprite.clone()),
+            transform: Transform::from_translation(
+                mole_position(mole, &server_time, &real_time).extend(0f32),
+            ),
             sprite: Sprite {
-                custom_size: Some(Vec2::splat(64.0)),
+                custom_size: Some(Vec2::splat(kind.size)),
+                color: tint(&kind),
                 ..Default::default()
             },
             ..Default::default()
-        })
-        .insert(VisualMole { id: spawn.id });
+        });
+        if let MoleKind::HitCount(_) = mole.def.kind {
+            commands.entity(entity).with_children(|parent| {
+                parent.spawn((
+                    HitCountBadge,
+                    Text2dBundle {
+                        text: Text::from_section(
+                            "",
+                            TextStyle {
+                                font_size: 24f32,
+                                color: Color::WHITE,
+                                ..default()
+                            },
+                        ),
+                        transform: Transform::from_xyz(kind.size * 0.375, kind.size * 0.375, 1f32),
+                        ..default()
+                    },
+                ));
+            });
+        }
+    }
 }
 
 fn spawn_explosions(
@@ -486,3 +711,100 @@ fn explosion_lifecycle(
         }
     }
 }
+
+fn spawn_escapes(
+    mut commands: Commands,
+    mut events: EventReader<SpawnEscapeEvent>,
+    assets: Res<AssetServer>,
+    catalog: Option<Res<MoleCatalog>>,
+) {
+    for event in events.read() {
+        let kind = mole_kind(catalog.as_deref(), event.catalog_id);
+        commands
+            .spawn(EscapeData {
+                timer: Timer::from_seconds(0.4, TimerMode::Once),
+                from: event.position,
+            })
+            .insert(SpriteBundle {
+                texture: assets.load(kind.sprite),
+                transform: Transform::from_translation(event.position.extend(5f32)),
+                sprite: Sprite {
+                    custom_size: Some(Vec2::splat(kind.size)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+    }
+}
+
+/// Shrinks, sinks and fades the escaped mole, then despawns it.
+fn escape_lifecycle(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_escapes: Query<(Entity, &mut EscapeData, &mut Transform, &mut Sprite)>,
+) {
+    for (e, mut escape, mut transform, mut sprite) in q_escapes.iter_mut() {
+        escape.timer.tick(time.delta());
+        if escape.timer.finished() {
+            commands.entity(e).despawn();
+            continue;
+        }
+        let left = 1f32 - escape.timer.fraction();
+        transform.translation = (escape.from - Vec2::new(0f32, 32f32 * (1f32 - left))).extend(5f32);
+        transform.scale = Vec3::new(left, left, 1f32);
+        sprite.color = Color::rgba(0.5, 0.5, 0.5, left);
+    }
+}
+
+fn update_hit_count_badges(
+    moles: Query<(&Mole, &Children), Changed<Mole>>,
+    mut badges: Query<&mut Text, With<HitCountBadge>>,
+) {
+    for (mole, children) in moles.iter() {
+        if let MoleKind::HitCount(remaining) = mole.def.kind {
+            for child in children.iter() {
+                if let Ok(mut text) = badges.get_mut(*child) {
+                    text.sections[0].value = remaining.to_string();
+                }
+            }
+        }
+    }
+}
+
+fn flash_damaged_moles(
+    mut commands: Commands,
+    mut events: EventReader<MoleDamagedEvent>,
+    catalog: Option<Res<MoleCatalog>>,
+    moles: Query<(Entity, &Mole)>,
+) {
+    for event in events.read() {
+        if let Some((entity, mole)) = moles.iter().find(|(_, mole)| mole.id == event.mole_id) {
+            commands.entity(entity).insert(DamageFlash {
+                tint: tint(&mole_kind(catalog.as_deref(), mole.def.catalog_id)),
+                timer: Timer::from_seconds(0.25, TimerMode::Once),
+                color: if event.kind == ExplosionKind::LocalPlayer {
+                    Color::ORANGE_RED
+                } else {
+                    Color::PURPLE
+                },
+            });
+        }
+    }
+}
+
+fn damage_flash_lifecycle(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_flashes: Query<(Entity, &mut DamageFlash, &mut Sprite)>,
+) {
+    for (e, mut flash, mut sprite) in q_flashes.iter_mut() {
+        flash.timer.tick(time.delta());
+        if flash.timer.finished() {
+            sprite.color = flash.tint;
+            commands.entity(e).remove::<DamageFlash>();
+            continue;
+        }
+        let left = 1f32 - flash.timer.fraction();
+        sprite.color = flash.color * left + flash.tint * (1f32 - left);
+    }
+}
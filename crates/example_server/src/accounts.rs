@@ -0,0 +1,182 @@
+use bevy::{prelude::*, utils::HashMap};
+use example_shared::{to_hex, verify_challenge, ServerMessage};
+use litlnet_server_bevy::{MessagesToSend, SessionEvent};
+use litlnet_trait::ClientId;
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{PlayersNames, PlayersRanking, ServerConfig};
+
+/// Scores of an account kept in [`Account::rounds`].
+const ROUNDS: usize = 100;
+
+/// A player known by a key rather than by name, saved with the leaderboards.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Account {
+    /// Ed25519, in hex, the client proves it has the secret key by signing challenges.
+    pub public_key: String,
+    /// Reserved to the account, even when it's not playing.
+    pub name: Option<String>,
+    /// Of the last rounds it scored in, oldest first.
+    pub rounds: Vec<RoundScore>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RoundScore {
+    pub number: u32,
+    pub score: usize,
+}
+
+impl Account {
+    pub fn add_round(&mut self, number: u32, score: usize) {
+        self.rounds.push(RoundScore { number, score });
+        if self.rounds.len() > ROUNDS {
+            self.rounds.drain(..self.rounds.len() - ROUNDS);
+        }
+    }
+}
+
+/// Key of an account in the leaderboards, guests can't have `#` in their name.
+pub fn account_key(account: u64) -> String {
+    format!("#{}", account)
+}
+
+pub fn parse_account_key(key: &str) -> Option<u64> {
+    key.strip_prefix('#')?.parse().ok()
+}
+
+/// Sessions playing as an account, and the challenges of the others.
+#[derive(Resource, Default)]
+pub struct Authentication {
+    pub accounts: HashMap<ClientId, u64>,
+    challenges: HashMap<ClientId, String>,
+    /// Accounts by public key.
+    public_keys: HashMap<String, u64>,
+}
+
+impl Authentication {
+    /// Of the saved `accounts`.
+    pub fn new(accounts: &HashMap<u64, Account>) -> Self {
+        let public_keys = accounts
+            .iter()
+            .map(|(id, account)| (account.public_key.clone(), *id))
+            .collect();
+        Self {
+            public_keys,
+            ..Default::default()
+        }
+    }
+
+    /// A new challenge for the session, the last one can't be answered anymore.
+    pub(crate) fn challenge(&mut self, client_id: ClientId) -> ServerMessage {
+        let challenge = to_hex(&thread_rng().gen::<[u8; 16]>());
+        self.challenges.insert(client_id, challenge.clone());
+        ServerMessage::Challenge(challenge)
+    }
+}
+
+pub(crate) fn send_challenges(
+    config: Res<ServerConfig>,
+    mut events: EventReader<SessionEvent>,
+    mut authentication: ResMut<Authentication>,
+    mut send: ResMut<MessagesToSend<ServerMessage>>,
+) {
+    for event in events.read() {
+        match event {
+            SessionEvent::Started(client_id) if config.accounts => {
+                send.push((*client_id, authentication.challenge(*client_id)));
+            }
+            SessionEvent::Ended(client_id) => {
+                authentication.accounts.remove(client_id);
+                authentication.challenges.remove(client_id);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Creates the account of `public_key`, if the session signed its challenge with the secret key.
+///
+/// Known keys only sign in with [`authenticate`].
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn register(
+    client_id: ClientId,
+    public_key: &str,
+    signature: &str,
+    config: &ServerConfig,
+    authentication: &mut Authentication,
+    ranking: &mut ResMut<PlayersRanking>,
+    player_names: &mut PlayersNames,
+) -> ServerMessage {
+    let public_key = public_key.to_ascii_lowercase();
+    let challenge = authentication.challenges.remove(&client_id);
+    let is_signed =
+        challenge.is_some_and(|challenge| verify_challenge(&public_key, &challenge, signature));
+    if !config.accounts || !is_signed || authentication.public_keys.contains_key(&public_key) {
+        return ServerMessage::AuthenticationFailed;
+    }
+    let boards = &mut ranking.boards;
+    let account = boards.next_account;
+    boards.next_account += 1;
+    boards.accounts.insert(
+        account,
+        Account {
+            public_key: public_key.clone(),
+            ..Default::default()
+        },
+    );
+    authentication.public_keys.insert(public_key, account);
+    sign_in(client_id, account, authentication, ranking, player_names)
+}
+
+/// Checks `signature` against the challenge sent to the session, which can only be answered once.
+pub(crate) fn authenticate(
+    client_id: ClientId,
+    account: u64,
+    signature: &str,
+    authentication: &mut Authentication,
+    ranking: &mut ResMut<PlayersRanking>,
+    player_names: &mut PlayersNames,
+) -> ServerMessage {
+    let challenge = authentication.challenges.remove(&client_id);
+    let public_key = ranking
+        .boards
+        .accounts
+        .get(&account)
+        .map(|a| a.public_key.as_str());
+    match (challenge, public_key) {
+        (Some(challenge), Some(public_key))
+            if verify_challenge(public_key, &challenge, signature) =>
+        {
+            sign_in(client_id, account, authentication, ranking, player_names)
+        }
+        _ => ServerMessage::AuthenticationFailed,
+    }
+}
+
+fn sign_in(
+    client_id: ClientId,
+    account: u64,
+    authentication: &mut Authentication,
+    ranking: &mut ResMut<PlayersRanking>,
+    player_names: &mut PlayersNames,
+) -> ServerMessage {
+    authentication.challenges.remove(&client_id);
+    authentication.accounts.insert(client_id, account);
+    let name = match &ranking.boards.accounts[&account].name {
+        Some(name) => {
+            player_names.names.insert(client_id, name.clone());
+            Some(name.clone())
+        }
+        // Claims the name chosen as a guest, it can't be owned by another account.
+        None => {
+            let name = player_names.names.get(&client_id).cloned();
+            if name.is_some() {
+                ranking.boards.accounts.get_mut(&account).unwrap().name = name.clone();
+            }
+            name
+        }
+    };
+    ServerMessage::Authenticated { account, name }
+}
@@ -0,0 +1,145 @@
+use std::fs;
+
+use bevy::{prelude::*, utils::HashMap};
+use example_shared::{MoleCatalog, MoleKindDef, Movement};
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+use serde::Deserialize;
+
+/// Kinds of moles and when to spawn them, from a TOML file.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Catalog {
+    pub kinds: Vec<MoleKindDef>,
+    #[serde(default)]
+    pub timeline: Vec<SpawnTable>,
+}
+
+/// Weights of the kinds, by name, from `from` seconds of the round until the next table.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnTable {
+    pub from: f32,
+    #[serde(default)]
+    pub weights: HashMap<String, f32>,
+}
+
+impl Default for Catalog {
+    fn default() -> Self {
+        Self::from_toml(include_str!("../assets/moles.toml")).expect("built-in catalog")
+    }
+}
+
+impl Catalog {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let toml = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::from_toml(&toml).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Self, String> {
+        let catalog: Self = toml::from_str(toml).map_err(|e| e.to_string())?;
+        catalog.validate()?;
+        Ok(catalog)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.kinds.is_empty() {
+            return Err("no kinds of moles".to_string());
+        }
+        for (i, kind) in self.kinds.iter().enumerate() {
+            if self.kinds[..i].iter().any(|other| other.name == kind.name) {
+                return Err(format!("kind {:?} is defined twice", kind.name));
+            }
+            if kind.hits == 0 {
+                return Err(format!("kind {:?} needs at least 1 hit", kind.name));
+            }
+            if !kind.weight.is_finite() || kind.weight < 0f32 {
+                return Err(format!("kind {:?} has an invalid weight", kind.name));
+            }
+            let sizes = [
+                ("size", kind.size),
+                ("hit_radius", kind.hit_radius),
+                ("lifetime", kind.lifetime),
+            ];
+            for (key, value) in sizes {
+                if !value.is_finite() || value <= 0f32 {
+                    return Err(format!("kind {:?} needs a positive {}", kind.name, key));
+                }
+            }
+            let (durations, distances) = match kind.movement {
+                Movement::Still => (vec![], vec![]),
+                Movement::Linear { distance, period } => (vec![period], vec![distance]),
+                Movement::Circle { radius, period } => (vec![period], vec![radius]),
+                Movement::Hop {
+                    distance, interval, ..
+                } => (vec![interval], vec![distance]),
+                Movement::Flee {
+                    radius,
+                    distance,
+                    duration,
+                    ..
+                } => (vec![duration], vec![radius, distance]),
+            };
+            if durations
+                .iter()
+                .any(|duration| !duration.is_finite() || *duration <= 0f32)
+            {
+                return Err(format!("kind {:?} moves in no time", kind.name));
+            }
+            if distances
+                .iter()
+                .any(|distance| !distance.is_finite() || *distance < 0f32)
+            {
+                return Err(format!("kind {:?} moves a negative distance", kind.name));
+            }
+            if let Movement::Flee { hits, window, .. } = kind.movement {
+                if hits == 0 || !window.is_finite() || window < 0f32 {
+                    return Err(format!("kind {:?} flees from no hits", kind.name));
+                }
+            }
+        }
+        for (i, table) in self.timeline.iter().enumerate() {
+            if i > 0 && table.from < self.timeline[i - 1].from {
+                return Err(format!("spawn table from {} is not in order", table.from));
+            }
+            for (name, weight) in &table.weights {
+                if !self.kinds.iter().any(|kind| &kind.name == name) {
+                    return Err(format!(
+                        "spawn table from {}: unknown kind {:?}",
+                        table.from, name
+                    ));
+                }
+                if !weight.is_finite() || *weight < 0f32 {
+                    return Err(format!("spawn table from {}: invalid weight", table.from));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The spawn tables, and how far in them the round is.
+#[derive(Resource)]
+pub struct SpawnTimeline {
+    pub tables: Vec<SpawnTable>,
+    pub elapsed: f32,
+}
+
+impl SpawnTimeline {
+    /// Index in the catalog of the kind to spawn, `None` if all weights are 0.
+    pub fn pick(&self, catalog: &MoleCatalog, random: &mut impl Rng) -> Option<usize> {
+        let table = self
+            .tables
+            .iter()
+            .rev()
+            .find(|table| table.from <= self.elapsed);
+        let weights = catalog.kinds.iter().map(|kind| {
+            table
+                .and_then(|table| table.weights.get(&kind.name))
+                .copied()
+                .unwrap_or(kind.weight)
+        });
+        WeightedIndex::new(weights)
+            .ok()
+            .map(|index| index.sample(random))
+    }
+}
@@ -0,0 +1,202 @@
+use std::fs;
+
+use bevy::prelude::*;
+use litlnet_trait::DecodeLimits;
+use serde::Deserialize;
+use toml::{de::ValueDeserializer, Table, Value};
+
+/// Who is credited the kill of a mole hit by several players.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KillCredit {
+    LastHit,
+    /// Its points are split between the players who hit it, by their number of hits.
+    /// Scores are whole numbers, the points left go to the players who hit it first.
+    Contributors,
+}
+
+/// Tuning of the game and the server, from a TOML file and `--key value` flags.
+///
+/// Flags override the file, `-` in their name is read as `_`: `--hit-radius 40`.
+#[derive(Resource, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind_address: String,
+    pub port: u16,
+    /// Random if not set.
+    pub seed: Option<u64>,
+    /// Players connected for a round to start.
+    pub min_players: usize,
+    /// Seconds of each phase of a round.
+    pub countdown_duration: f32,
+    pub round_duration: f32,
+    pub results_duration: f32,
+    /// Seconds between two moles.
+    pub spawn_interval: f32,
+    pub max_moles: usize,
+    /// TOML file of the kinds of moles and spawn tables, the built-in one if not set.
+    pub catalog: Option<String>,
+    pub kill_credit: KillCredit,
+    /// Seconds a hit can be checked in the past, at the time the player saw the moles.
+    pub max_rewind: f32,
+    /// Whether players can authenticate to keep their name and scores, saved with the leaderboards.
+    pub accounts: bool,
+    /// Names containing one of them, in any case, are rejected.
+    pub banned_words: Vec<String>,
+    /// JSON file the leaderboards are kept in, in memory only if not set.
+    pub leaderboard: Option<String>,
+    /// Seconds between two saves of the leaderboards, they are also saved on shutdown.
+    pub leaderboard_flush_interval: f32,
+    /// Moles spawn in this rectangle around `spawn_offset`.
+    pub spawn_area_radius: Vec2,
+    pub spawn_offset: Vec2,
+    /// Seconds a disconnected player can come back as the same session.
+    pub session_grace: f32,
+    /// Seconds between two snapshots of the moles and scores.
+    pub replication_interval: f32,
+    pub max_clients: Option<usize>,
+    pub max_connections_per_ip: Option<usize>,
+    pub max_accepts_per_second: Option<f32>,
+    pub max_message_size: usize,
+    pub max_depth: usize,
+    /// Only used with clients offering it.
+    pub compression: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: "0.0.0.0".to_string(),
+            port: 8083,
+            seed: None,
+            min_players: 1,
+            countdown_duration: 3f32,
+            round_duration: 120f32,
+            results_duration: 10f32,
+            spawn_interval: 0.5f32,
+            max_moles: 50,
+            catalog: None,
+            kill_credit: KillCredit::LastHit,
+            // Covers the round trip of most players, later hits are checked as if seen then.
+            max_rewind: 0.25f32,
+            accounts: true,
+            banned_words: vec![],
+            leaderboard: None,
+            leaderboard_flush_interval: 30f32,
+            // resolution of client ("optimized" for itch.io embed rendering)
+            spawn_area_radius: Vec2::new(300f32, 150f32),
+            spawn_offset: Vec2::new(100f32, 0f32),
+            // Long enough for a mobile player to get their connection back.
+            session_grace: 60f32,
+            replication_interval: 0.1f32,
+            // fly.io VM has 256MB of memory.
+            max_clients: Some(200),
+            max_connections_per_ip: Some(8),
+            max_accepts_per_second: Some(20f32),
+            // Client messages are tiny, the biggest is a 6 characters name.
+            max_message_size: 4 * 1024,
+            max_depth: 8,
+            compression: true,
+        }
+    }
+}
+
+pub const USAGE: &str = "usage: example_server [--config <file.toml>] [--<key> <value>]...
+keys are the fields of ServerConfig, values are TOML: --port 9000 --spawn-area-radius [200,100]";
+
+impl ServerConfig {
+    /// `args` without the program name.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut file = None;
+        let mut flags = Table::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let key = match arg.strip_prefix("--") {
+                Some(key) => key.replace('-', "_"),
+                None => return Err(format!("unexpected argument {}", arg)),
+            };
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for --{}", key))?;
+            if key == "config" {
+                file = Some(value);
+            } else {
+                // Not TOML, like an address, is a string.
+                let value = Value::deserialize(ValueDeserializer::new(&value))
+                    .unwrap_or(Value::String(value));
+                flags.insert(key, value);
+            }
+        }
+        let mut config = match file {
+            Some(path) => {
+                let toml = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+                toml.parse::<Table>()
+                    .map_err(|e| format!("{}: {}", path, e))?
+            }
+            None => Table::new(),
+        };
+        config.extend(flags);
+        Self::from_table(config)
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Self, String> {
+        Self::from_table(toml.parse().map_err(|e: toml::de::Error| e.to_string())?)
+    }
+
+    fn from_table(config: Table) -> Result<Self, String> {
+        let config: Self = Value::Table(config).try_into().map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Values the game would panic on.
+    fn validate(&self) -> Result<(), String> {
+        let values = [
+            ("countdown_duration", self.countdown_duration),
+            ("round_duration", self.round_duration),
+            ("results_duration", self.results_duration),
+            ("spawn_interval", self.spawn_interval),
+            ("max_rewind", self.max_rewind),
+            (
+                "leaderboard_flush_interval",
+                self.leaderboard_flush_interval,
+            ),
+            ("session_grace", self.session_grace),
+            ("replication_interval", self.replication_interval),
+            ("spawn_area_radius", self.spawn_area_radius.x),
+            ("spawn_area_radius", self.spawn_area_radius.y),
+            (
+                "max_accepts_per_second",
+                self.max_accepts_per_second.unwrap_or_default(),
+            ),
+        ];
+        for (key, value) in values {
+            if !value.is_finite() || value < 0f32 {
+                return Err(format!("{} must be a positive number", key));
+            }
+        }
+        // Nothing would ever spawn or connect.
+        let rates = [
+            ("spawn_interval", Some(self.spawn_interval)),
+            ("max_accepts_per_second", self.max_accepts_per_second),
+        ];
+        for (key, value) in rates {
+            if value == Some(0f32) {
+                return Err(format!("{} must not be 0", key));
+            }
+        }
+        Ok(())
+    }
+
+    /// What clients' packets are received with.
+    pub fn decode_limits(&self) -> DecodeLimits {
+        DecodeLimits {
+            max_message_size: self.max_message_size,
+            max_depth: self.max_depth,
+        }
+    }
+
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.bind_address, self.port)
+    }
+}
@@ -0,0 +1,268 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, SyncSender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{app::AppExit, prelude::*, utils::HashMap};
+use example_shared::{Board, PlayerRank, TopPlayers};
+use litlnet_trait::ClientId;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accounts::{parse_account_key, Account},
+    sort_ranks, PlayersRanking,
+};
+
+/// Rounds kept in [`Leaderboards::rounds`].
+const ROUNDS: usize = 100;
+/// Most players answered to a [`TopPlayers`] request.
+const MAX_TOP_PLAYERS: usize = 100;
+
+/// Scores by guest name or [`account_key`](crate::account_key), kept across restarts.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Leaderboards {
+    pub accounts: HashMap<u64, Account>,
+    pub next_account: u64,
+    pub all_time: HashMap<String, usize>,
+    /// Days since the unix epoch, in UTC, of `daily`.
+    pub day: u64,
+    pub daily: HashMap<String, usize>,
+    /// Of the last rounds played, oldest first.
+    pub rounds: Vec<RoundBoard>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RoundBoard {
+    pub number: u32,
+    pub ranks: HashMap<String, usize>,
+}
+
+impl Leaderboards {
+    pub fn credit(&mut self, key: &str, points: usize, day: u64) {
+        if self.day != day {
+            self.day = day;
+            self.daily.clear();
+        }
+        *self.all_time.entry(key.to_string()).or_insert(0) += points;
+        *self.daily.entry(key.to_string()).or_insert(0) += points;
+    }
+
+    pub fn end_round(&mut self, number: u32, ranks: HashMap<String, usize>) {
+        for (key, score) in &ranks {
+            let account = parse_account_key(key).and_then(|id| self.accounts.get_mut(&id));
+            if let Some(account) = account {
+                account.add_round(number, *score);
+            }
+        }
+        self.rounds.push(RoundBoard { number, ranks });
+        if self.rounds.len() > ROUNDS {
+            self.rounds.drain(..self.rounds.len() - ROUNDS);
+        }
+    }
+
+    /// Number of the last round played, 0 before the first one.
+    pub fn last_round(&self) -> u32 {
+        self.rounds.last().map_or(0, |round| round.number)
+    }
+
+    /// The best `count` players of `board`, on `day` for [`Board::Daily`].
+    pub fn top(&self, board: Board, count: usize, day: u64) -> Vec<PlayerRank> {
+        let ranks = match board {
+            Board::AllTime => Some(&self.all_time),
+            Board::Daily => (self.day == day).then_some(&self.daily),
+            Board::LastRound => self.rounds.last().map(|round| &round.ranks),
+            Board::Round(number) => self
+                .rounds
+                .iter()
+                .find(|round| round.number == number)
+                .map(|round| &round.ranks),
+        };
+        let mut players: Vec<_> = ranks
+            .into_iter()
+            .flatten()
+            .map(|(key, score)| PlayerRank {
+                player_id: None,
+                name: self.name(key),
+                score: *score,
+            })
+            .collect();
+        sort_ranks(&mut players);
+        players.truncate(count);
+        players
+    }
+
+    /// Of a key of the boards, the one of an account is its name.
+    pub fn name(&self, key: &str) -> String {
+        parse_account_key(key)
+            .and_then(|id| self.accounts.get(&id))
+            .and_then(|account| account.name.clone())
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    /// Owned by accounts other than `account`.
+    pub fn owned_names(&self, account: Option<u64>) -> impl Iterator<Item = &str> {
+        self.accounts
+            .iter()
+            .filter(move |(id, _)| Some(**id) != account)
+            .filter_map(|(_, account)| account.name.as_deref())
+    }
+}
+
+/// Days since the unix epoch, in UTC.
+pub fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() / (24 * 60 * 60))
+}
+
+/// Where the leaderboards are kept between restarts of the server.
+pub trait LeaderboardStore: Send + Sync {
+    /// Empty boards if none were saved yet.
+    fn load(&mut self) -> Result<Leaderboards, String>;
+    fn save(&mut self, boards: &Leaderboards) -> Result<(), String>;
+}
+
+pub struct JsonFileStore {
+    path: PathBuf,
+}
+
+impl JsonFileStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl LeaderboardStore for JsonFileStore {
+    fn load(&mut self) -> Result<Leaderboards, String> {
+        let json = match fs::read_to_string(&self.path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Leaderboards::default()),
+            Err(e) => return Err(format!("{}: {}", self.path.display(), e)),
+        };
+        serde_json::from_str(&json).map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+
+    fn save(&mut self, boards: &Leaderboards) -> Result<(), String> {
+        let json = serde_json::to_string(boards).map_err(|e| e.to_string())?;
+        // Renamed over the last save, which stays whole if the server dies while writing.
+        let temporary = self.path.with_extension("tmp");
+        fs::write(&temporary, json)
+            .and_then(|_| fs::rename(&temporary, &self.path))
+            .map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+}
+
+/// Clones share the same boards.
+#[derive(Clone, Default)]
+pub struct MemoryStore {
+    pub boards: Arc<Mutex<Leaderboards>>,
+}
+
+impl LeaderboardStore for MemoryStore {
+    fn load(&mut self) -> Result<Leaderboards, String> {
+        Ok(self.boards.lock().map_err(|e| e.to_string())?.clone())
+    }
+
+    fn save(&mut self, boards: &Leaderboards) -> Result<(), String> {
+        *self.boards.lock().map_err(|e| e.to_string())? = boards.clone();
+        Ok(())
+    }
+}
+
+/// A store, shared with the thread saving to it.
+pub type SharedStore = Arc<Mutex<Box<dyn LeaderboardStore>>>;
+
+/// Saves the boards of [`PlayersRanking`] periodically and on exit, off the game loop.
+#[derive(Resource)]
+pub struct LeaderboardStorage {
+    /// To the thread saving them, `None` once exited.
+    saves: Option<SyncSender<Leaderboards>>,
+    saver: Option<JoinHandle<()>>,
+    timer: Timer,
+    /// Changed since the last save.
+    is_dirty: bool,
+}
+
+impl LeaderboardStorage {
+    pub fn new(store: SharedStore, flush_interval: f32) -> Self {
+        // One save waits for the one being written, later ones for the next flush.
+        let (saves, boards) = mpsc::sync_channel::<Leaderboards>(1);
+        let saver = thread::spawn(move || {
+            for boards in boards {
+                let saved = store
+                    .lock()
+                    .map_err(|e| e.to_string())
+                    .and_then(|mut store| store.save(&boards));
+                if let Err(e) = saved {
+                    eprintln!("leaderboard not saved: {}", e);
+                }
+            }
+        });
+        Self {
+            saves: Some(saves),
+            saver: Some(saver),
+            timer: Timer::new(
+                Duration::from_secs_f32(flush_interval),
+                TimerMode::Repeating,
+            ),
+            is_dirty: false,
+        }
+    }
+}
+
+/// Set from another thread, like a signal handler, to save the leaderboards and exit.
+#[derive(Resource, Clone, Default)]
+pub struct ShutdownRequest(pub Arc<AtomicBool>);
+
+pub(crate) fn exit_on_shutdown_request(
+    shutdown: Res<ShutdownRequest>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if shutdown.0.load(Ordering::Relaxed) {
+        exit.send(AppExit);
+    }
+}
+
+pub(crate) fn flush_leaderboards(
+    time: Res<Time>,
+    ranking: Res<PlayersRanking>,
+    mut storage: ResMut<LeaderboardStorage>,
+    mut exit: EventReader<AppExit>,
+) {
+    storage.is_dirty |= ranking.is_changed();
+    storage.timer.tick(time.delta());
+    let is_exiting = exit.read().count() > 0;
+    if storage.is_dirty && (storage.timer.just_finished() || is_exiting) {
+        let boards = ranking.boards.clone();
+        let is_sent = match &storage.saves {
+            Some(saves) if is_exiting => saves.send(boards).is_ok(),
+            Some(saves) => saves.try_send(boards).is_ok(),
+            None => false,
+        };
+        storage.is_dirty &= !is_sent;
+    }
+    if is_exiting {
+        // Written before the app exits.
+        storage.saves = None;
+        if let Some(saver) = storage.saver.take() {
+            let _ = saver.join();
+        }
+    }
+}
+
+pub(crate) fn top_players(
+    In((_, request)): In<(ClientId, TopPlayers)>,
+    ranking: Res<PlayersRanking>,
+) -> Vec<PlayerRank> {
+    ranking
+        .boards
+        .top(request.board, request.count.min(MAX_TOP_PLAYERS), today())
+}
@@ -0,0 +1,787 @@
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashMap};
+use example_shared::{AllExistingMoles, Board, ExistingMoles, PlayerRank, Scores};
+use example_shared::{
+    ClientMessage, Mole, MoleCatalog, MoleDef, MoleKind, Movement, Path, ServerMessage,
+};
+use example_shared::{SpawnMole, UpdateScores};
+use litlnet_server_bevy::{
+    MessagesToRead, MessagesToSend, RComServer, RateLimit, RateLimitExceeded, RateLimiter,
+    Replicated, ReplicationAppExt, RpcAppExt, ServerPlugin, ServerSet, SessionEvent, Sessions,
+};
+use litlnet_trait::ClientId;
+use litlnet_trait::{Compression, ConnectionLimits, DecodeLimits, Server};
+use litlnet_websocket_server::ComServer;
+use rand::thread_rng;
+use rand::Rng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+mod accounts;
+mod catalog;
+mod config;
+mod leaderboard;
+mod names;
+mod rewind;
+mod round;
+
+pub use accounts::{account_key, parse_account_key, Account, Authentication, RoundScore};
+pub use catalog::{Catalog, SpawnTable, SpawnTimeline};
+pub use config::{KillCredit, ServerConfig, USAGE};
+pub use leaderboard::{
+    today, JsonFileStore, LeaderboardStorage, LeaderboardStore, Leaderboards, MemoryStore,
+    RoundBoard, SharedStore, ShutdownRequest,
+};
+pub use names::{default_name, NameRules};
+pub use rewind::{Escaped, MoleHistory, RewindStats, CHANGED_HITS, REWOUND_HITS};
+pub use round::Round;
+
+pub struct GamePlugin {
+    pub config: ServerConfig,
+    pub catalog: Catalog,
+    /// As loaded from `store`.
+    pub leaderboards: Leaderboards,
+    pub store: SharedStore,
+}
+
+impl GamePlugin {
+    /// With the leaderboards saved in `store`.
+    pub fn new(
+        config: ServerConfig,
+        catalog: Catalog,
+        mut store: Box<dyn LeaderboardStore>,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            config,
+            catalog,
+            leaderboards: store.load()?,
+            store: Arc::new(Mutex::new(store)),
+        })
+    }
+}
+
+/// Players in the replicated boards of [`Scores`].
+const SCOREBOARD: usize = 10;
+
+#[derive(Resource)]
+pub struct MoleIds {
+    pub next_id: usize,
+}
+#[derive(Default, Resource)]
+pub struct PlayersRanking {
+    /// Of the current round, by session, players with the same name are apart.
+    pub ranks: HashMap<ClientId, PlayerRank>,
+    /// Of the sessions in `ranks`, in `boards`.
+    pub board_keys: HashMap<ClientId, String>,
+    pub boards: Leaderboards,
+}
+
+impl PlayersRanking {
+    /// Players with an account are saved under it, guests under their name.
+    pub fn credit(&mut self, client_id: ClientId, name: &str, account: Option<u64>, points: usize) {
+        let rank = self.ranks.entry(client_id).or_insert_with(|| PlayerRank {
+            player_id: Some(client_id.into()),
+            name: name.to_string(),
+            score: 0,
+        });
+        // Renamed players are shown with their last name.
+        rank.name = name.to_string();
+        rank.score += points;
+        let key = account.map_or_else(|| name.to_string(), account_key);
+        self.boards.credit(&key, points, today());
+        self.board_keys.insert(client_id, key);
+    }
+
+    /// Of the current round, best first.
+    pub fn round_board(&self) -> Vec<PlayerRank> {
+        let mut players: Vec<_> = self.ranks.values().cloned().collect();
+        sort_ranks(&mut players);
+        players
+    }
+
+    /// Of the current round, by key of the saved boards.
+    pub fn round_scores_by_key(&self) -> HashMap<String, usize> {
+        let mut scores = HashMap::default();
+        for (client_id, rank) in &self.ranks {
+            if let Some(key) = self.board_keys.get(client_id) {
+                *scores.entry(key.clone()).or_insert(0) += rank.score;
+            }
+        }
+        scores
+    }
+}
+
+/// Best first, then by name and session.
+pub fn sort_ranks(players: &mut [PlayerRank]) {
+    players.sort_by(|p1, p2| {
+        p2.score
+            .cmp(&p1.score)
+            .then_with(|| p1.name.cmp(&p2.name))
+            .then_with(|| p1.player_id.cmp(&p2.player_id))
+    });
+}
+
+/// Rank and score last sent to each player with [`ServerMessage::OwnRank`].
+#[derive(Default, Resource)]
+pub struct OwnRanks {
+    pub sent: HashMap<ClientId, (Option<usize>, usize)>,
+}
+#[derive(Default, Resource)]
+pub struct PlayersNames {
+    pub names: HashMap<ClientId, String>,
+}
+
+#[derive(Resource)]
+pub struct SpawnTimer {
+    timer: Timer,
+}
+
+/// Server side only, the mole escapes when it finishes.
+#[derive(Component)]
+pub struct MoleLifetime {
+    timer: Timer,
+}
+
+/// Server side only, players who hit a tough mole, in order.
+#[derive(Component, Default)]
+pub struct MoleDamage {
+    by: Vec<ClientId>,
+}
+
+/// Server side only, recent hits close to a fleeing mole, with the server time they arrived at.
+#[derive(Component, Default)]
+pub struct NearbyHits(Vec<(f64, Vec2)>);
+
+#[derive(Resource)]
+pub struct RandomDeterministic {
+    pub random: ChaCha20Rng,
+    pub seed: u64,
+}
+
+#[derive(Resource)]
+pub struct ConnectionTarget {
+    url: String,
+    limits: ConnectionLimits,
+    decode_limits: DecodeLimits,
+    compression: Option<Compression>,
+}
+impl RandomDeterministic {
+    pub fn from_seed(seed: u64) -> Self {
+        Self {
+            random: ChaCha20Rng::seed_from_u64(seed),
+            seed,
+        }
+    }
+}
+impl Default for RandomDeterministic {
+    fn default() -> Self {
+        Self::from_seed(thread_rng().gen::<u64>())
+    }
+}
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        let config = &self.config;
+        app.add_plugins(
+            ServerPlugin::<RComServer<ComServer>, ServerMessage, ClientMessage>::default()
+                .with_session_grace(Duration::from_secs_f32(config.session_grace))
+                .with_replication_interval(Duration::from_secs_f32(config.replication_interval)),
+        );
+        app.add_plugins(MinimalPlugins);
+        app.replicate::<Mole>();
+        app.replicate_resource::<Scores>();
+        app.replicate_resource::<MoleCatalog>();
+        app.add_rpc_handler(existing_moles);
+        app.add_rpc_handler(leaderboard::top_players);
+        app.insert_resource(
+            RateLimiter::new(|message: &ClientMessage| match message {
+                ClientMessage::HitPosition(_) | ClientMessage::HitAt { .. } => "hit",
+                ClientMessage::SetName(_) => "set_name",
+                ClientMessage::Register { .. } | ClientMessage::Authenticate { .. } => "account",
+            })
+            .with_limit(
+                "hit",
+                RateLimit {
+                    per_second: 8f32,
+                    burst: 12f32,
+                },
+            )
+            .with_limit(
+                "set_name",
+                RateLimit {
+                    per_second: 0.5f32,
+                    burst: 3f32,
+                },
+            )
+            .with_limit(
+                "account",
+                RateLimit {
+                    per_second: 0.2f32,
+                    burst: 3f32,
+                },
+            ),
+        );
+        app.insert_resource(match config.seed {
+            Some(seed) => RandomDeterministic::from_seed(seed),
+            None => RandomDeterministic::default(),
+        });
+        app.insert_resource(MoleIds { next_id: 0 });
+        app.insert_resource(PlayersNames::default());
+        app.insert_resource(NameRules::with_banned_words(&config.banned_words));
+        app.insert_resource(OwnRanks::default());
+        let boards = self.leaderboards.clone();
+        app.insert_resource(Authentication::new(&boards.accounts));
+        app.insert_resource(RewindStats::default());
+        rewind::register_diagnostics(app);
+        app.insert_resource(PlayersRanking {
+            boards,
+            ..Default::default()
+        });
+        app.insert_resource(LeaderboardStorage::new(
+            self.store.clone(),
+            config.leaderboard_flush_interval,
+        ));
+        app.init_resource::<ShutdownRequest>();
+        app.insert_resource(Round {
+            number: self.leaderboards.last_round(),
+            ..Default::default()
+        });
+        app.insert_resource(SpawnTimer {
+            timer: Timer::from_seconds(config.spawn_interval, TimerMode::Repeating),
+        });
+        app.insert_resource(Scores::default());
+        app.insert_resource(MoleCatalog {
+            kinds: self.catalog.kinds.clone(),
+        });
+        app.insert_resource(SpawnTimeline {
+            tables: self.catalog.timeline.clone(),
+            elapsed: 0f32,
+        });
+        app.insert_resource(ConnectionTarget {
+            url: dbg!(config.address()),
+            limits: ConnectionLimits {
+                max_clients: config.max_clients,
+                max_connections_per_ip: config.max_connections_per_ip,
+                max_accepts_per_second: config.max_accepts_per_second,
+            },
+            decode_limits: config.decode_limits(),
+            compression: config.compression.then(Compression::default),
+        });
+        app.insert_resource(config.clone());
+        // Before the plugin sends messages and the replicated state, so clients get both together.
+        app.add_systems(
+            Update,
+            (
+                round::update_round,
+                accounts::send_challenges,
+                receive_messages,
+                expire_moles,
+                spawn_moles
+                    .run_if(round::is_playing)
+                    .run_if(resource_exists::<RComServer<ComServer>>),
+                update_scores,
+                send_own_ranks,
+            )
+                .chain()
+                .after(ServerSet::Receive)
+                .before(ServerSet::Send),
+        );
+        app.add_systems(Update, reconnect);
+        app.add_systems(Update, log_rate_limits);
+        app.add_systems(Update, rewind::report_rewinds.after(receive_messages));
+        app.add_systems(Update, forget_ended_sessions);
+        app.add_systems(
+            Update,
+            (
+                leaderboard::exit_on_shutdown_request,
+                leaderboard::flush_leaderboards,
+            )
+                .chain()
+                .after(update_scores),
+        );
+    }
+}
+
+fn log_rate_limits(mut events: EventReader<RateLimitExceeded>) {
+    for event in events.read() {
+        warn!(
+            "client {:?} exceeded the rate of {}: {:?}",
+            event.client_id, event.class, event.action
+        );
+    }
+}
+
+fn forget_ended_sessions(
+    mut events: EventReader<SessionEvent>,
+    mut player_names: ResMut<PlayersNames>,
+    mut own_ranks: ResMut<OwnRanks>,
+) {
+    for event in events.read() {
+        if let SessionEvent::Ended(client_id) = event {
+            player_names.names.remove(client_id);
+            own_ranks.sent.remove(client_id);
+        }
+    }
+}
+
+fn existing_moles(
+    In((client_id, _)): In<(ClientId, ExistingMoles)>,
+    moles: Query<&Mole, Without<Escaped>>,
+) -> AllExistingMoles {
+    AllExistingMoles {
+        local_player_id: client_id.into(),
+        moles: moles
+            .iter()
+            .map(|mole| SpawnMole {
+                id: mole.id,
+                def: mole.def.clone(),
+            })
+            .collect(),
+    }
+}
+
+fn reconnect(
+    mut commands: Commands,
+    connection: Res<ConnectionTarget>,
+    com: Option<ResMut<RComServer<ComServer>>>,
+) {
+    if com.is_none() {
+        dbg!("Reconnection");
+        if let Ok(mut new_com) = RComServer::<ComServer>::bind(&connection.url) {
+            new_com.server.set_limits(connection.limits.clone());
+            new_com.server.set_decode_limits(connection.decode_limits);
+            new_com.server.set_compression(connection.compression);
+            commands.insert_resource(new_com);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn receive_messages(
+    mut commands: Commands,
+    com_server: Option<Res<RComServer<ComServer>>>,
+    sessions: Res<Sessions>,
+    mut player_names: ResMut<PlayersNames>,
+    mut ranking: ResMut<PlayersRanking>,
+    mut recv: ResMut<MessagesToRead<ClientMessage>>,
+    mut send: ResMut<MessagesToSend<ServerMessage>>,
+    mut moles: Query<(
+        Entity,
+        &mut Mole,
+        &mut MoleDamage,
+        &mut MoleHistory,
+        Option<&Escaped>,
+    )>,
+    mut nearby_hits: Query<&mut NearbyHits>,
+    config: Res<ServerConfig>,
+    name_rules: Res<NameRules>,
+    mut authentication: ResMut<Authentication>,
+    mut rewind_stats: ResMut<RewindStats>,
+    catalog: Res<MoleCatalog>,
+    real_time: Res<Time<Real>>,
+) {
+    // Server time, clients evaluate the paths of moles on the same clock.
+    let now = real_time.elapsed_seconds_f64();
+    if com_server.is_some() {
+        // Despawned after this system, several hits may arrive in the same frame.
+        let mut killed = vec![];
+        while let Some((from_client_id, message)) = recv.pop() {
+            match message {
+                ClientMessage::HitPosition(position) | ClientMessage::HitAt { position, .. } => {
+                    // Server time the player saw the moles at, hits without one are checked on arrival.
+                    let time = match message {
+                        ClientMessage::HitAt { time, .. } => time,
+                        _ => now,
+                    };
+                    debug!("hit at {:?}, seen at {}", position, time);
+                    // Rewound at most `max_rewind`, older views would let players hit what's long gone.
+                    let view_time = time.max(now - config.max_rewind as f64).min(now);
+                    let mole_hit_at = |time| {
+                        moles
+                            .iter()
+                            .find(|(entity, mole, _, history, escaped)| {
+                                !killed.contains(entity)
+                                    && rewind::is_hit(
+                                        mole, history, *escaped, position, time, &catalog,
+                                    )
+                            })
+                            .map(|(entity, ..)| entity)
+                    };
+                    let hit = mole_hit_at(view_time);
+                    rewind_stats.record(view_time < now, hit, mole_hit_at(now));
+                    if let Some((entity, mut mole, mut damage, _, _)) =
+                        hit.and_then(|entity| moles.get_mut(entity).ok())
+                    {
+                        damage.by.push(from_client_id);
+                        let mole_id = mole.id;
+                        let points = catalog
+                            .get(mole.def.catalog_id)
+                            .map_or(1, |kind| kind.points);
+                        let message = match &mut mole.def.kind {
+                            MoleKind::HitCount(remaining) if *remaining > 1 => {
+                                *remaining -= 1;
+                                ServerMessage::MoleDamaged {
+                                    mole_id,
+                                    remaining: *remaining,
+                                    by: from_client_id.into(),
+                                }
+                            }
+                            _ => {
+                                let credited = match config.kill_credit {
+                                    KillCredit::LastHit => vec![(from_client_id, points)],
+                                    KillCredit::Contributors => split_points(points, &damage.by),
+                                };
+                                for (client_id, points) in credited {
+                                    let name = player_names
+                                        .names
+                                        .get(&client_id)
+                                        .cloned()
+                                        .unwrap_or_else(|| default_name(client_id));
+                                    let account = authentication.accounts.get(&client_id).copied();
+                                    ranking.credit(client_id, &name, account, points);
+                                }
+                                info!("dead mole: {}", mole_id);
+                                commands.entity(entity).despawn();
+                                killed.push(entity);
+                                ServerMessage::DeadMole {
+                                    mole_id,
+                                    player_killer_id: from_client_id.into(),
+                                }
+                            }
+                        };
+                        for send_client_id in sessions.iter() {
+                            send.push((*send_client_id, message.clone()));
+                        }
+                    }
+                    // TODO: if none mole to die, lose points ?
+                    for (entity, mut mole, _, mut history, escaped) in moles.iter_mut() {
+                        let flee = catalog.get(mole.def.catalog_id).map(|kind| &kind.movement);
+                        if let Some(Movement::Flee {
+                            radius,
+                            distance,
+                            duration,
+                            hits,
+                            window,
+                        }) = flee
+                        {
+                            let current = mole.def.position_at(now);
+                            if killed.contains(&entity)
+                                || escaped.is_some()
+                                || current.distance(position) >= *radius
+                            {
+                                continue;
+                            }
+                            let Ok(mut nearby) = nearby_hits.get_mut(entity) else {
+                                continue;
+                            };
+                            nearby.0.retain(|(at, _)| *at >= now - *window as f64);
+                            nearby.0.push((now, position));
+                            if nearby.0.len() < *hits {
+                                continue;
+                            }
+                            let cluster =
+                                nearby.0.iter().map(|(_, position)| *position).sum::<Vec2>()
+                                    / nearby.0.len() as f32;
+                            nearby.0.clear();
+                            let away = (current - cluster).try_normalize().unwrap_or(Vec2::X);
+                            history.replace(mole.def.clone(), now, config.max_rewind);
+                            mole.def.position = current;
+                            mole.def.start_time = now;
+                            mole.def.path = Path::Dash {
+                                to: away * *distance,
+                                duration: *duration,
+                            };
+                        }
+                    }
+                }
+                ClientMessage::SetName(name) => {
+                    let account = authentication.accounts.get(&from_client_id).copied();
+                    let taken = player_names
+                        .names
+                        .iter()
+                        .filter(|(client_id, _)| **client_id != from_client_id)
+                        .map(|(_, name)| name.as_str())
+                        .chain(ranking.boards.owned_names(account));
+                    let message = match name_rules.check(&name, taken) {
+                        Ok(name) => {
+                            player_names.names.insert(from_client_id, name.clone());
+                            let owner = account.filter(|id| {
+                                ranking.boards.accounts.get(id).is_some_and(|owner| {
+                                    owner.name.as_deref() != Some(name.as_str())
+                                })
+                            });
+                            if let Some(id) = owner {
+                                ranking.boards.accounts.get_mut(&id).unwrap().name =
+                                    Some(name.clone());
+                            }
+                            ServerMessage::NameAccepted(name)
+                        }
+                        Err(rejection) => ServerMessage::NameRejected(rejection),
+                    };
+                    send.push((from_client_id, message));
+                }
+                ClientMessage::Register {
+                    public_key,
+                    signature,
+                } => {
+                    let message = accounts::register(
+                        from_client_id,
+                        &public_key,
+                        &signature,
+                        &config,
+                        &mut authentication,
+                        &mut ranking,
+                        &mut player_names,
+                    );
+                    send.push((from_client_id, message));
+                }
+                ClientMessage::Authenticate { account, signature } => {
+                    let message = accounts::authenticate(
+                        from_client_id,
+                        account,
+                        &signature,
+                        &mut authentication,
+                        &mut ranking,
+                        &mut player_names,
+                    );
+                    let is_failed = message == ServerMessage::AuthenticationFailed;
+                    send.push((from_client_id, message));
+                    // To register again, if the server lost the account.
+                    if is_failed {
+                        send.push((from_client_id, authentication.challenge(from_client_id)));
+                    }
+                }
+            }
+        }
+    }
+}
+/// Escaped moles are kept, hidden from clients, until late hits can't be rewound to them.
+fn expire_moles(
+    mut commands: Commands,
+    time: Res<Time>,
+    real_time: Res<Time<Real>>,
+    config: Res<ServerConfig>,
+    sessions: Res<Sessions>,
+    mut send: ResMut<MessagesToSend<ServerMessage>>,
+    mut moles: Query<(Entity, &Mole, &mut MoleLifetime, Option<&Escaped>)>,
+) {
+    let now = real_time.elapsed_seconds_f64();
+    for (entity, mole, mut lifetime, escaped) in &mut moles {
+        if let Some(escaped) = escaped {
+            if escaped.at + (config.max_rewind as f64) < now {
+                commands.entity(entity).despawn();
+            }
+            continue;
+        }
+        lifetime.timer.tick(time.delta());
+        if !lifetime.timer.finished() {
+            continue;
+        }
+        info!("escaped mole: {}", mole.id);
+        commands
+            .entity(entity)
+            .remove::<Replicated>()
+            .insert(Escaped { at: now });
+        for client_id in sessions.iter() {
+            send.push((*client_id, ServerMessage::EscapedMole(mole.id)));
+        }
+    }
+}
+
+/// `points` for each player in `hits`, in proportion to their hits, in order of their first hit.
+pub fn split_points(points: usize, hits: &[ClientId]) -> Vec<(ClientId, usize)> {
+    let mut counts: Vec<(ClientId, usize)> = vec![];
+    for client_id in hits {
+        match counts.iter_mut().find(|(id, _)| id == client_id) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((*client_id, 1)),
+        }
+    }
+    let mut credited: Vec<_> = counts
+        .iter()
+        .map(|(client_id, count)| (*client_id, points * count / hits.len()))
+        .collect();
+    // Largest remainders first, the earliest hitter on a tie.
+    let mut remainders: Vec<_> = (0..counts.len()).collect();
+    remainders.sort_by_key(|&i| std::cmp::Reverse(points * counts[i].1 % hits.len()));
+    let left = points - credited.iter().map(|(_, points)| points).sum::<usize>();
+    for i in remainders.into_iter().take(left) {
+        credited[i].1 += 1;
+    }
+    credited
+}
+
+/// What [`spawn_moles`] picks the next mole with.
+#[derive(SystemParam)]
+struct Spawner<'w> {
+    random: ResMut<'w, RandomDeterministic>,
+    timer: ResMut<'w, SpawnTimer>,
+    timeline: ResMut<'w, SpawnTimeline>,
+    mole_ids: ResMut<'w, MoleIds>,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_moles(
+    mut commands: Commands,
+    time: Res<Time>,
+    real_time: Res<Time<Real>>,
+    config: Res<ServerConfig>,
+    catalog: Res<MoleCatalog>,
+    mut spawner: Spawner,
+    sessions: Res<Sessions>,
+    mut send: ResMut<MessagesToSend<ServerMessage>>,
+    moles: Query<(), (With<Mole>, Without<Escaped>)>,
+) {
+    let Spawner {
+        random,
+        timer,
+        timeline,
+        mole_ids,
+    } = &mut spawner;
+    timeline.elapsed += time.delta_seconds();
+    timer.timer.tick(time.delta());
+    if !timer.timer.just_finished() {
+        return;
+    }
+    if config.max_moles <= moles.iter().count() {
+        return;
+    }
+    let catalog_id = match timeline.pick(&catalog, &mut random.random) {
+        Some(catalog_id) => catalog_id,
+        None => return,
+    };
+    let kind = &catalog.kinds[catalog_id];
+    let path = pick_path(&kind.movement, &mut random.random);
+    let def = MoleDef {
+        catalog_id,
+        kind: if kind.hits > 1 {
+            MoleKind::HitCount(kind.hits)
+        } else {
+            MoleKind::Duration(kind.lifetime)
+        },
+        position: Vec2::new(
+            random
+                .random
+                .gen_range(-config.spawn_area_radius.x..=config.spawn_area_radius.x),
+            random
+                .random
+                .gen_range(-config.spawn_area_radius.y..=config.spawn_area_radius.y),
+        ) + config.spawn_offset,
+        start_time: real_time.elapsed_seconds_f64(),
+        path,
+    };
+    let message = ServerMessage::Spawn(SpawnMole {
+        id: mole_ids.next_id,
+        def: def.clone(),
+    });
+    commands.spawn((
+        Replicated,
+        MoleLifetime {
+            timer: Timer::from_seconds(kind.lifetime, TimerMode::Once),
+        },
+        MoleDamage::default(),
+        NearbyHits::default(),
+        MoleHistory::new(def.start_time),
+        Mole {
+            id: mole_ids.next_id,
+            def,
+        },
+    ));
+    dbg!("new mole");
+    for client_id in sessions.iter() {
+        send.push((*client_id, message.clone()));
+    }
+    mole_ids.next_id += 1;
+}
+
+/// The path of a new mole moving as its kind does.
+fn pick_path(movement: &Movement, random: &mut impl Rng) -> Path {
+    match movement {
+        Movement::Still | Movement::Flee { .. } => Path::Still,
+        Movement::Linear { distance, period } => Path::Linear {
+            to: Vec2::from_angle(random.gen_range(0f32..std::f32::consts::TAU)) * *distance,
+            period: *period,
+        },
+        Movement::Circle { radius, period } => Path::Circle {
+            radius: *radius,
+            period: *period,
+            phase: random.gen_range(0f32..std::f32::consts::TAU),
+        },
+        Movement::Hop {
+            holes,
+            distance,
+            interval,
+        } => Path::Hop {
+            // The first is where it spawns.
+            holes: std::iter::once(Vec2::ZERO)
+                .chain((1..*holes).map(|_| {
+                    Vec2::from_angle(random.gen_range(0f32..std::f32::consts::TAU))
+                        * random.gen_range(0f32..=*distance)
+                }))
+                .collect(),
+            interval: *interval,
+        },
+    }
+}
+
+fn update_scores(
+    ranking: Res<PlayersRanking>,
+    sessions: Res<Sessions>,
+    mut scores: ResMut<Scores>,
+    mut send: ResMut<MessagesToSend<ServerMessage>>,
+) {
+    if !ranking.is_changed() {
+        return;
+    }
+    let all_time = ranking.boards.top(Board::AllTime, SCOREBOARD, today());
+    if scores.all_time != all_time {
+        scores.all_time = all_time;
+    }
+    let mut best_players = ranking.round_board();
+    best_players.truncate(SCOREBOARD);
+    if scores.best_players == best_players {
+        return;
+    }
+    scores.best_players = best_players;
+    let message = ServerMessage::UpdateScores(UpdateScores {
+        best_players: scores.best_players.clone(),
+    });
+    for client_id in sessions.iter() {
+        send.push((*client_id, message.clone()));
+    }
+}
+
+/// Players out of the replicated top know their own rank.
+fn send_own_ranks(
+    ranking: Res<PlayersRanking>,
+    sessions: Res<Sessions>,
+    mut own_ranks: ResMut<OwnRanks>,
+    mut send: ResMut<MessagesToSend<ServerMessage>>,
+) {
+    if !ranking.is_changed() {
+        return;
+    }
+    let board = ranking.round_board();
+    for client_id in sessions.iter() {
+        let player_id = Some((*client_id).into());
+        let own_rank = board
+            .iter()
+            .position(|rank| rank.player_id == player_id)
+            .map_or((None, 0), |position| {
+                (Some(position + 1), board[position].score)
+            });
+        let sent = own_ranks.sent.get(client_id).copied().unwrap_or((None, 0));
+        if own_rank == sent {
+            continue;
+        }
+        own_ranks.sent.insert(*client_id, own_rank);
+        send.push((
+            *client_id,
+            ServerMessage::OwnRank {
+                rank: own_rank.0,
+                score: own_rank.1,
+            },
+        ));
+    }
+}
@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+use example_shared::{NameRejection, MAX_NAME_LENGTH};
+use litlnet_trait::ClientId;
+
+/// Checks the names players choose, replace it after the plugin to change the filter.
+#[derive(Resource)]
+pub struct NameRules {
+    /// Whether a name is offensive, it's given in lowercase.
+    filter: Box<dyn Fn(&str) -> bool + Send + Sync>,
+}
+
+impl NameRules {
+    /// Names containing one of the words are offensive, regardless of their case.
+    pub fn with_banned_words(words: &[String]) -> Self {
+        let words: Vec<_> = words.iter().map(|word| word.to_lowercase()).collect();
+        Self::with_filter(move |name| words.iter().any(|word| name.contains(word.as_str())))
+    }
+
+    pub fn with_filter(filter: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            filter: Box::new(filter),
+        }
+    }
+
+    /// `name` without surrounding spaces, if it's allowed and none of `taken` in another case.
+    pub fn check<'a>(
+        &self,
+        name: &str,
+        mut taken: impl Iterator<Item = &'a str>,
+    ) -> Result<String, NameRejection> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(NameRejection::Empty);
+        }
+        if name.chars().count() > MAX_NAME_LENGTH {
+            return Err(NameRejection::TooLong);
+        }
+        if !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(NameRejection::InvalidCharacters);
+        }
+        let lowercase = name.to_lowercase();
+        if (self.filter)(&lowercase) {
+            return Err(NameRejection::Offensive);
+        }
+        if taken.any(|other| other.to_lowercase() == lowercase) {
+            return Err(NameRejection::Taken);
+        }
+        Ok(name.to_string())
+    }
+}
+
+impl Default for NameRules {
+    fn default() -> Self {
+        Self::with_banned_words(&[])
+    }
+}
+
+/// Of a player who didn't choose one, a different one for each session.
+pub fn default_name(client_id: ClientId) -> String {
+    format!("Newbie{}", client_id.0)
+}
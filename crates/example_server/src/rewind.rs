@@ -0,0 +1,158 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+};
+use example_shared::{Mole, MoleCatalog, MoleDef};
+
+/// Percent of hits checked in the past.
+pub const REWOUND_HITS: DiagnosticPath = DiagnosticPath::const_new("example_server/rewound_hits");
+/// Percent of hits whose outcome changed by rewinding.
+pub const CHANGED_HITS: DiagnosticPath = DiagnosticPath::const_new("example_server/changed_hits");
+
+/// Seconds between two logs of [`RewindStats`].
+const REPORT_INTERVAL: f32 = 60f32;
+
+/// Server side only, the paths a mole followed before its current one, to check late hits.
+#[derive(Component)]
+pub struct MoleHistory {
+    /// Server time the mole spawned at.
+    spawned_at: f64,
+    /// Each with the server time it was replaced at, oldest first.
+    past: Vec<(f64, MoleDef)>,
+}
+
+impl MoleHistory {
+    pub fn new(spawned_at: f64) -> Self {
+        Self {
+            spawned_at,
+            past: vec![],
+        }
+    }
+
+    /// The mole as it was at `time`, `None` before it spawned.
+    pub fn def_at<'a>(&'a self, time: f64, current: &'a MoleDef) -> Option<&'a MoleDef> {
+        if time < self.spawned_at {
+            return None;
+        }
+        let past = self.past.iter().find(|(until, _)| time < *until);
+        Some(past.map_or(current, |(_, def)| def))
+    }
+
+    /// Keeps `def`, replaced at `now`, as long as hits can be rewound to it.
+    pub fn replace(&mut self, def: MoleDef, now: f64, max_rewind: f32) {
+        self.past
+            .retain(|(until, _)| *until >= now - max_rewind as f64);
+        self.past.push((now, def));
+    }
+}
+
+/// Server side only, on an escaped mole kept for late hits, it's no longer replicated.
+#[derive(Component)]
+pub struct Escaped {
+    /// Server time the mole escaped at.
+    pub at: f64,
+}
+
+/// Whether `position` is on the mole at `time`, as players saw it then.
+pub(crate) fn is_hit(
+    mole: &Mole,
+    history: &MoleHistory,
+    escaped: Option<&Escaped>,
+    position: Vec2,
+    time: f64,
+    catalog: &MoleCatalog,
+) -> bool {
+    let hit_radius = catalog
+        .get(mole.def.catalog_id)
+        .map_or(0f32, |kind| kind.hit_radius);
+    let is_gone = escaped.is_some_and(|escaped| escaped.at <= time);
+    !is_gone
+        && history
+            .def_at(time, &mole.def)
+            .is_some_and(|def| def.position_at(time).distance(position) < hit_radius)
+}
+
+/// Hits checked in the past, and how many of them hit another mole than they would have on arrival.
+#[derive(Resource, Default, Debug)]
+pub struct RewindStats {
+    pub hits: usize,
+    pub rewound: usize,
+    pub changed: usize,
+}
+
+impl RewindStats {
+    pub(crate) fn record(
+        &mut self,
+        is_rewound: bool,
+        hit: Option<Entity>,
+        hit_on_arrival: Option<Entity>,
+    ) {
+        self.hits += 1;
+        if is_rewound {
+            self.rewound += 1;
+        }
+        if hit != hit_on_arrival {
+            self.changed += 1;
+        }
+    }
+
+    /// Percent of hits checked in the past, 0 without hits.
+    pub fn rewound_rate(&self) -> f64 {
+        percent(self.rewound, self.hits)
+    }
+
+    /// Percent of hits whose outcome changed by rewinding, 0 without hits.
+    pub fn changed_rate(&self) -> f64 {
+        percent(self.changed, self.hits)
+    }
+}
+
+fn percent(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        return 0f64;
+    }
+    100f64 * count as f64 / total as f64
+}
+
+pub(crate) fn register_diagnostics(app: &mut App) {
+    app.register_diagnostic(Diagnostic::new(REWOUND_HITS).with_suffix("%"))
+        .register_diagnostic(Diagnostic::new(CHANGED_HITS).with_suffix("%"));
+}
+
+/// Hits counted at the last report.
+pub(crate) struct RewindReport {
+    timer: Timer,
+    hits: usize,
+}
+
+impl Default for RewindReport {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(REPORT_INTERVAL, TimerMode::Repeating),
+            hits: 0,
+        }
+    }
+}
+
+/// Logs how often rewinds change the outcome of hits, if there were new ones.
+pub(crate) fn report_rewinds(
+    time: Res<Time>,
+    stats: Res<RewindStats>,
+    mut report: Local<RewindReport>,
+    mut diagnostics: Diagnostics,
+) {
+    report.timer.tick(time.delta());
+    if !report.timer.just_finished() || report.hits == stats.hits {
+        return;
+    }
+    report.hits = stats.hits;
+    println!(
+        "rewinds: {}/{}/{} changed/rewound/hits, {:.1}% of hits changed",
+        stats.changed,
+        stats.rewound,
+        stats.hits,
+        stats.changed_rate()
+    );
+    diagnostics.add_measurement(&REWOUND_HITS, || stats.rewound_rate());
+    diagnostics.add_measurement(&CHANGED_HITS, || stats.changed_rate());
+}
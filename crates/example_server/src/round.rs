@@ -0,0 +1,100 @@
+use bevy::prelude::*;
+use example_shared::{Mole, RoundPhase, ServerMessage};
+use litlnet_server_bevy::{MessagesToSend, SessionEvent, Sessions};
+
+use crate::{PlayersRanking, ServerConfig, SpawnTimeline};
+
+/// Players announced as the winners of a round.
+const WINNERS: usize = 3;
+
+/// The round being played, its phases last as configured in [`ServerConfig`].
+#[derive(Resource, Default)]
+pub struct Round {
+    pub phase: RoundPhase,
+    /// Server time the phase ends at, `None` in the lobby.
+    pub ends_at: Option<f64>,
+    /// Rounds started since the server started.
+    pub number: u32,
+}
+
+impl Round {
+    fn message(&self) -> ServerMessage {
+        ServerMessage::RoundState {
+            phase: self.phase,
+            ends_at: self.ends_at,
+        }
+    }
+}
+
+pub(crate) fn is_playing(round: Res<Round>) -> bool {
+    round.phase == RoundPhase::Playing
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn update_round(
+    mut commands: Commands,
+    real_time: Res<Time<Real>>,
+    config: Res<ServerConfig>,
+    sessions: Res<Sessions>,
+    mut session_events: EventReader<SessionEvent>,
+    mut round: ResMut<Round>,
+    mut ranking: ResMut<PlayersRanking>,
+    mut timeline: ResMut<SpawnTimeline>,
+    mut send: ResMut<MessagesToSend<ServerMessage>>,
+    moles: Query<Entity, With<Mole>>,
+) {
+    for event in session_events.read() {
+        if let SessionEvent::Started(client_id) = event {
+            send.push((*client_id, round.message()));
+        }
+    }
+    let now = real_time.elapsed_seconds_f64();
+    let next = match (round.phase, round.ends_at) {
+        (RoundPhase::Lobby, _) => {
+            (sessions.connected().count() >= config.min_players).then_some(RoundPhase::Countdown)
+        }
+        (phase, Some(ends_at)) if ends_at <= now => Some(match phase {
+            RoundPhase::Countdown => RoundPhase::Playing,
+            RoundPhase::Playing => RoundPhase::Results,
+            _ => RoundPhase::Lobby,
+        }),
+        _ => None,
+    };
+    let phase = match next {
+        Some(phase) => phase,
+        None => return,
+    };
+    let duration = match phase {
+        RoundPhase::Lobby => None,
+        RoundPhase::Countdown => {
+            round.number += 1;
+            // The board of the last round is shown until then.
+            ranking.ranks.clear();
+            ranking.board_keys.clear();
+            Some(config.countdown_duration)
+        }
+        RoundPhase::Playing => {
+            timeline.elapsed = 0f32;
+            Some(config.round_duration)
+        }
+        RoundPhase::Results => {
+            for mole in &moles {
+                commands.entity(mole).despawn();
+            }
+            let scores = ranking.round_scores_by_key();
+            ranking.boards.end_round(round.number, scores);
+            let mut winners = ranking.round_board();
+            winners.truncate(WINNERS);
+            for client_id in sessions.iter() {
+                send.push((*client_id, ServerMessage::RoundWinners(winners.clone())));
+            }
+            Some(config.results_duration)
+        }
+    };
+    round.phase = phase;
+    round.ends_at = duration.map(|duration| now + duration as f64);
+    info!("round {}: {:?}", round.number, phase);
+    for client_id in sessions.iter() {
+        send.push((*client_id, round.message()));
+    }
+}
@@ -0,0 +1,68 @@
+use example_server::{Catalog, SpawnTimeline};
+use example_shared::MoleCatalog;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+const CATALOG: &str = r#"
+[[kinds]]
+name = "common"
+
+[[kinds]]
+name = "rare"
+hits = 2
+weight = 0
+
+[[timeline]]
+from = 0
+
+[[timeline]]
+from = 10
+weights = { common = 0, rare = 1 }
+"#;
+
+#[test]
+fn built_in_catalog_is_valid() {
+    assert!(!Catalog::default().kinds.is_empty());
+}
+
+#[test]
+fn missing_keys_are_defaults() {
+    let catalog = Catalog::from_toml(CATALOG).unwrap();
+    assert_eq!(catalog.kinds[1].hits, 2);
+    assert_eq!(catalog.kinds[1].lifetime, catalog.kinds[0].lifetime);
+}
+
+#[test]
+fn spawn_tables_follow_the_timeline() {
+    let catalog = Catalog::from_toml(CATALOG).unwrap();
+    let kinds = MoleCatalog {
+        kinds: catalog.kinds,
+    };
+    let mut timeline = SpawnTimeline {
+        tables: catalog.timeline,
+        elapsed: 0f32,
+    };
+    let mut random = ChaCha20Rng::seed_from_u64(0);
+    for _ in 0..20 {
+        assert_eq!(timeline.pick(&kinds, &mut random), Some(0));
+    }
+    timeline.elapsed = 10f32;
+    for _ in 0..20 {
+        assert_eq!(timeline.pick(&kinds, &mut random), Some(1));
+    }
+}
+
+#[test]
+fn invalid_catalogs_are_errors() {
+    assert!(Catalog::from_toml("kinds = []").is_err());
+    let unknown_kind = CATALOG.replace("rare = 1", "rarest = 1");
+    assert!(Catalog::from_toml(&unknown_kind).is_err());
+    let unknown_key = CATALOG.replace("hits = 2", "hitz = 2");
+    assert!(Catalog::from_toml(&unknown_key).is_err());
+    for key in ["size", "hit_radius", "lifetime"] {
+        let negative = CATALOG.replace("hits = 2", &format!("{} = -1", key));
+        assert!(Catalog::from_toml(&negative).is_err(), "{}", key);
+        let infinite = CATALOG.replace("hits = 2", &format!("{} = inf", key));
+        assert!(Catalog::from_toml(&infinite).is_err(), "{}", key);
+    }
+}
@@ -0,0 +1,50 @@
+use bevy::math::Vec2;
+use example_server::ServerConfig;
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn missing_keys_are_defaults() {
+    let config =
+        ServerConfig::from_toml("spawn_interval = 0.25\nspawn_area_radius = [200, 100]").unwrap();
+    assert_eq!(config.spawn_interval, 0.25f32);
+    assert_eq!(config.spawn_area_radius, Vec2::new(200f32, 100f32));
+    assert_eq!(config.max_moles, ServerConfig::default().max_moles);
+}
+
+#[test]
+fn unknown_keys_are_errors() {
+    assert!(ServerConfig::from_toml("spawn_intervall = 0.25").is_err());
+    assert!(ServerConfig::from_args(args(&["--spawn-intervall", "0.25"])).is_err());
+}
+
+#[test]
+fn flags_override_the_file() {
+    let path = std::env::temp_dir().join("example_server_flags_override_the_file.toml");
+    std::fs::write(&path, "max_moles = 10\nseed = 7\n").unwrap();
+    let config = ServerConfig::from_args(args(&[
+        "--config",
+        path.to_str().unwrap(),
+        "--max-moles",
+        "20",
+        "--bind-address",
+        "127.0.0.1",
+    ]))
+    .unwrap();
+    assert_eq!(config.max_moles, 20);
+    assert_eq!(config.seed, Some(7));
+    assert_eq!(config.bind_address, "127.0.0.1");
+}
+
+#[test]
+fn values_the_game_would_panic_on_are_errors() {
+    assert!(ServerConfig::from_toml("session_grace = -1").is_err());
+    assert!(ServerConfig::from_toml("replication_interval = -0.1").is_err());
+    assert!(ServerConfig::from_toml("spawn_interval = nan").is_err());
+    assert!(ServerConfig::from_args(args(&["--spawn-area-radius", "[-200, 100]"])).is_err());
+    assert!(ServerConfig::from_args(args(&["--spawn-interval", "0"])).is_err());
+    assert!(ServerConfig::from_toml("max_accepts_per_second = 0").is_err());
+    assert!(ServerConfig::from_toml("max_accepts_per_second = 0.5").is_ok());
+}
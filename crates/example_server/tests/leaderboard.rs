@@ -0,0 +1,94 @@
+use bevy::utils::HashMap;
+use example_server::{
+    account_key, Account, JsonFileStore, LeaderboardStore, Leaderboards, MemoryStore, RoundScore,
+};
+use example_shared::{Board, PlayerRank};
+
+fn rank(name: &str, score: usize) -> PlayerRank {
+    PlayerRank {
+        player_id: None,
+        name: name.to_string(),
+        score,
+    }
+}
+
+#[test]
+fn daily_board_starts_over_on_a_new_day() {
+    let mut boards = Leaderboards::default();
+    boards.credit("alice", 2, 10);
+    boards.credit("bob", 1, 10);
+    boards.credit("bob", 1, 11);
+    assert_eq!(boards.top(Board::Daily, 10, 11), [rank("bob", 1)]);
+    assert!(boards.top(Board::Daily, 10, 12).is_empty());
+    assert_eq!(
+        boards.top(Board::AllTime, 10, 12),
+        [rank("alice", 2), rank("bob", 2)]
+    );
+    assert_eq!(boards.top(Board::AllTime, 1, 12), [rank("alice", 2)]);
+}
+
+#[test]
+fn only_the_last_rounds_are_kept() {
+    let mut boards = Leaderboards::default();
+    for number in 1..=150 {
+        let ranks = HashMap::from_iter([("alice".to_string(), number as usize)]);
+        boards.end_round(number, ranks);
+    }
+    assert_eq!(boards.last_round(), 150);
+    assert_eq!(boards.rounds.len(), 100);
+    assert!(boards.top(Board::Round(50), 10, 0).is_empty());
+    assert_eq!(boards.top(Board::Round(51), 10, 0), [rank("alice", 51)]);
+    assert_eq!(boards.top(Board::LastRound, 10, 0), [rank("alice", 150)]);
+}
+
+#[test]
+fn accounts_are_shown_by_name_and_keep_their_rounds() {
+    let mut boards = Leaderboards::default();
+    boards.accounts.insert(
+        7,
+        Account {
+            name: Some("alice".to_string()),
+            ..Default::default()
+        },
+    );
+    boards.credit(&account_key(7), 3, 0);
+    boards.credit("alice", 1, 0);
+    boards.end_round(1, boards.all_time.clone());
+    assert_eq!(
+        boards.top(Board::LastRound, 10, 0),
+        [rank("alice", 3), rank("alice", 1)]
+    );
+    assert_eq!(
+        boards.accounts[&7].rounds,
+        [RoundScore {
+            number: 1,
+            score: 3
+        }]
+    );
+    assert_eq!(boards.owned_names(None).collect::<Vec<_>>(), ["alice"]);
+    assert_eq!(boards.owned_names(Some(7)).count(), 0);
+}
+
+#[test]
+fn memory_store_is_shared_by_clones() {
+    let mut boards = Leaderboards::default();
+    boards.credit("alice", 1, 0);
+    let store = MemoryStore::default();
+    store.clone().save(&boards).unwrap();
+    assert_eq!(store.clone().load().unwrap(), boards);
+}
+
+#[test]
+fn json_file_store_loads_what_it_saved() {
+    let path = std::env::temp_dir().join(format!("leaderboards-{}.json", std::process::id()));
+    let mut store = JsonFileStore::new(&path);
+    assert_eq!(store.load().unwrap(), Leaderboards::default());
+    let mut boards = Leaderboards::default();
+    boards.credit("alice", 1, 0);
+    boards.end_round(1, boards.all_time.clone());
+    store.save(&boards).unwrap();
+    assert_eq!(JsonFileStore::new(&path).load().unwrap(), boards);
+    std::fs::write(&path, "{").unwrap();
+    assert!(store.load().is_err());
+    std::fs::remove_file(&path).unwrap();
+}
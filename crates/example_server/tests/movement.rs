@@ -0,0 +1,160 @@
+use bevy::math::Vec2;
+use example_server::{Catalog, MoleHistory, RewindStats};
+use example_shared::{MoleDef, MoleKind, Movement, Path};
+
+fn mole(path: Path) -> MoleDef {
+    MoleDef {
+        catalog_id: 0,
+        kind: MoleKind::Duration(10f32),
+        position: Vec2::new(100f32, 50f32),
+        start_time: 20f64,
+        path,
+    }
+}
+
+fn assert_near(a: Vec2, b: Vec2) {
+    assert!(a.distance(b) < 0.001, "{} is not {}", a, b);
+}
+
+#[test]
+fn paths_start_at_the_position() {
+    let paths = [
+        Path::Still,
+        Path::Linear {
+            to: Vec2::new(30f32, 0f32),
+            period: 2f32,
+        },
+        Path::Hop {
+            holes: vec![Vec2::ZERO, Vec2::new(0f32, 40f32)],
+            interval: 1f32,
+        },
+        Path::Dash {
+            to: Vec2::new(-50f32, 0f32),
+            duration: 0.5f32,
+        },
+    ];
+    for path in paths {
+        let mole = mole(path);
+        assert_near(mole.position_at(20f64), mole.position);
+        // Before the start too, the server may be ahead of the estimated clock.
+        assert_near(mole.position_at(0f64), mole.position);
+    }
+}
+
+#[test]
+fn linear_goes_back_and_forth() {
+    let mole = mole(Path::Linear {
+        to: Vec2::new(30f32, 0f32),
+        period: 2f32,
+    });
+    assert_near(mole.position_at(21f64), Vec2::new(130f32, 50f32));
+    assert_near(mole.position_at(21.5f64), Vec2::new(115f32, 50f32));
+    assert_near(mole.position_at(22f64), Vec2::new(100f32, 50f32));
+}
+
+#[test]
+fn circle_turns_around_the_position() {
+    let mole = mole(Path::Circle {
+        radius: 10f32,
+        period: 4f32,
+        phase: 0f32,
+    });
+    assert_near(mole.position_at(20f64), Vec2::new(110f32, 50f32));
+    assert_near(mole.position_at(21f64), Vec2::new(100f32, 60f32));
+    assert_near(mole.position_at(24f64), Vec2::new(110f32, 50f32));
+}
+
+#[test]
+fn hop_cycles_through_holes() {
+    let mole = mole(Path::Hop {
+        holes: vec![Vec2::ZERO, Vec2::new(0f32, 40f32)],
+        interval: 1f32,
+    });
+    assert_near(mole.position_at(21.5f64), Vec2::new(100f32, 90f32));
+    assert_near(mole.position_at(22.5f64), Vec2::new(100f32, 50f32));
+}
+
+#[test]
+fn dash_stays_at_the_end() {
+    let mole = mole(Path::Dash {
+        to: Vec2::new(-50f32, 0f32),
+        duration: 0.5f32,
+    });
+    assert_near(mole.position_at(20.25f64), Vec2::new(75f32, 50f32));
+    assert_near(mole.position_at(30f64), Vec2::new(50f32, 50f32));
+}
+
+#[test]
+fn history_keeps_replaced_paths_for_the_rewind() {
+    let still = mole(Path::Still);
+    let dash = mole(Path::Dash {
+        to: Vec2::new(-50f32, 0f32),
+        duration: 0.5f32,
+    });
+    let mut history = MoleHistory::new(20f64);
+    history.replace(still.clone(), 21f64, 0.5f32);
+    assert_eq!(history.def_at(19f64, &dash), None);
+    assert_eq!(history.def_at(20.5f64, &dash), Some(&still));
+    assert_eq!(history.def_at(21f64, &dash), Some(&dash));
+    // Older than the rewind of the next replacement.
+    history.replace(dash.clone(), 22f64, 0.5f32);
+    assert_eq!(history.def_at(20.5f64, &dash), Some(&dash));
+}
+
+#[test]
+fn catalog_movements() {
+    let catalog = Catalog::from_toml(
+        r#"
+[[kinds]]
+name = "spinner"
+movement = { type = "circle", radius = 40, period = 3 }
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        catalog.kinds[0].movement,
+        Movement::Circle {
+            radius: 40f32,
+            period: 3f32
+        }
+    );
+    let no_time = r#"
+[[kinds]]
+name = "spinner"
+movement = { type = "circle", radius = 40, period = 0 }
+"#;
+    assert!(Catalog::from_toml(no_time).is_err());
+    let negative = no_time
+        .replace("period = 0", "period = 3")
+        .replace("40", "-40");
+    assert!(Catalog::from_toml(&negative).is_err());
+    let hop = r#"
+[[kinds]]
+name = "hopper"
+movement = { type = "hop", holes = 3, distance = -1, interval = 1 }
+"#;
+    assert!(Catalog::from_toml(hop).is_err());
+    let flee = r#"
+[[kinds]]
+name = "coward"
+movement = { type = "flee", radius = 100, distance = 50, duration = 0.5, hits = 0 }
+"#;
+    assert!(Catalog::from_toml(flee).is_err());
+    let flee = flee.replace("hits = 0", "window = 2");
+    assert!(matches!(
+        Catalog::from_toml(&flee).unwrap().kinds[0].movement,
+        Movement::Flee { hits: 1, .. }
+    ));
+}
+
+#[test]
+fn rewind_rates_are_percents_of_hits() {
+    assert_eq!(RewindStats::default().changed_rate(), 0f64);
+    let stats = RewindStats {
+        hits: 8,
+        rewound: 4,
+        changed: 1,
+    };
+    assert_eq!(stats.rewound_rate(), 50f64);
+    assert_eq!(stats.changed_rate(), 12.5f64);
+}
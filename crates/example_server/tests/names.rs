@@ -0,0 +1,39 @@
+use example_server::NameRules;
+use example_shared::NameRejection;
+
+#[test]
+fn names_are_trimmed_and_checked() {
+    let rules = NameRules::default();
+    assert_eq!(
+        rules.check(" ab_1-é ", [].into_iter()),
+        Ok("ab_1-é".to_string())
+    );
+    assert_eq!(rules.check("", [].into_iter()), Err(NameRejection::Empty));
+    assert_eq!(
+        rules.check("abcdefg", [].into_iter()),
+        Err(NameRejection::TooLong)
+    );
+    assert_eq!(
+        rules.check("a b", [].into_iter()),
+        Err(NameRejection::InvalidCharacters)
+    );
+}
+
+#[test]
+fn taken_names_are_compared_in_lowercase() {
+    let rules = NameRules::default();
+    assert_eq!(
+        rules.check("Alice", ["bob", "alice"].into_iter()),
+        Err(NameRejection::Taken)
+    );
+}
+
+#[test]
+fn filter_sees_names_in_lowercase() {
+    let rules = NameRules::with_filter(|name| name == "admin");
+    assert_eq!(
+        rules.check("Admin", [].into_iter()),
+        Err(NameRejection::Offensive)
+    );
+    assert!(rules.check("admin2", [].into_iter()).is_ok());
+}
@@ -0,0 +1,1025 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::{atomic::Ordering, mpsc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::{app::AppExit, ecs::event::Events, prelude::*};
+use example_server::{
+    default_name, split_points, today, Catalog, GamePlugin, JsonFileStore, KillCredit,
+    LeaderboardStore, Leaderboards, MemoryStore, ServerConfig, ShutdownRequest,
+};
+use example_shared::{
+    public_key, sign_challenge, Board, ClientMessage, ExistingMoles, Mole, MoleKind, MoleKindDef,
+    Movement, NameRejection, Path, PlayerRank, RoundPhase, Scores, ServerMessage, SpawnMole,
+    TopPlayers, UpdateScores,
+};
+use litlnet_server_bevy::RComServer;
+use litlnet_trait::{
+    replication::WorldState, ClientId, Communication, Packet, Replicable, Rpc, RpcError,
+};
+use litlnet_websocket::WebsocketClient;
+use litlnet_websocket_server::ComServer;
+use serde_json::Value;
+
+const SEED: u64 = 42;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A catalog of a single kind, always spawned.
+fn catalog(kind: MoleKindDef) -> Catalog {
+    Catalog {
+        kinds: vec![kind],
+        timeline: vec![],
+    }
+}
+
+/// Moles only disappear when hit once, unless a test changes the catalog, in a round starting at once.
+fn game(seed: u64) -> GamePlugin {
+    let game = GamePlugin::new(
+        ServerConfig {
+            bind_address: "127.0.0.1".to_string(),
+            port: 0,
+            seed: Some(seed),
+            countdown_duration: 0f32,
+            round_duration: 3600f32,
+            ..Default::default()
+        },
+        catalog(MoleKindDef {
+            name: "test".to_string(),
+            lifetime: 3600f32,
+            ..Default::default()
+        }),
+        Box::<MemoryStore>::default(),
+    );
+    game.unwrap()
+}
+
+/// `game` with the leaderboards of `store`.
+fn with_store(game: GamePlugin, store: impl LeaderboardStore + 'static) -> GamePlugin {
+    GamePlugin::new(game.config, game.catalog, Box::new(store)).unwrap()
+}
+
+/// A game running on another thread, shut down when dropped.
+struct TestServer {
+    port: u16,
+    shutdown: ShutdownRequest,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.shutdown.0.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Runs the game on an ephemeral port until it requests to exit.
+fn start_server(game: GamePlugin) -> TestServer {
+    let (port_sender, port_receiver) = mpsc::channel();
+    let shutdown = ShutdownRequest::default();
+    let server_shutdown = shutdown.clone();
+    let thread = thread::spawn(move || {
+        let mut app = App::new();
+        // Before the game, which only inits it.
+        app.insert_resource(server_shutdown);
+        app.add_plugins(game);
+        app.finish();
+        app.cleanup();
+        let mut is_port_sent = false;
+        while app.world.resource::<Events<AppExit>>().is_empty() {
+            app.update();
+            if !is_port_sent {
+                if let Some(com) = app.world.get_resource::<RComServer<ComServer>>() {
+                    let _ = port_sender.send(com.server.local_addr().unwrap().port());
+                    is_port_sent = true;
+                }
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    });
+    TestServer {
+        port: port_receiver.recv_timeout(TIMEOUT).unwrap(),
+        shutdown,
+        thread: Some(thread),
+    }
+}
+
+/// A player speaking the protocol of the bevy plugins.
+struct TestClient {
+    com: WebsocketClient,
+    id: Option<ClientId>,
+    history: BTreeMap<u64, WorldState>,
+    state: WorldState,
+    /// Whether each received snapshot was a full one.
+    snapshots: Vec<bool>,
+    messages: VecDeque<ServerMessage>,
+    responses: HashMap<u64, Result<Value, RpcError>>,
+    next_request_id: u64,
+}
+
+impl TestClient {
+    fn connect(port: u16) -> Self {
+        let mut com = WebsocketClient::connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+        // The server starts the session on the first packet.
+        com.send(&Packet::<()>::TimeRequest { client_time: 0f64 })
+            .unwrap();
+        let mut client = Self {
+            com,
+            id: None,
+            history: BTreeMap::new(),
+            state: WorldState::default(),
+            snapshots: vec![],
+            messages: VecDeque::new(),
+            responses: HashMap::new(),
+            next_request_id: 0,
+        };
+        client.wait_for(|client| client.id);
+        client
+    }
+
+    fn send(&mut self, message: ClientMessage) {
+        self.com.send(&Packet::Message(message)).unwrap();
+    }
+
+    fn poll(&mut self) {
+        let packets = self.com.receive::<Packet<ServerMessage>>().unwrap();
+        for packet in packets.into_iter().flatten() {
+            match packet {
+                Packet::Message(message) => self.messages.push_back(message),
+                Packet::Session { client_id, .. } => self.id = Some(client_id),
+                Packet::Response { id, result } => {
+                    self.responses.insert(id, result);
+                }
+                Packet::Snapshot(snapshot) => {
+                    let base = match snapshot.base {
+                        Some(base) => self.history.get(&base).unwrap().clone(),
+                        None => WorldState::default(),
+                    };
+                    self.snapshots.push(snapshot.base.is_none());
+                    self.state = base.apply(&snapshot);
+                    self.history.insert(snapshot.tick, self.state.clone());
+                    self.com
+                        .send(&Packet::<()>::SnapshotAck {
+                            tick: snapshot.tick,
+                        })
+                        .unwrap();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn request<T: Rpc>(&mut self, request: &T) -> T::Response {
+        let id = self.next_request_id;
+        self.next_request_id += 1;
+        self.com
+            .send(&Packet::<()>::Request {
+                id,
+                method: T::METHOD.to_string(),
+                payload: serde_json::to_value(request).unwrap(),
+            })
+            .unwrap();
+        let response = self.wait_for(|client| client.responses.remove(&id));
+        serde_json::from_value(response.unwrap()).unwrap()
+    }
+
+    /// Polls until `condition` returns something.
+    fn wait_for<T>(&mut self, mut condition: impl FnMut(&mut Self) -> Option<T>) -> T {
+        let start = Instant::now();
+        loop {
+            self.poll();
+            if let Some(result) = condition(self) {
+                return result;
+            }
+            assert!(start.elapsed() < TIMEOUT, "timed out");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn moles(&self) -> Vec<Mole> {
+        self.state
+            .entities
+            .values()
+            .filter_map(|components| components.get(Mole::NAME))
+            .map(|mole| serde_json::from_value(mole.clone()).unwrap())
+            .collect()
+    }
+
+    fn scores(&self) -> Option<Scores> {
+        self.state
+            .resources
+            .get(Scores::NAME)
+            .map(|scores| serde_json::from_value(scores.clone()).unwrap())
+    }
+
+    fn wait_for_mole(&mut self) -> Mole {
+        self.wait_for_mole_except(&[])
+    }
+
+    /// A mole no other is close enough to be hit instead, the server kills the first it finds.
+    fn wait_for_mole_except(&mut self, excluded: &[usize]) -> Mole {
+        self.wait_for(|client| {
+            let moles = client.moles();
+            moles
+                .iter()
+                .filter(|mole| !excluded.contains(&mole.id))
+                .find(|mole| {
+                    moles.iter().all(|other| {
+                        other.id == mole.id
+                            || other.def.position.distance(mole.def.position) >= 50f32
+                    })
+                })
+                .cloned()
+        })
+    }
+
+    fn wait_for_escaped_mole(&mut self, id: usize) {
+        self.wait_for(|client| {
+            client
+                .messages
+                .contains(&ServerMessage::EscapedMole(id))
+                .then_some(())
+        })
+    }
+
+    /// The remaining hits and who hit it.
+    fn wait_for_damaged_mole(&mut self, id: usize) -> (u32, usize) {
+        self.wait_for(|client| {
+            client.messages.iter().find_map(|message| match message {
+                ServerMessage::MoleDamaged {
+                    mole_id,
+                    remaining,
+                    by,
+                } if *mole_id == id => Some((*remaining, *by)),
+                _ => None,
+            })
+        })
+    }
+
+    fn wait_for_challenge(&mut self) -> String {
+        self.wait_for(|client| {
+            client.messages.iter().find_map(|message| match message {
+                ServerMessage::Challenge(challenge) => Some(challenge.clone()),
+                _ => None,
+            })
+        })
+    }
+
+    /// The account and its name, `None` if it failed.
+    fn wait_for_authentication(&mut self) -> Option<(u64, Option<String>)> {
+        self.wait_for(|client| {
+            let answer = client.messages.iter().find_map(|message| match message {
+                ServerMessage::Authenticated { account, name } => {
+                    Some(Some((*account, name.clone())))
+                }
+                ServerMessage::AuthenticationFailed => Some(None),
+                _ => None,
+            });
+            client.messages.retain(|message| {
+                !matches!(
+                    message,
+                    ServerMessage::Authenticated { .. } | ServerMessage::AuthenticationFailed
+                )
+            });
+            answer
+        })
+    }
+
+    fn has_mole_messages(&self) -> bool {
+        self.messages.iter().any(|message| {
+            matches!(
+                message,
+                ServerMessage::DeadMole { .. }
+                    | ServerMessage::EscapedMole(_)
+                    | ServerMessage::MoleDamaged { .. }
+            )
+        })
+    }
+
+    fn wait_for_dead_mole(&mut self, id: usize) -> usize {
+        self.wait_for(|client| {
+            client.messages.iter().find_map(|message| match message {
+                ServerMessage::DeadMole {
+                    mole_id,
+                    player_killer_id,
+                } if *mole_id == id => Some(*player_killer_id),
+                _ => None,
+            })
+        })
+    }
+}
+
+#[test]
+fn dropped_server_stops() {
+    let server = start_server(game(SEED));
+    let port = server.port;
+    TestClient::connect(port);
+    drop(server);
+    assert!(WebsocketClient::connect(&format!("ws://127.0.0.1:{}", port)).is_err());
+}
+
+#[test]
+fn spawned_moles_are_replicated_to_every_client() {
+    let server = start_server(game(SEED));
+    let mut alice = TestClient::connect(server.port);
+    let mut bob = TestClient::connect(server.port);
+    let mole = alice.wait_for_mole();
+    let mole_for_bob = bob.wait_for(|bob| bob.moles().into_iter().find(|m| m.id == mole.id));
+    assert_eq!(mole, mole_for_bob);
+    // Also announced, for clients which don't replicate.
+    let spawned = bob.wait_for(|bob| {
+        bob.messages.iter().find_map(|message| match message {
+            ServerMessage::Spawn(spawned) if spawned.id == mole.id => Some(spawned.clone()),
+            _ => None,
+        })
+    });
+    assert_eq!(
+        spawned,
+        SpawnMole {
+            id: mole.id,
+            def: mole.def
+        }
+    );
+}
+
+#[test]
+fn hit_kills_the_mole_and_credits_the_hitter() {
+    let server = start_server(game(SEED));
+    let mut alice = TestClient::connect(server.port);
+    let mut bob = TestClient::connect(server.port);
+    let mole = alice.wait_for_mole();
+    alice.send(ClientMessage::HitPosition(mole.def.position));
+    let alice_id = alice.id.unwrap().0;
+    assert_eq!(alice.wait_for_dead_mole(mole.id), alice_id);
+    assert_eq!(bob.wait_for_dead_mole(mole.id), alice_id);
+    for client in [&mut alice, &mut bob] {
+        client.wait_for(|client| client.moles().iter().all(|m| m.id != mole.id).then_some(()));
+    }
+}
+
+#[test]
+fn missed_hit_kills_nothing() {
+    let server = start_server(game(SEED));
+    let mut alice = TestClient::connect(server.port);
+    let mole = alice.wait_for_mole();
+    alice.send(ClientMessage::HitPosition(
+        mole.def.position + Vec2::new(10_000f32, 0f32),
+    ));
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(1) {
+        alice.poll();
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert!(!alice.has_mole_messages());
+    assert!(alice.moles().iter().any(|m| m.id == mole.id));
+}
+
+#[test]
+fn named_players_are_ranked_by_kills() {
+    let server = start_server(game(SEED));
+    let mut alice = TestClient::connect(server.port);
+    let mut bob = TestClient::connect(server.port);
+    alice.send(ClientMessage::SetName("alice".to_string()));
+    let mut killed = vec![];
+    for _ in 0..2 {
+        // The despawn of the previous one may not be replicated yet.
+        let mole = alice.wait_for_mole_except(&killed);
+        alice.send(ClientMessage::HitPosition(mole.def.position));
+        alice.wait_for_dead_mole(mole.id);
+        killed.push(mole.id);
+    }
+    let scores = bob.wait_for(|bob| {
+        bob.scores()
+            .filter(|scores| scores.best_players.iter().any(|p| p.score == 2))
+    });
+    assert_eq!(scores.best_players.len(), 1);
+    assert_eq!(scores.best_players[0].name, "alice");
+    assert_eq!(scores.best_players[0].score, 2);
+    let update = bob.wait_for(|bob| {
+        bob.messages.iter().rev().find_map(|message| match message {
+            ServerMessage::UpdateScores(update) => Some(update.clone()),
+            _ => None,
+        })
+    });
+    assert_eq!(
+        update,
+        UpdateScores {
+            best_players: scores.best_players
+        }
+    );
+}
+
+#[test]
+fn scores_are_only_sent_when_they_change() {
+    let server = start_server(game(SEED));
+    let key = "ab".repeat(32);
+    let mut alice = TestClient::connect(server.port);
+    let challenge = alice.wait_for_challenge();
+    alice.send(ClientMessage::Register {
+        public_key: public_key(&key).unwrap(),
+        signature: sign_challenge(&key, &challenge).unwrap(),
+    });
+    alice.wait_for_authentication().unwrap();
+    alice.send(ClientMessage::SetName("alice".to_string()));
+    alice.wait_for(|alice| {
+        alice
+            .messages
+            .contains(&ServerMessage::NameAccepted("alice".to_string()))
+            .then_some(())
+    });
+    // Answered after the updates of the name.
+    alice.request(&TopPlayers {
+        board: Board::AllTime,
+        count: 10,
+    });
+    let is_sent = |message: &ServerMessage| matches!(message, ServerMessage::UpdateScores(_));
+    assert!(!alice.messages.iter().any(is_sent));
+
+    let mole = alice.wait_for_mole();
+    alice.send(ClientMessage::HitPosition(mole.def.position));
+    alice.wait_for(|alice| alice.messages.iter().find(|m| is_sent(m)).map(|_| ()));
+}
+
+#[test]
+fn players_are_ranked_by_session_and_told_their_rank() {
+    let server = start_server(game(SEED));
+    let mut alice = TestClient::connect(server.port);
+    // Unnamed.
+    let mut bob = TestClient::connect(server.port);
+    alice.send(ClientMessage::SetName("alice".to_string()));
+    let mut killed = vec![];
+    for is_alice in [true, true, false] {
+        let hitter = if is_alice { &mut alice } else { &mut bob };
+        let mole = hitter.wait_for_mole_except(&killed);
+        hitter.send(ClientMessage::HitPosition(mole.def.position));
+        hitter.wait_for_dead_mole(mole.id);
+        killed.push(mole.id);
+    }
+    let scores = bob.wait_for(|bob| bob.scores().filter(|scores| scores.best_players.len() == 2));
+    let ranked: Vec<_> = scores
+        .best_players
+        .iter()
+        .map(|rank| (rank.player_id, rank.name.as_str(), rank.score))
+        .collect();
+    let bob_name = default_name(bob.id.unwrap());
+    assert_eq!(
+        ranked,
+        [
+            (Some(alice.id.unwrap().0), "alice", 2),
+            (Some(bob.id.unwrap().0), bob_name.as_str(), 1)
+        ]
+    );
+    let own_rank = |client: &mut TestClient, score| {
+        client.wait_for(|client| {
+            client.messages.iter().find_map(|message| match message {
+                ServerMessage::OwnRank { rank, score: s } if *s == score => Some(*rank),
+                _ => None,
+            })
+        })
+    };
+    assert_eq!(own_rank(&mut alice, 2), Some(1));
+    assert_eq!(own_rank(&mut bob, 1), Some(2));
+}
+
+#[test]
+fn names_are_checked_and_unique_among_players() {
+    let mut game = game(SEED);
+    game.config.banned_words = vec!["Darn".to_string()];
+    let server = start_server(game);
+    let mut alice = TestClient::connect(server.port);
+    let mut bob = TestClient::connect(server.port);
+    let mut carol = TestClient::connect(server.port);
+    let answer = |client: &mut TestClient, name: &str| {
+        client.messages.clear();
+        client.send(ClientMessage::SetName(name.to_string()));
+        client.wait_for(|client| {
+            client.messages.iter().find_map(|message| match message {
+                ServerMessage::NameAccepted(name) => Some(Ok(name.clone())),
+                ServerMessage::NameRejected(rejection) => Some(Err(*rejection)),
+                _ => None,
+            })
+        })
+    };
+    assert_eq!(answer(&mut alice, " alice "), Ok("alice".to_string()));
+    // Their own name again.
+    assert_eq!(answer(&mut alice, "alice"), Ok("alice".to_string()));
+    assert_eq!(answer(&mut bob, "ALICE"), Err(NameRejection::Taken));
+    assert_eq!(answer(&mut bob, "bobbobbob"), Err(NameRejection::TooLong));
+    assert_eq!(
+        answer(&mut bob, "b@b"),
+        Err(NameRejection::InvalidCharacters)
+    );
+    assert_eq!(answer(&mut carol, "  "), Err(NameRejection::Empty));
+    assert_eq!(answer(&mut carol, "xdarnx"), Err(NameRejection::Offensive));
+    assert_eq!(answer(&mut carol, "carol"), Ok("carol".to_string()));
+}
+
+#[test]
+fn late_joiner_gets_all_existing_moles() {
+    let server = start_server(game(SEED));
+    let mut alice = TestClient::connect(server.port);
+    let moles = alice.wait_for(|alice| {
+        let moles = alice.moles();
+        (moles.len() >= 3).then_some(moles)
+    });
+    let mut late = TestClient::connect(server.port);
+    late.wait_for(|late| (!late.snapshots.is_empty()).then_some(()));
+    assert!(late.snapshots[0], "the first snapshot is a full one");
+    // Moles only disappear when hit.
+    let late_moles = late.moles();
+    for mole in &moles {
+        assert!(late_moles.contains(mole));
+    }
+    let all = late.request(&ExistingMoles);
+    assert_eq!(all.local_player_id, late.id.unwrap().0);
+    for mole in moles {
+        assert!(all.moles.contains(&SpawnMole {
+            id: mole.id,
+            def: mole.def
+        }));
+    }
+    // The next ones are deltas from it, acked or not.
+    assert_eq!(late.snapshots.iter().filter(|full| **full).count(), 1);
+}
+
+#[test]
+fn same_seed_spawns_same_moles() {
+    let first_server = start_server(game(SEED));
+    let second_server = start_server(game(SEED));
+    let mut first = TestClient::connect(first_server.port);
+    let mut second = TestClient::connect(second_server.port);
+    let mut first_mole = first.wait_for(|c| c.moles().into_iter().find(|m| m.id == 0));
+    let second_mole = second.wait_for(|c| c.moles().into_iter().find(|m| m.id == 0));
+    // Each server has its own clock.
+    first_mole.def.start_time = second_mole.def.start_time;
+    assert_eq!(first_mole, second_mole);
+}
+
+#[test]
+fn unhit_mole_escapes() {
+    let server = start_server(GamePlugin {
+        catalog: catalog(MoleKindDef {
+            lifetime: 0.5f32,
+            ..Default::default()
+        }),
+        ..game(SEED)
+    });
+    let mut alice = TestClient::connect(server.port);
+    let mole = alice.wait_for_mole();
+    alice.wait_for_escaped_mole(mole.id);
+    alice.wait_for(|alice| alice.moles().iter().all(|m| m.id != mole.id).then_some(()));
+    assert!(!alice
+        .messages
+        .iter()
+        .any(|message| matches!(message, ServerMessage::DeadMole { .. })));
+}
+
+/// Alice hits a mole once it escaped, as seen just before, returns whether she killed it.
+fn hit_escaped_mole(max_rewind: f32) -> bool {
+    let mut game = GamePlugin {
+        catalog: catalog(MoleKindDef {
+            lifetime: 0.5f32,
+            ..Default::default()
+        }),
+        ..game(SEED)
+    };
+    game.config.max_rewind = max_rewind;
+    let server = start_server(game);
+    let mut alice = TestClient::connect(server.port);
+    let mole = alice.wait_for_mole();
+    alice.wait_for_escaped_mole(mole.id);
+    alice.send(ClientMessage::HitAt {
+        position: mole.def.position,
+        time: mole.def.start_time + 0.4f64,
+    });
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(1) {
+        alice.poll();
+        let is_killed = alice.messages.iter().any(|message| {
+            matches!(message, ServerMessage::DeadMole { mole_id, .. } if *mole_id == mole.id)
+        });
+        if is_killed {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    false
+}
+
+#[test]
+fn late_hit_is_checked_when_the_player_saw_the_mole() {
+    assert!(hit_escaped_mole(1f32));
+}
+
+#[test]
+fn late_hit_is_not_rewound_past_the_limit() {
+    assert!(!hit_escaped_mole(0f32));
+}
+
+/// Alice and Bob hit the same mole needing two hits and worth 3 points, returns the ranking.
+fn hit_tough_mole_together(kill_credit: KillCredit) -> Scores {
+    let mut game = game(SEED);
+    game.config.kill_credit = kill_credit;
+    game.catalog.kinds[0].hits = 2;
+    game.catalog.kinds[0].points = 3;
+    let server = start_server(game);
+    let mut alice = TestClient::connect(server.port);
+    let mut bob = TestClient::connect(server.port);
+    alice.send(ClientMessage::SetName("alice".to_string()));
+    bob.send(ClientMessage::SetName("bob".to_string()));
+    let mole = alice.wait_for_mole();
+    assert_eq!(mole.def.kind, MoleKind::HitCount(2));
+    alice.send(ClientMessage::HitPosition(mole.def.position));
+    let alice_id = alice.id.unwrap().0;
+    assert_eq!(bob.wait_for_damaged_mole(mole.id), (1, alice_id));
+    bob.wait_for(|bob| {
+        bob.moles()
+            .into_iter()
+            .find(|m| m.id == mole.id && m.def.kind == MoleKind::HitCount(1))
+    });
+    bob.send(ClientMessage::HitPosition(mole.def.position));
+    assert_eq!(alice.wait_for_dead_mole(mole.id), bob.id.unwrap().0);
+    alice.wait_for(|alice| {
+        alice
+            .scores()
+            .filter(|scores| !scores.best_players.is_empty())
+    })
+}
+
+#[test]
+fn tough_mole_is_credited_to_the_last_hit() {
+    let scores = hit_tough_mole_together(KillCredit::LastHit);
+    assert_eq!(scores.best_players.len(), 1);
+    assert_eq!(scores.best_players[0].name, "bob");
+    assert_eq!(scores.best_players[0].score, 3);
+}
+
+#[test]
+fn tough_mole_is_split_between_contributors() {
+    let scores = hit_tough_mole_together(KillCredit::Contributors);
+    let ranks: Vec<_> = scores
+        .best_players
+        .iter()
+        .map(|p| (p.name.as_str(), p.score))
+        .collect();
+    // The point left goes to the first hit.
+    assert_eq!(ranks, [("alice", 2), ("bob", 1)]);
+}
+
+#[test]
+fn points_are_split_by_hits() {
+    let (alice, bob, carol) = (ClientId(1), ClientId(2), ClientId(3));
+    assert_eq!(
+        split_points(3, &[alice, bob, carol]),
+        [(alice, 1), (bob, 1), (carol, 1)]
+    );
+    assert_eq!(split_points(5, &[bob, alice, bob]), [(bob, 3), (alice, 2)]);
+    assert_eq!(split_points(1, &[alice, bob, bob]), [(alice, 0), (bob, 1)]);
+}
+
+/// Alice hits `hits` times close to an isolated mole fleeing from clusters of `hits` hits.
+fn scare_fleeing_mole(hits: usize) {
+    let server = start_server(GamePlugin {
+        catalog: catalog(MoleKindDef {
+            lifetime: 3600f32,
+            hit_radius: 10f32,
+            movement: Movement::Flee {
+                radius: 100f32,
+                distance: 50f32,
+                duration: 0.5f32,
+                hits,
+                window: 5f32,
+            },
+            ..Default::default()
+        }),
+        ..game(SEED)
+    });
+    let mut alice = TestClient::connect(server.port);
+    // Isolated, so only this one flees.
+    let mole = alice.wait_for(|alice| {
+        let moles = alice.moles();
+        moles
+            .iter()
+            .find(|mole| {
+                moles.iter().all(|other| {
+                    other.id == mole.id || other.def.position.distance(mole.def.position) >= 200f32
+                })
+            })
+            .cloned()
+    });
+    let hit = || ClientMessage::HitPosition(mole.def.position + Vec2::new(20f32, 0f32));
+    for _ in 1..hits {
+        alice.send(hit());
+    }
+    // Not a cluster yet.
+    thread::sleep(Duration::from_millis(200));
+    alice.poll();
+    assert!(alice
+        .moles()
+        .iter()
+        .any(|m| m.id == mole.id && m.def.path == Path::Still));
+    alice.send(hit());
+    let fled = alice.wait_for(|alice| {
+        alice
+            .moles()
+            .into_iter()
+            .find(|m| m.id == mole.id && m.def.path != Path::Still)
+    });
+    assert_eq!(fled.def.position, mole.def.position);
+    assert_eq!(
+        fled.def.path,
+        Path::Dash {
+            to: Vec2::new(-50f32, 0f32),
+            duration: 0.5f32
+        }
+    );
+    assert!(!alice.has_mole_messages());
+}
+
+#[test]
+fn fleeing_mole_dashes_away_from_a_close_hit() {
+    scare_fleeing_mole(1);
+}
+
+#[test]
+fn fleeing_mole_dashes_away_from_a_cluster_of_hits() {
+    scare_fleeing_mole(3);
+}
+
+#[test]
+fn rounds_go_through_phases_and_announce_winners() {
+    let mut game = game(SEED);
+    game.config.countdown_duration = 0.2f32;
+    game.config.round_duration = 2f32;
+    game.config.results_duration = 1f32;
+    let server = start_server(game);
+    let mut alice = TestClient::connect(server.port);
+    alice.send(ClientMessage::SetName("alice".to_string()));
+    let mole = alice.wait_for_mole();
+    alice.send(ClientMessage::HitPosition(mole.def.position));
+    alice.wait_for_dead_mole(mole.id);
+    let winners = alice.wait_for(|alice| {
+        alice.messages.iter().find_map(|message| match message {
+            ServerMessage::RoundWinners(winners) => Some(winners.clone()),
+            _ => None,
+        })
+    });
+    assert_eq!(winners.len(), 1);
+    assert_eq!((winners[0].name.as_str(), winners[0].score), ("alice", 1));
+    alice.wait_for(|alice| alice.moles().is_empty().then_some(()));
+    // Until the next round starts.
+    let scores = alice.wait_for(|alice| {
+        alice
+            .scores()
+            .filter(|scores| scores.best_players.is_empty())
+    });
+    assert_eq!(winners[0].player_id, Some(alice.id.unwrap().0));
+    assert_eq!(
+        (scores.all_time[0].name.as_str(), scores.all_time[0].score),
+        ("alice", 1)
+    );
+    let phases: Vec<_> = alice
+        .messages
+        .iter()
+        .filter_map(|message| match message {
+            ServerMessage::RoundState { phase, .. } => Some(*phase),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        phases,
+        [
+            // On joining.
+            RoundPhase::Lobby,
+            RoundPhase::Countdown,
+            RoundPhase::Playing,
+            RoundPhase::Results,
+            RoundPhase::Lobby,
+            RoundPhase::Countdown
+        ]
+    );
+}
+
+#[test]
+fn leaderboards_are_saved_and_served_after_a_restart() {
+    let path = std::env::temp_dir().join(format!("leaderboard-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut first = with_store(game(SEED), JsonFileStore::new(&path));
+    first.config.leaderboard_flush_interval = 0.05f32;
+    first.config.round_duration = 1f32;
+    let first = start_server(first);
+    let mut alice = TestClient::connect(first.port);
+    alice.send(ClientMessage::SetName("alice".to_string()));
+    let mole = alice.wait_for_mole();
+    alice.send(ClientMessage::HitPosition(mole.def.position));
+    alice.wait_for_dead_mole(mole.id);
+    let expected = vec![PlayerRank {
+        player_id: None,
+        name: "alice".to_string(),
+        score: 1,
+    }];
+    // Saved once the round ended.
+    alice.wait_for(|_| {
+        JsonFileStore::new(&path)
+            .load()
+            .ok()
+            .filter(|leaderboards| !leaderboards.rounds.is_empty())
+    });
+
+    let restarted = start_server(with_store(game(SEED), JsonFileStore::new(&path)));
+    let mut bob = TestClient::connect(restarted.port);
+    for board in [
+        Board::AllTime,
+        Board::Daily,
+        Board::LastRound,
+        Board::Round(1),
+    ] {
+        assert_eq!(bob.request(&TopPlayers { board, count: 10 }), expected);
+    }
+    let none = bob.request(&TopPlayers {
+        board: Board::Round(2),
+        count: 10,
+    });
+    assert!(none.is_empty());
+    let empty = bob.request(&TopPlayers {
+        board: Board::AllTime,
+        count: 0,
+    });
+    assert!(empty.is_empty());
+    let scores = bob.wait_for(|bob| bob.scores());
+    assert_eq!(scores.all_time, expected);
+    let _ = std::fs::remove_file(&path);
+}
+
+/// Saves once the test releases it.
+struct SlowStore {
+    release: Mutex<mpsc::Receiver<()>>,
+    saved: MemoryStore,
+}
+
+impl LeaderboardStore for SlowStore {
+    fn load(&mut self) -> Result<Leaderboards, String> {
+        Ok(Leaderboards::default())
+    }
+
+    fn save(&mut self, boards: &Leaderboards) -> Result<(), String> {
+        let _ = self.release.get_mut().unwrap().recv();
+        self.saved.save(boards)
+    }
+}
+
+#[test]
+fn leaderboards_are_saved_off_the_game_loop() {
+    let (release, released) = mpsc::channel();
+    let saved = MemoryStore::default();
+    let store = SlowStore {
+        release: Mutex::new(released),
+        saved: saved.clone(),
+    };
+    let mut game = with_store(game(SEED), store);
+    game.config.leaderboard_flush_interval = 0.01f32;
+    let server = start_server(game);
+    let mut alice = TestClient::connect(server.port);
+    alice.send(ClientMessage::SetName("alice".to_string()));
+    // Played while the first save waits.
+    let mole = alice.wait_for_mole();
+    alice.send(ClientMessage::HitPosition(mole.def.position));
+    alice.wait_for_dead_mole(mole.id);
+    drop(release);
+    let best = alice.wait_for(|_| {
+        let boards = saved.boards.lock().unwrap();
+        boards.top(Board::AllTime, 1, today()).pop()
+    });
+    assert_eq!((best.name.as_str(), best.score), ("alice", 1));
+}
+
+/// The challenges sent to the client, the last one first.
+fn challenges(client: &TestClient) -> Vec<String> {
+    client
+        .messages
+        .iter()
+        .rev()
+        .filter_map(|message| match message {
+            ServerMessage::Challenge(challenge) => Some(challenge.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn registered_keys_only_sign_in_with_their_account() {
+    let saved = MemoryStore::default();
+    let mut game = with_store(game(SEED), saved.clone());
+    game.config.leaderboard_flush_interval = 0.05f32;
+    let server = start_server(game);
+    let key = "ab".repeat(32);
+    let register = |challenge: &str| ClientMessage::Register {
+        public_key: public_key(&key).unwrap(),
+        signature: sign_challenge(&key, challenge).unwrap(),
+    };
+    let mut alice = TestClient::connect(server.port);
+    let challenge = alice.wait_for_challenge();
+    alice.send(register(&challenge));
+    let (account, _) = alice.wait_for_authentication().unwrap();
+
+    // Not signed by the secret key.
+    let mut eve = TestClient::connect(server.port);
+    let challenge = eve.wait_for_challenge();
+    eve.send(ClientMessage::Register {
+        public_key: public_key(&"cd".repeat(32)).unwrap(),
+        signature: sign_challenge(&key, &challenge).unwrap(),
+    });
+    assert_eq!(eve.wait_for_authentication(), None);
+    // A failed authentication gets a new challenge.
+    eve.send(ClientMessage::Authenticate {
+        account,
+        signature: "00".repeat(64),
+    });
+    assert_eq!(eve.wait_for_authentication(), None);
+    let challenge = eve.wait_for(|eve| {
+        challenges(eve)
+            .into_iter()
+            .next()
+            .filter(|last| *last != challenge)
+    });
+    // Registering a known key again doesn't sign in, even signed.
+    eve.send(register(&challenge));
+    assert_eq!(eve.wait_for_authentication(), None);
+
+    let accounts = alice.wait_for(|_| {
+        let boards = saved.boards.lock().unwrap();
+        Some(boards.accounts.clone()).filter(|accounts| !accounts.is_empty())
+    });
+    assert_eq!(accounts.len(), 1);
+    assert_eq!(accounts[&account].public_key, public_key(&key).unwrap());
+}
+
+#[test]
+fn accounts_keep_their_name_and_scores_across_sessions() {
+    let mut game = game(SEED);
+    game.config.session_grace = 0.1f32;
+    let server = start_server(game);
+    let key = "ab".repeat(32);
+    let sign = |challenge: &str| sign_challenge(&key, challenge).unwrap();
+    let mut alice = TestClient::connect(server.port);
+    let challenge = alice.wait_for_challenge();
+    alice.send(ClientMessage::Register {
+        public_key: public_key(&key).unwrap(),
+        signature: sign(&challenge),
+    });
+    let (account, name) = alice.wait_for_authentication().unwrap();
+    assert_eq!(name, None);
+    alice.send(ClientMessage::SetName("alice".to_string()));
+    let mole = alice.wait_for_mole();
+    alice.send(ClientMessage::HitPosition(mole.def.position));
+    alice.wait_for_dead_mole(mole.id);
+    drop(alice);
+
+    let mut bob = TestClient::connect(server.port);
+    // Once the session of Alice ended.
+    thread::sleep(Duration::from_millis(500));
+    bob.send(ClientMessage::SetName("Alice".to_string()));
+    bob.wait_for(|bob| {
+        bob.messages
+            .contains(&ServerMessage::NameRejected(NameRejection::Taken))
+            .then_some(())
+    });
+    let challenge = bob.wait_for_challenge();
+    bob.send(ClientMessage::Authenticate {
+        account,
+        signature: sign_challenge(&"cd".repeat(32), &challenge).unwrap(),
+    });
+    assert_eq!(bob.wait_for_authentication(), None);
+    // The challenge is answered once.
+    bob.send(ClientMessage::Authenticate {
+        account,
+        signature: sign(&challenge),
+    });
+    assert_eq!(bob.wait_for_authentication(), None);
+
+    let mut alice = TestClient::connect(server.port);
+    let challenge = alice.wait_for_challenge();
+    alice.send(ClientMessage::Authenticate {
+        account,
+        signature: sign(&challenge),
+    });
+    assert_eq!(
+        alice.wait_for_authentication(),
+        Some((account, Some("alice".to_string())))
+    );
+    let top = alice.request(&TopPlayers {
+        board: Board::AllTime,
+        count: 10,
+    });
+    assert_eq!(
+        top,
+        [PlayerRank {
+            player_id: None,
+            name: "alice".to_string(),
+            score: 1,
+        }]
+    );
+}
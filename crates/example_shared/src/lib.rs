@@ -1,11 +1,85 @@
-use bevy::math::Vec2;
+use bevy::{
+    ecs::{component::Component, system::Resource},
+    math::Vec2,
+};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use litlnet_trait::{Replicable, Rpc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize, Debug, PartialEq)]
 pub enum ClientMessage {
+    /// A hit on the moles as they are when it arrives.
     HitPosition(Vec2),
-    RequestAllExistingMoles,
+    /// A hit on the moles as they were at `time`, the server time the player saw them at.
+    ///
+    /// The server rewinds to it up to its `max_rewind`, so lag doesn't make players miss.
+    HitAt {
+        position: Vec2,
+        time: f64,
+    },
     SetName(String),
+    /// Creates an account for the [`public_key`] of the player, with the [`sign_challenge`] of
+    /// [`ServerMessage::Challenge`] proving it has the secret key, which never leaves the client.
+    Register {
+        public_key: String,
+        signature: String,
+    },
+    /// Answers [`ServerMessage::Challenge`] with its [`sign_challenge`] by the key of `account`.
+    Authenticate {
+        account: u64,
+        signature: String,
+    },
+}
+
+impl ClientMessage {
+    /// A [`ClientMessage::HitAt`] at `time`, or a [`ClientMessage::HitPosition`] until the server time is known.
+    pub fn hit(position: Vec2, time: Option<f64>) -> Self {
+        match time {
+            Some(time) => Self::HitAt { position, time },
+            None => Self::HitPosition(position),
+        }
+    }
+}
+
+/// Hex digits of the Ed25519 keys of accounts, secret and public ones.
+pub const ACCOUNT_KEY_LENGTH: usize = 64;
+
+/// Of the secret key of an account, in hex, `None` if it's not one.
+pub fn public_key(secret_key: &str) -> Option<String> {
+    let key = SigningKey::from_bytes(&from_hex(secret_key)?);
+    Some(to_hex(key.verifying_key().as_bytes()))
+}
+
+/// Ed25519 signature of `challenge` with the secret key of an account, in hex.
+pub fn sign_challenge(secret_key: &str, challenge: &str) -> Option<String> {
+    let key = SigningKey::from_bytes(&from_hex(secret_key)?);
+    Some(to_hex(&key.sign(challenge.as_bytes()).to_bytes()))
+}
+
+/// Whether `signature` is the [`sign_challenge`] of `challenge` by the secret key of `public_key`.
+pub fn verify_challenge(public_key: &str, challenge: &str, signature: &str) -> bool {
+    let key = from_hex(public_key).and_then(|key| VerifyingKey::from_bytes(&key).ok());
+    let signature = from_hex(signature).map(|signature| Signature::from_bytes(&signature));
+    match (key, signature) {
+        (Some(key), Some(signature)) => key.verify_strict(challenge.as_bytes(), &signature).is_ok(),
+        _ => false,
+    }
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// `N` bytes, `None` if `hex` has another length or isn't hex.
+pub fn from_hex<const N: usize>(hex: &str) -> Option<[u8; N]> {
+    if hex.len() != 2 * N || !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut bytes = [0u8; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some(bytes)
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
@@ -16,8 +90,173 @@ pub enum MoleKind {
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
 pub struct MoleDef {
+    /// Index in the [`MoleCatalog`].
+    pub catalog_id: usize,
     pub kind: MoleKind,
+    /// Where `path` starts.
     pub position: Vec2,
+    /// Server time `path` starts at, in seconds of its `Time<Real>`.
+    pub start_time: f64,
+    pub path: Path,
+}
+
+impl MoleDef {
+    /// Where the mole is at `time`, in seconds of the server `Time<Real>`.
+    pub fn position_at(&self, time: f64) -> Vec2 {
+        let elapsed = (time - self.start_time).max(0f64) as f32;
+        self.path.offset(elapsed) + self.position
+    }
+}
+
+/// How a mole moves, relative to [`MoleDef::position`].
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone, Default)]
+pub enum Path {
+    #[default]
+    Still,
+    /// Back and forth to `to`, a round trip in `period` seconds.
+    Linear { to: Vec2, period: f32 },
+    /// Around the position, a turn in `period` seconds, from the `phase` angle.
+    Circle {
+        radius: f32,
+        period: f32,
+        phase: f32,
+    },
+    /// In each of the `holes` in turn, `interval` seconds each.
+    Hop { holes: Vec<Vec2>, interval: f32 },
+    /// To `to` in `duration` seconds, then stays there.
+    Dash { to: Vec2, duration: f32 },
+}
+
+impl Path {
+    fn offset(&self, elapsed: f32) -> Vec2 {
+        match self {
+            Path::Still => Vec2::ZERO,
+            Path::Linear { to, period } => {
+                let t = (elapsed / period).fract() * 2f32;
+                *to * if t < 1f32 { t } else { 2f32 - t }
+            }
+            Path::Circle {
+                radius,
+                period,
+                phase,
+            } => Vec2::from_angle(phase + elapsed / period * std::f32::consts::TAU) * *radius,
+            Path::Hop { holes, interval } => {
+                if holes.is_empty() {
+                    return Vec2::ZERO;
+                }
+                holes[(elapsed / interval) as usize % holes.len()]
+            }
+            Path::Dash { to, duration } => *to * (elapsed / duration).min(1f32),
+        }
+    }
+}
+
+/// How a kind of mole moves, the server picks a [`Path`] for each mole from it.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone, Default)]
+#[serde(rename_all = "snake_case", tag = "type", deny_unknown_fields)]
+pub enum Movement {
+    #[default]
+    Still,
+    /// Back and forth `distance` in a random direction.
+    Linear {
+        distance: f32,
+        period: f32,
+    },
+    Circle {
+        radius: f32,
+        period: f32,
+    },
+    /// Between `holes` random holes up to `distance` away.
+    Hop {
+        holes: usize,
+        distance: f32,
+        interval: f32,
+    },
+    /// Dashes `distance` away from clusters of hits closer than `radius`, by any player:
+    /// `hits` of them in `window` seconds.
+    Flee {
+        radius: f32,
+        distance: f32,
+        duration: f32,
+        #[serde(default = "one_hit")]
+        hits: usize,
+        #[serde(default = "one_second")]
+        window: f32,
+    },
+}
+
+fn one_hit() -> usize {
+    1
+}
+
+fn one_second() -> f32 {
+    1f32
+}
+
+/// A kind of mole, as described in the catalog file of the server.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct MoleKindDef {
+    pub name: String,
+    /// Path in the client assets.
+    pub sprite: String,
+    pub size: f32,
+    /// Color multiplied with the sprite, RGB.
+    pub tint: [f32; 3],
+    pub hit_radius: f32,
+    /// Seconds before escaping.
+    pub lifetime: f32,
+    /// Hits needed to kill it, a [`MoleKind::HitCount`] mole if more than 1.
+    pub hits: u32,
+    /// Added to the score of the players credited with the kill.
+    pub points: usize,
+    pub movement: Movement,
+    /// Relative chance to be spawned, when the current spawn table doesn't say.
+    pub weight: f32,
+}
+
+impl Default for MoleKindDef {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            sprite: "players/icon_bevy.png".to_string(),
+            size: 64f32,
+            tint: [1f32, 1f32, 1f32],
+            hit_radius: 50f32,
+            lifetime: 2f32,
+            hits: 1,
+            points: 1,
+            movement: Movement::Still,
+            weight: 1f32,
+        }
+    }
+}
+
+/// Replicated resource, the kinds of moles the server spawns.
+#[derive(Resource, Deserialize, Serialize, Debug, PartialEq, Clone, Default)]
+pub struct MoleCatalog {
+    pub kinds: Vec<MoleKindDef>,
+}
+
+impl Replicable for MoleCatalog {
+    const NAME: &'static str = "mole_catalog";
+}
+
+impl MoleCatalog {
+    pub fn get(&self, catalog_id: usize) -> Option<&MoleKindDef> {
+        self.kinds.get(catalog_id)
+    }
+}
+
+/// Replicated on the server's mole entities.
+#[derive(Component, Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct Mole {
+    pub id: usize,
+    pub def: MoleDef,
+}
+
+impl Replicable for Mole {
+    const NAME: &'static str = "mole";
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
@@ -34,23 +273,146 @@ pub struct AllExistingMoles {
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
 pub struct PlayerRank {
+    /// Session of the player in the boards of the current round, `None` in the saved ones.
+    #[serde(default)]
+    pub player_id: Option<usize>,
     pub name: String,
     pub score: usize,
 }
 
+/// Replicated resource.
+#[derive(Resource, Deserialize, Serialize, Debug, PartialEq, Clone, Default)]
+pub struct Scores {
+    /// Best of the current round, or the last one until the next starts.
+    pub best_players: Vec<PlayerRank>,
+    /// Best of [`Board::AllTime`], the others are asked with [`TopPlayers`].
+    pub all_time: Vec<PlayerRank>,
+}
+
+impl Replicable for Scores {
+    const NAME: &'static str = "scores";
+}
+
 #[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
 pub struct UpdateScores {
     pub best_players: Vec<PlayerRank>,
 }
 
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum RoundPhase {
+    /// Waiting for enough players.
+    #[default]
+    Lobby,
+    Countdown,
+    /// Moles spawn and can be hit.
+    Playing,
+    Results,
+}
+
+/// Asks the moles alive now, replicated clients get them anyway.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct ExistingMoles;
+
+impl Rpc for ExistingMoles {
+    type Response = AllExistingMoles;
+    const METHOD: &'static str = "existing_moles";
+}
+
+/// Longest name a player can choose, in characters.
+pub const MAX_NAME_LENGTH: usize = 6;
+
+/// Why the server refused a [`ClientMessage::SetName`].
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum NameRejection {
+    Empty,
+    /// More than [`MAX_NAME_LENGTH`] characters.
+    TooLong,
+    /// Only letters, digits, `-` and `_` are allowed.
+    InvalidCharacters,
+    Offensive,
+    /// By another connected player, or owned by another account.
+    Taken,
+}
+
+impl std::fmt::Display for NameRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NameRejection::Empty => write!(f, "name is empty"),
+            NameRejection::TooLong => write!(f, "name is over {} characters", MAX_NAME_LENGTH),
+            NameRejection::InvalidCharacters => {
+                write!(f, "only letters, digits, - and _ are allowed")
+            }
+            NameRejection::Offensive => write!(f, "name is not allowed"),
+            NameRejection::Taken => write!(f, "name is taken"),
+        }
+    }
+}
+
+/// The leaderboards the server keeps across restarts.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Board {
+    AllTime,
+    /// Of the current UTC day.
+    Daily,
+    /// Of the last round played.
+    LastRound,
+    /// By number, only the last rounds are kept.
+    Round(u32),
+}
+
+/// Asks the best players of a board, at most `count` of them.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct TopPlayers {
+    pub board: Board,
+    pub count: usize,
+}
+
+impl Rpc for TopPlayers {
+    type Response = Vec<PlayerRank>;
+    const METHOD: &'static str = "top_players";
+}
+
 #[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
 pub enum ServerMessage {
+    /// Also replicated, for clients which don't replicate moles.
     Spawn(SpawnMole),
     DeadMole {
         mole_id: usize,
         player_killer_id: usize,
     },
     EscapedMole(usize),
+    /// Sent when the round's board changes, the same as the replicated [`Scores::best_players`].
     UpdateScores(UpdateScores),
-    AllExistingMoles(AllExistingMoles),
+    /// Sent on each change, and to new players.
+    RoundState {
+        phase: RoundPhase,
+        /// Server time the phase ends at, in seconds of its `Time<Real>`, `None` in the lobby.
+        ends_at: Option<f64>,
+    },
+    /// The best players of the round which just ended, at the start of [`RoundPhase::Results`].
+    RoundWinners(Vec<PlayerRank>),
+    /// A hit on a [`MoleKind::HitCount`] mole which didn't kill it.
+    MoleDamaged {
+        mole_id: usize,
+        remaining: u32,
+        by: usize,
+    },
+    /// Position, from 1, and score of the recipient in the current round, sent when they change.
+    OwnRank {
+        rank: Option<usize>,
+        score: usize,
+    },
+    /// Answers [`ClientMessage::SetName`], with the name as kept by the server.
+    NameAccepted(String),
+    NameRejected(NameRejection),
+    /// To new sessions if the server has accounts, and after a failed [`ClientMessage::Authenticate`],
+    /// to sign for [`ClientMessage::Register`] or [`ClientMessage::Authenticate`].
+    Challenge(String),
+    /// The session plays as the account from now on, with the name it owns if any.
+    Authenticated {
+        account: u64,
+        name: Option<String>,
+    },
+    /// Unknown account, wrong signature or already registered key, the session stays a guest.
+    AuthenticationFailed,
 }
@@ -1,13 +1,34 @@
-use std::{collections::VecDeque, marker::PhantomData};
+mod reconnect;
+mod replication;
+mod rpc;
+mod stats;
+mod time_sync;
+
+use std::{collections::VecDeque, marker::PhantomData, time::Duration};
 
 use bevy::prelude::*;
-use litlnet_trait::Communication;
+use litlnet_trait::{real_now, ClientId, Communication, Packet, Traffic};
 use serde::{de::DeserializeOwned, Serialize};
 
+pub use reconnect::{
+    just_connected, Backoff, ConnectionState, ConnectionStateChanged, ReconnectPlugin, Reconnection,
+};
+pub use replication::{Replicated, ReplicatedEntities, ReplicationAppExt};
+use replication::{ReplicationClient, ReplicationRegistry};
+pub use rpc::{RpcClient, RpcHandle};
+pub use stats::{
+    NetStats, BYTES_RECEIVED, BYTES_SENT, DECODE_ERRORS, MESSAGES_RECEIVED, MESSAGES_SENT, RTT,
+};
+pub use time_sync::ServerTime;
+use time_sync::TimeSyncTimer;
+
 pub struct ClientPlugin<C: Communication, S: Serialize, R: DeserializeOwned> {
     _phantom_c: Option<PhantomData<C>>,
     _phantom_s: Option<PhantomData<S>>,
     _phantom_r: Option<PhantomData<R>>,
+    send_config: SendConfig,
+    time_sync_interval: Duration,
+    rpc_timeout: Duration,
 }
 
 impl<C: Communication, S: Serialize, R: DeserializeOwned> Default for ClientPlugin<C, S, R> {
@@ -16,10 +37,53 @@ impl<C: Communication, S: Serialize, R: DeserializeOwned> Default for ClientPlug
             _phantom_c: None,
             _phantom_s: None,
             _phantom_r: None,
+            send_config: SendConfig::default(),
+            time_sync_interval: Duration::from_secs(2),
+            rpc_timeout: Duration::from_secs(10),
         }
     }
 }
 
+impl<C: Communication, S: Serialize, R: DeserializeOwned> ClientPlugin<C, S, R> {
+    pub fn with_batching(mut self, batching: bool) -> Self {
+        self.send_config.batching = batching;
+        self
+    }
+    /// Delay between two [`ServerTime`] updates, the first one is sent on connection.
+    pub fn with_time_sync_interval(mut self, interval: Duration) -> Self {
+        self.time_sync_interval = interval;
+        self
+    }
+    /// How long a request waits for its response, see [`RpcClient`].
+    pub fn with_rpc_timeout(mut self, timeout: Duration) -> Self {
+        self.rpc_timeout = timeout;
+        self
+    }
+}
+
+#[derive(Resource, Clone, Default)]
+pub struct SendConfig {
+    /// Sends all messages of a tick in a single frame.
+    pub batching: bool,
+}
+
+/// Identity given by the server, presented again on reconnection to be the same player.
+#[derive(Resource, Default, Debug)]
+pub struct Session {
+    /// Id of the player at the server, the same on resumed sessions.
+    pub id: Option<ClientId>,
+    pub token: Option<String>,
+    /// Whether the server accepted the token on the current connection.
+    pub resumed: bool,
+}
+
+/// Sets of the systems exchanging messages, [`MessagesToRead`] is filled after `Receive`.
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ClientSet {
+    Receive,
+    Send,
+}
+
 #[derive(Resource)]
 pub struct RComClient<C: Communication + Send + Sync + 'static> {
     pub com: C,
@@ -36,6 +100,14 @@ where
     fn send<T: Serialize>(&mut self, message: &T) -> std::io::Result<()> {
         self.com.send(message)
     }
+
+    fn send_batch<T: Serialize>(&mut self, messages: &[T]) -> std::io::Result<()> {
+        self.com.send_batch(messages)
+    }
+
+    fn traffic(&self) -> Traffic {
+        self.com.traffic()
+    }
 }
 
 #[derive(Resource)]
@@ -72,6 +144,10 @@ impl<R: DeserializeOwned> MessagesToRead<R> {
     pub fn pop(&mut self) -> Option<R> {
         self.messages.pop_front()
     }
+    /// The messages not popped yet, for systems running before the one popping them.
+    pub fn iter(&self) -> impl Iterator<Item = &R> + '_ {
+        self.messages.iter()
+    }
 }
 impl<C, S, R> Plugin for ClientPlugin<C, S, R>
 where
@@ -82,27 +158,99 @@ where
     fn build(&self, app: &mut App) {
         app.insert_resource(MessagesToRead::<R>::default());
         app.insert_resource(MessagesToSend::<S>::default());
-        app.add_systems(Update, receive_messages::<C, R>);
-        app.add_systems(Update, send_messages::<C, S>);
+        app.insert_resource(NetStats::default());
+        app.insert_resource(ServerTime::default());
+        app.insert_resource(Session::default());
+        app.insert_resource(TimeSyncTimer(Timer::new(
+            self.time_sync_interval,
+            TimerMode::Repeating,
+        )));
+        app.insert_resource(self.send_config.clone());
+        app.init_resource::<ReplicationRegistry>();
+        app.init_resource::<ReplicationClient>();
+        app.init_resource::<ReplicatedEntities>();
+        app.insert_resource(RpcClient::new(self.rpc_timeout));
+        stats::register_diagnostics(app);
+        app.add_systems(Update, receive_messages::<C, R>.in_set(ClientSet::Receive));
+        app.add_systems(Update, sync_time::<C>.before(receive_messages::<C, R>));
+        app.add_systems(Update, resume_session::<C>.before(sync_time::<C>));
+        app.add_systems(
+            Update,
+            send_messages::<C, S>
+                .in_set(ClientSet::Send)
+                .after(resume_session::<C>),
+        );
+        app.add_systems(
+            Update,
+            rpc::send_requests::<C>
+                .in_set(ClientSet::Send)
+                .after(resume_session::<C>)
+                .after(receive_messages::<C, R>),
+        );
+        // Late so the application sees the messages of a tick before its replicated changes.
+        app.add_systems(PostUpdate, replication::apply_snapshots::<C>);
+        app.add_systems(
+            Update,
+            stats::update_stats::<C>
+                .after(receive_messages::<C, R>)
+                .after(send_messages::<C, S>),
+        );
     }
 }
+#[allow(clippy::too_many_arguments)]
 fn receive_messages<
     C: Resource + Communication + Send + Sync + 'static,
     R: DeserializeOwned + Send + Sync + 'static,
 >(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
     mut com: Option<ResMut<C>>,
     mut messages_to_read: ResMut<MessagesToRead<R>>,
+    mut server_time: ResMut<ServerTime>,
+    mut stats: ResMut<NetStats>,
+    mut session: ResMut<Session>,
+    mut replication: ResMut<ReplicationClient>,
+    mut rpc: ResMut<RpcClient>,
 ) {
     if let Some(com) = com.as_mut() {
         match com.receive() {
-            Ok(Some(messages)) => {
-                for message in messages {
-                    messages_to_read.messages.push_back(message);
+            Ok(Some(packets)) => {
+                for packet in packets {
+                    match packet {
+                        Packet::Message(message) => messages_to_read.messages.push_back(message),
+                        Packet::TimeResponse {
+                            client_time,
+                            server_time: time_at_server,
+                        } => {
+                            server_time.add_sample(
+                                client_time,
+                                time_at_server,
+                                real_now(time.elapsed_seconds_f64(), time.last_update()),
+                            );
+                            stats.rtt = server_time.rtt;
+                        }
+                        Packet::Session {
+                            client_id,
+                            token,
+                            resumed,
+                        } => {
+                            session.id = Some(client_id);
+                            session.token = Some(token);
+                            session.resumed = resumed;
+                        }
+                        Packet::Snapshot(snapshot) => replication.pending.push(snapshot),
+                        Packet::Response { id, result } => rpc.respond(id, result),
+                        Packet::TimeRequest { .. }
+                        | Packet::Resume { .. }
+                        | Packet::SnapshotAck { .. }
+                        | Packet::Request { .. } => {}
+                    }
                 }
             }
             Ok(None) => {}
-            Err(_e) => {
-                //dbg!(e);
+            Err(e) => {
+                dbg!(e);
+                commands.remove_resource::<C>();
             }
         }
     }
@@ -115,13 +263,25 @@ fn send_messages<
     mut commands: Commands,
     mut com: Option<ResMut<C>>,
     mut messages_to_send: ResMut<MessagesToSend<S>>,
+    send_config: Res<SendConfig>,
 ) {
     let mut is_fail = false;
     if let Some(com) = com.as_mut() {
-        for msg in messages_to_send.messages.iter() {
-            if com.send(&msg).is_err() {
+        if send_config.batching {
+            let packets: Vec<Packet<&S>> = messages_to_send
+                .messages
+                .iter()
+                .map(Packet::Message)
+                .collect();
+            if com.send_batch(&packets).is_err() {
                 is_fail = true;
             }
+        } else {
+            for msg in messages_to_send.messages.iter() {
+                if com.send(&Packet::Message(msg)).is_err() {
+                    is_fail = true;
+                }
+            }
         }
         messages_to_send.messages.clear();
     }
@@ -129,3 +289,42 @@ fn send_messages<
         commands.remove_resource::<C>();
     }
 }
+
+fn sync_time<C: Resource + Communication + Send + Sync + 'static>(
+    time: Res<Time<Real>>,
+    mut com: Option<ResMut<C>>,
+    mut timer: ResMut<TimeSyncTimer>,
+    mut server_time: ResMut<ServerTime>,
+) {
+    if let Some(com) = com.as_mut() {
+        timer.0.tick(time.delta());
+        // A new connection may be to a restarted server, with another clock.
+        if com.is_added() {
+            server_time.reset();
+            timer.0.reset();
+        } else if !timer.0.just_finished() {
+            return;
+        }
+        // A failure is noticed by `send_messages`.
+        let _ = com.send(&Packet::<()>::TimeRequest {
+            client_time: real_now(time.elapsed_seconds_f64(), time.last_update()),
+        });
+    }
+}
+
+/// Must be the first packet of the connection.
+fn resume_session<C: Resource + Communication + Send + Sync + 'static>(
+    mut com: Option<ResMut<C>>,
+    mut session: ResMut<Session>,
+) {
+    if let Some(com) = com.as_mut() {
+        if !com.is_added() {
+            return;
+        }
+        session.resumed = false;
+        if let Some(token) = session.token.clone() {
+            // A failure is noticed by `send_messages`.
+            let _ = com.send(&Packet::<()>::Resume { token });
+        }
+    }
+}
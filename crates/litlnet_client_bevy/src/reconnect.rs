@@ -0,0 +1,193 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    marker::PhantomData,
+    sync::Arc,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use litlnet_trait::Communication;
+
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Waiting for the next attempt, see [`Reconnection::retry_in`].
+    #[default]
+    Disconnected,
+    /// An attempt is made during this frame.
+    Connecting,
+    Connected,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ConnectionStateChanged {
+    pub previous: ConnectionState,
+    pub current: ConnectionState,
+}
+
+/// Delay before each connection attempt: `initial * multiplier ^ failed attempts`, up to `max`.
+#[derive(Clone, Copy, Debug)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+    pub multiplier: f32,
+    /// Fraction of the delay which is random, so clients of a restarted server don't all come back at once.
+    pub jitter: f32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(500),
+            max: Duration::from_secs(30),
+            multiplier: 2f32,
+            jitter: 0.5f32,
+        }
+    }
+}
+
+impl Backoff {
+    /// `random` is between 0 and 1.
+    pub fn delay(&self, attempt: u32, random: f32) -> Duration {
+        // Computed in floats, where overflowing gives infinity rather than a panic.
+        let delay = (self.initial.as_secs_f32()
+            * self.multiplier.powi(attempt.min(i32::MAX as u32) as i32))
+        .min(self.max.as_secs_f32());
+        Duration::from_secs_f32(delay * (1f32 - self.jitter.clamp(0f32, 1f32) * random))
+    }
+}
+
+/// Progress of the reconnection, for display.
+#[derive(Resource)]
+pub struct Reconnection {
+    /// Failed attempts since the last connection.
+    attempt: u32,
+    timer: Timer,
+}
+
+impl Reconnection {
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+    pub fn retry_in(&self) -> Duration {
+        self.timer.remaining()
+    }
+}
+
+#[derive(Resource)]
+struct Connector<C> {
+    connect: Arc<dyn Fn() -> Result<C, std::io::Error> + Send + Sync>,
+    backoff: Backoff,
+}
+
+/// Connects the `C` resource, and connects it again when [`crate::ClientPlugin`] drops it.
+///
+/// The first attempt is made on startup.
+pub struct ReconnectPlugin<C> {
+    connect: Arc<dyn Fn() -> Result<C, std::io::Error> + Send + Sync>,
+    backoff: Backoff,
+    _phantom_c: PhantomData<C>,
+}
+
+impl<C> ReconnectPlugin<C> {
+    pub fn new(connect: impl Fn() -> Result<C, std::io::Error> + Send + Sync + 'static) -> Self {
+        Self {
+            connect: Arc::new(connect),
+            backoff: Backoff::default(),
+            _phantom_c: PhantomData,
+        }
+    }
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+}
+
+impl<C> Plugin for ReconnectPlugin<C>
+where
+    C: Resource + Communication + Send + Sync + 'static,
+{
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Connector {
+            connect: self.connect.clone(),
+            backoff: self.backoff,
+        });
+        app.insert_resource(ConnectionState::default());
+        app.insert_resource(Reconnection {
+            attempt: 0,
+            timer: Timer::new(Duration::ZERO, TimerMode::Once),
+        });
+        app.add_event::<ConnectionStateChanged>();
+        app.add_systems(Update, reconnect::<C>);
+    }
+}
+
+/// Run condition for systems re-sending session data, like a login, to the new connection.
+pub fn just_connected(mut events: EventReader<ConnectionStateChanged>) -> bool {
+    let connected = events
+        .read()
+        .any(|event| event.current == ConnectionState::Connected);
+    // Otherwise remaining events would trigger it again next frame.
+    events.clear();
+    connected
+}
+
+fn reconnect<C: Resource + Communication + Send + Sync + 'static>(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    com: Option<Res<C>>,
+    connector: Res<Connector<C>>,
+    mut state: ResMut<ConnectionState>,
+    mut reconnection: ResMut<Reconnection>,
+    mut events: EventWriter<ConnectionStateChanged>,
+) {
+    let next = match *state {
+        // Connected by the application itself.
+        ConnectionState::Disconnected | ConnectionState::Connecting if com.is_some() => {
+            reconnection.attempt = 0;
+            ConnectionState::Connected
+        }
+        ConnectionState::Connected if com.is_none() => {
+            reconnection.attempt = 0;
+            let delay = connector.backoff.delay(0, random(&time));
+            reconnection.timer = Timer::new(delay, TimerMode::Once);
+            ConnectionState::Disconnected
+        }
+        ConnectionState::Connected => return,
+        ConnectionState::Disconnected => {
+            reconnection.timer.tick(time.delta());
+            if !reconnection.timer.finished() {
+                return;
+            }
+            // Shown for a frame before the attempt, which may block.
+            ConnectionState::Connecting
+        }
+        ConnectionState::Connecting => match (connector.connect)() {
+            Ok(new_com) => {
+                commands.insert_resource(new_com);
+                reconnection.attempt = 0;
+                ConnectionState::Connected
+            }
+            Err(e) => {
+                dbg!(e);
+                reconnection.attempt = reconnection.attempt.saturating_add(1);
+                let delay = connector.backoff.delay(reconnection.attempt, random(&time));
+                reconnection.timer = Timer::new(delay, TimerMode::Once);
+                ConnectionState::Disconnected
+            }
+        },
+    };
+    events.send(ConnectionStateChanged {
+        previous: *state,
+        current: next,
+    });
+    *state = next;
+}
+
+/// Between 0 and 1, no need for a good generator to spread clients apart.
+fn random(time: &Time<Real>) -> f32 {
+    let mut hasher = RandomState::new().build_hasher();
+    // The hasher keys may not be random on all platforms, startup time differs between clients.
+    hasher.write_u128(time.elapsed().as_nanos());
+    (hasher.finish() % 10_000) as f32 / 10_000f32
+}
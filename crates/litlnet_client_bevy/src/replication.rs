@@ -0,0 +1,246 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bevy::prelude::*;
+use litlnet_trait::{
+    replication::{Snapshot, WorldState},
+    Communication, Packet, Replicable,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// Marks entities spawned by replication, they are despawned with their server entity.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Replicated;
+
+/// Client entities of replicated server entities.
+#[derive(Resource, Default)]
+pub struct ReplicatedEntities {
+    map: HashMap<u64, Entity>,
+}
+
+impl ReplicatedEntities {
+    pub fn get(&self, server_entity: u64) -> Option<Entity> {
+        self.map.get(&server_entity).copied()
+    }
+}
+
+/// States kept as bases of deltas, as many as the server keeps.
+const HISTORY: usize = 64;
+
+struct ComponentFns {
+    insert: fn(&mut EntityWorldMut, Value) -> serde_json::Result<()>,
+    remove: fn(&mut EntityWorldMut),
+}
+
+struct ResourceFns {
+    insert: fn(&mut World, Value) -> serde_json::Result<()>,
+    remove: fn(&mut World),
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct ReplicationRegistry {
+    components: HashMap<&'static str, ComponentFns>,
+    resources: HashMap<&'static str, ResourceFns>,
+}
+
+fn insert_component<T: Component + DeserializeOwned>(
+    entity: &mut EntityWorldMut,
+    value: Value,
+) -> serde_json::Result<()> {
+    entity.insert(serde_json::from_value::<T>(value)?);
+    Ok(())
+}
+
+fn remove_component<T: Component>(entity: &mut EntityWorldMut) {
+    entity.remove::<T>();
+}
+
+fn insert_resource<T: Resource + DeserializeOwned>(
+    world: &mut World,
+    value: Value,
+) -> serde_json::Result<()> {
+    world.insert_resource(serde_json::from_value::<T>(value)?);
+    Ok(())
+}
+
+fn remove_resource<T: Resource>(world: &mut World) {
+    world.remove_resource::<T>();
+}
+
+/// Registration of replicated types, the server must register the same ones.
+pub trait ReplicationAppExt {
+    /// Registering a name twice panics.
+    fn replicate<T: Component + DeserializeOwned + Replicable>(&mut self) -> &mut Self;
+    fn replicate_resource<T: Resource + DeserializeOwned + Replicable>(&mut self) -> &mut Self;
+}
+
+impl ReplicationAppExt for App {
+    fn replicate<T: Component + DeserializeOwned + Replicable>(&mut self) -> &mut Self {
+        self.init_resource::<ReplicationRegistry>();
+        let mut registry = self.world.resource_mut::<ReplicationRegistry>();
+        registry.register(T::NAME);
+        registry.components.insert(
+            T::NAME,
+            ComponentFns {
+                insert: insert_component::<T>,
+                remove: remove_component::<T>,
+            },
+        );
+        self
+    }
+
+    fn replicate_resource<T: Resource + DeserializeOwned + Replicable>(&mut self) -> &mut Self {
+        self.init_resource::<ReplicationRegistry>();
+        let mut registry = self.world.resource_mut::<ReplicationRegistry>();
+        registry.register(T::NAME);
+        registry.resources.insert(
+            T::NAME,
+            ResourceFns {
+                insert: insert_resource::<T>,
+                remove: remove_resource::<T>,
+            },
+        );
+        self
+    }
+}
+
+impl ReplicationRegistry {
+    fn register(&self, name: &'static str) {
+        let is_registered = self.components.contains_key(name) || self.resources.contains_key(name);
+        assert!(!is_registered, "{} is already replicated", name);
+    }
+
+    /// Changes the world from `previous` to `state`.
+    fn sync(
+        &self,
+        world: &mut World,
+        entities: &mut ReplicatedEntities,
+        previous: &WorldState,
+        state: &WorldState,
+    ) {
+        for server_entity in previous.entities.keys() {
+            if state.entities.contains_key(server_entity) {
+                continue;
+            }
+            if let Some(entity) = entities.map.remove(server_entity) {
+                if let Some(entity) = world.get_entity_mut(entity) {
+                    entity.despawn_recursive();
+                }
+            }
+        }
+        for (server_entity, components) in &state.entities {
+            // The application may have despawned it.
+            let existing = entities
+                .get(*server_entity)
+                .filter(|entity| world.get_entity(*entity).is_some());
+            let (entity, previous_components) = match existing {
+                Some(entity) => (entity, previous.entities.get(server_entity)),
+                None => (world.spawn(Replicated).id(), None),
+            };
+            entities.map.insert(*server_entity, entity);
+            let mut entity = world.entity_mut(entity);
+            for (name, value) in changed(components, previous_components) {
+                if let Some(fns) = self.components.get(name.as_str()) {
+                    if let Err(e) = (fns.insert)(&mut entity, value.clone()) {
+                        dbg!(name, e);
+                    }
+                }
+            }
+            for name in removed(components, previous_components) {
+                if let Some(fns) = self.components.get(name.as_str()) {
+                    (fns.remove)(&mut entity);
+                }
+            }
+        }
+        for (name, value) in changed(&state.resources, Some(&previous.resources)) {
+            if let Some(fns) = self.resources.get(name.as_str()) {
+                if let Err(e) = (fns.insert)(world, value.clone()) {
+                    dbg!(name, e);
+                }
+            }
+        }
+        for name in removed(&state.resources, Some(&previous.resources)) {
+            if let Some(fns) = self.resources.get(name.as_str()) {
+                (fns.remove)(world);
+            }
+        }
+    }
+}
+
+fn changed<'a>(
+    current: &'a BTreeMap<String, Value>,
+    previous: Option<&'a BTreeMap<String, Value>>,
+) -> impl Iterator<Item = (&'a String, &'a Value)> + 'a {
+    current.iter().filter(move |(name, value)| {
+        previous.and_then(|previous| previous.get(*name)) != Some(*value)
+    })
+}
+
+fn removed<'a>(
+    current: &'a BTreeMap<String, Value>,
+    previous: Option<&'a BTreeMap<String, Value>>,
+) -> impl Iterator<Item = &'a String> + 'a {
+    previous
+        .into_iter()
+        .flat_map(|previous| previous.keys())
+        .filter(move |name| !current.contains_key(*name))
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct ReplicationClient {
+    pub(crate) pending: Vec<Snapshot>,
+    history: BTreeMap<u64, WorldState>,
+    applied: Option<u64>,
+}
+
+/// Applies received snapshots, and acknowledges the latest so it's the base of the next ones.
+pub(crate) fn apply_snapshots<C: Resource + Communication + Send + Sync + 'static>(
+    world: &mut World,
+) {
+    let pending = std::mem::take(&mut world.resource_mut::<ReplicationClient>().pending);
+    if pending.is_empty() {
+        return;
+    }
+    let mut ack = None;
+    world.resource_scope(|world, mut replication: Mut<ReplicationClient>| {
+        world.resource_scope(|world, mut entities: Mut<ReplicatedEntities>| {
+            let registry = world
+                .remove_resource::<ReplicationRegistry>()
+                .unwrap_or_default();
+            for snapshot in pending {
+                let is_older = replication
+                    .applied
+                    .is_some_and(|applied| snapshot.tick <= applied);
+                let state = match snapshot.base {
+                    Some(_) if is_older => continue,
+                    Some(base) => match replication.history.get(&base) {
+                        Some(base) => base.apply(&snapshot),
+                        None => continue,
+                    },
+                    None => WorldState::default().apply(&snapshot),
+                };
+                let empty = WorldState::default();
+                let previous = replication
+                    .applied
+                    .and_then(|applied| replication.history.get(&applied))
+                    .unwrap_or(&empty);
+                registry.sync(world, &mut entities, previous, &state);
+                // Transports are ordered, so an older full snapshot is from a restarted server.
+                if is_older {
+                    replication.history.clear();
+                }
+                replication.history.insert(snapshot.tick, state);
+                while replication.history.len() > HISTORY {
+                    replication.history.pop_first();
+                }
+                replication.applied = Some(snapshot.tick);
+                ack = Some(snapshot.tick);
+            }
+            world.insert_resource(registry);
+        });
+    });
+    if let (Some(tick), Some(mut com)) = (ack, world.get_resource_mut::<C>()) {
+        // A failure is noticed by `send_messages`.
+        let _ = com.send(&Packet::<()>::SnapshotAck { tick });
+    }
+}
@@ -0,0 +1,147 @@
+use std::{marker::PhantomData, time::Duration};
+
+use bevy::{prelude::*, utils::HashMap};
+use litlnet_trait::{Communication, Packet, Rpc, RpcError};
+use serde_json::Value;
+
+/// A request sent with [`RpcClient::request`], its response is taken with [`RpcClient::poll`].
+pub struct RpcHandle<T: Rpc> {
+    id: u64,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<T: Rpc> Clone for RpcHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: Rpc> Copy for RpcHandle<T> {}
+
+struct PendingRequest {
+    method: &'static str,
+    /// Taken once sent.
+    payload: Option<Value>,
+    remaining: Duration,
+}
+
+/// Requests to the server, sent once connected and failed after the timeout of [`crate::ClientPlugin`].
+///
+/// Results not polled within the timeout are dropped, so do dropped handles.
+#[derive(Resource)]
+pub struct RpcClient {
+    next_id: u64,
+    timeout: Duration,
+    pending: HashMap<u64, PendingRequest>,
+    /// With how long they are kept.
+    results: HashMap<u64, (Result<Value, RpcError>, Duration)>,
+}
+
+impl RpcClient {
+    pub(crate) fn new(timeout: Duration) -> Self {
+        Self {
+            next_id: 0,
+            timeout,
+            pending: HashMap::default(),
+            results: HashMap::default(),
+        }
+    }
+
+    pub fn request<T: Rpc>(&mut self, request: &T) -> RpcHandle<T> {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        match serde_json::to_value(request) {
+            Ok(payload) => {
+                self.pending.insert(
+                    id,
+                    PendingRequest {
+                        method: T::METHOD,
+                        payload: Some(payload),
+                        remaining: self.timeout,
+                    },
+                );
+            }
+            Err(e) => {
+                self.results
+                    .insert(id, (Err(RpcError::Invalid(e.to_string())), self.timeout));
+            }
+        }
+        RpcHandle {
+            id,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// `None` while the request is pending, the result is only returned once.
+    pub fn poll<T: Rpc>(&mut self, handle: &RpcHandle<T>) -> Option<Result<T::Response, RpcError>> {
+        let (result, _) = self.results.remove(&handle.id)?;
+        Some(result.and_then(|value| {
+            serde_json::from_value(value).map_err(|e| RpcError::Invalid(e.to_string()))
+        }))
+    }
+
+    /// Forgets the request, its response is ignored.
+    pub fn cancel<T: Rpc>(&mut self, handle: &RpcHandle<T>) {
+        self.pending.remove(&handle.id);
+        self.results.remove(&handle.id);
+    }
+
+    pub(crate) fn respond(&mut self, id: u64, result: Result<Value, RpcError>) {
+        if self.pending.remove(&id).is_some() {
+            self.results.insert(id, (result, self.timeout));
+        }
+    }
+
+    fn fail(&mut self, is_failed: impl Fn(&PendingRequest) -> Option<RpcError>) {
+        let results = &mut self.results;
+        let timeout = self.timeout;
+        self.pending.retain(|id, request| match is_failed(request) {
+            Some(error) => {
+                results.insert(*id, (Err(error), timeout));
+                false
+            }
+            None => true,
+        });
+    }
+}
+
+/// Sends queued requests, requests in flight fail when their connection is lost.
+pub(crate) fn send_requests<C: Resource + Communication + Send + Sync + 'static>(
+    time: Res<Time<Real>>,
+    mut com: Option<ResMut<C>>,
+    mut rpc: ResMut<RpcClient>,
+) {
+    let delta = time.delta();
+    for request in rpc.pending.values_mut() {
+        request.remaining = request.remaining.saturating_sub(delta);
+    }
+    rpc.results.retain(|_, (_, remaining)| {
+        *remaining = remaining.saturating_sub(delta);
+        !remaining.is_zero()
+    });
+    let is_new_connection = match com.as_ref() {
+        Some(com) => com.is_added(),
+        None => true,
+    };
+    rpc.fail(|request| {
+        if request.remaining.is_zero() {
+            Some(RpcError::Timeout)
+        } else if is_new_connection && request.payload.is_none() {
+            Some(RpcError::Disconnected)
+        } else {
+            None
+        }
+    });
+    if let Some(com) = com.as_mut() {
+        for (id, request) in rpc.pending.iter_mut() {
+            if let Some(payload) = request.payload.take() {
+                // A failure is noticed by `send_messages`.
+                let _ = com.send(&Packet::<()>::Request {
+                    id: *id,
+                    method: request.method.to_string(),
+                    payload,
+                });
+            }
+        }
+    }
+}
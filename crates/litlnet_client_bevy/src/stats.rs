@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+};
+use litlnet_trait::{Communication, Traffic};
+
+pub const BYTES_SENT: DiagnosticPath = DiagnosticPath::const_new("litlnet/client/bytes_sent");
+pub const BYTES_RECEIVED: DiagnosticPath =
+    DiagnosticPath::const_new("litlnet/client/bytes_received");
+pub const MESSAGES_SENT: DiagnosticPath = DiagnosticPath::const_new("litlnet/client/messages_sent");
+pub const MESSAGES_RECEIVED: DiagnosticPath =
+    DiagnosticPath::const_new("litlnet/client/messages_received");
+pub const DECODE_ERRORS: DiagnosticPath = DiagnosticPath::const_new("litlnet/client/decode_errors");
+pub const RTT: DiagnosticPath = DiagnosticPath::const_new("litlnet/client/rtt");
+
+/// Traffic of the client, kept across reconnections.
+#[derive(Resource, Debug)]
+pub struct NetStats {
+    pub total: Traffic,
+    /// Traffic during the last second.
+    pub per_second: Traffic,
+    pub rtt: Option<Duration>,
+    last_connection_total: Traffic,
+    window: Traffic,
+    window_timer: Timer,
+}
+
+impl Default for NetStats {
+    fn default() -> Self {
+        Self {
+            total: Traffic::default(),
+            per_second: Traffic::default(),
+            rtt: None,
+            last_connection_total: Traffic::default(),
+            window: Traffic::default(),
+            window_timer: Timer::from_seconds(1f32, TimerMode::Repeating),
+        }
+    }
+}
+
+pub(crate) fn register_diagnostics(app: &mut App) {
+    app.register_diagnostic(Diagnostic::new(BYTES_SENT).with_suffix(" B/s"))
+        .register_diagnostic(Diagnostic::new(BYTES_RECEIVED).with_suffix(" B/s"))
+        .register_diagnostic(Diagnostic::new(MESSAGES_SENT).with_suffix(" msg/s"))
+        .register_diagnostic(Diagnostic::new(MESSAGES_RECEIVED).with_suffix(" msg/s"))
+        .register_diagnostic(Diagnostic::new(DECODE_ERRORS))
+        .register_diagnostic(Diagnostic::new(RTT).with_suffix(" ms"));
+}
+
+pub(crate) fn update_stats<C: Resource + Communication + Send + Sync + 'static>(
+    time: Res<Time>,
+    com: Option<Res<C>>,
+    mut stats: ResMut<NetStats>,
+    mut diagnostics: Diagnostics,
+) {
+    if let Some(com) = com.as_ref() {
+        if com.is_added() {
+            stats.last_connection_total = Traffic::default();
+        }
+        let current = com.traffic();
+        let delta = current.saturating_sub(stats.last_connection_total);
+        stats.last_connection_total = current;
+        stats.total = stats.total + delta;
+        stats.window = stats.window + delta;
+    }
+    stats.window_timer.tick(time.delta());
+    if !stats.window_timer.just_finished() {
+        return;
+    }
+    stats.per_second = stats.window;
+    stats.window = Traffic::default();
+    let per_second = stats.per_second;
+    diagnostics.add_measurement(&BYTES_SENT, || per_second.bytes_sent as f64);
+    diagnostics.add_measurement(&BYTES_RECEIVED, || per_second.bytes_received as f64);
+    diagnostics.add_measurement(&MESSAGES_SENT, || per_second.messages_sent as f64);
+    diagnostics.add_measurement(&MESSAGES_RECEIVED, || per_second.messages_received as f64);
+    diagnostics.add_measurement(&DECODE_ERRORS, || stats.total.decode_errors as f64);
+    if let Some(rtt) = stats.rtt {
+        diagnostics.add_measurement(&RTT, || rtt.as_secs_f64() * 1000f64);
+    }
+}
@@ -0,0 +1,80 @@
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::prelude::*;
+use litlnet_trait::real_now;
+
+/// Number of exchanges the offset is chosen from.
+const SAMPLES: usize = 8;
+/// Weight of a new measure in the smoothed values.
+const SMOOTHING: f64 = 0.125;
+/// Offset changes bigger than this are applied at once instead of smoothed.
+const MAX_SLEW: f64 = 1.0;
+
+/// Estimation of the server clock, from periodic request/response exchanges.
+///
+/// Times are in seconds, as `Time<Real>::elapsed_seconds_f64` of each side.
+#[derive(Resource, Debug, Default)]
+pub struct ServerTime {
+    /// Server time minus local time, `None` until the first response.
+    pub offset: Option<f64>,
+    /// Smoothed round trip time.
+    pub rtt: Option<Duration>,
+    samples: VecDeque<Sample>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Sample {
+    offset: f64,
+    rtt: f64,
+}
+
+impl ServerTime {
+    /// Current server time, estimated.
+    pub fn now(&self, time: &Time<Real>) -> Option<f64> {
+        self.offset
+            .map(|offset| real_now(time.elapsed_seconds_f64(), time.last_update()) + offset)
+    }
+
+    /// Local time at which the server clock reads `server_time`.
+    pub fn to_local(&self, server_time: f64) -> Option<f64> {
+        self.offset.map(|offset| server_time - offset)
+    }
+
+    pub(crate) fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Adds the exchange of a request sent at `client_time`, answered at `server_time`
+    /// and received `now`.
+    pub fn add_sample(&mut self, client_time: f64, server_time: f64, now: f64) {
+        let rtt = (now - client_time).max(0f64);
+        // Assumes the request and the response took as long.
+        let offset = server_time + rtt / 2f64 - now;
+        self.samples.push_back(Sample { offset, rtt });
+        if self.samples.len() > SAMPLES {
+            self.samples.pop_front();
+        }
+        // The fastest exchange was the least delayed by queues, so its offset is the most accurate.
+        let best = self
+            .samples
+            .iter()
+            .min_by(|a, b| a.rtt.total_cmp(&b.rtt))
+            .map_or(offset, |sample| sample.offset);
+        self.offset = Some(match self.offset {
+            Some(current) if (best - current).abs() < MAX_SLEW => {
+                current + (best - current) * SMOOTHING
+            }
+            _ => best,
+        });
+        self.rtt = Some(Duration::from_secs_f64(match self.rtt {
+            Some(current) => {
+                let current = current.as_secs_f64();
+                current + (rtt - current) * SMOOTHING
+            }
+            None => rtt,
+        }));
+    }
+}
+
+#[derive(Resource)]
+pub(crate) struct TimeSyncTimer(pub Timer);
@@ -0,0 +1,128 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use litlnet_client_bevy::{ClientPlugin, RpcClient};
+use litlnet_trait::{Communication, Packet, Rpc, RpcError};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+/// Packets in flight between the test and [`MockCom`].
+#[derive(Default)]
+struct Network {
+    to_client: Vec<Value>,
+    to_server: Vec<Value>,
+}
+
+#[derive(Resource)]
+struct MockCom {
+    network: Arc<Mutex<Network>>,
+}
+
+impl Communication for MockCom {
+    fn receive<T: DeserializeOwned>(&mut self) -> Result<Option<Vec<T>>, std::io::Error> {
+        let packets = std::mem::take(&mut self.network.lock().unwrap().to_client);
+        Ok(Some(
+            packets
+                .into_iter()
+                .map(|packet| serde_json::from_value(packet).unwrap())
+                .collect(),
+        ))
+    }
+
+    fn send<T: Serialize>(&mut self, message: &T) -> std::io::Result<()> {
+        let packet = serde_json::to_value(message).unwrap();
+        self.network.lock().unwrap().to_server.push(packet);
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Double(u32);
+
+impl Rpc for Double {
+    type Response = u32;
+    const METHOD: &'static str = "double";
+}
+
+const TIMEOUT: Duration = Duration::from_millis(100);
+
+fn client(network: &Arc<Mutex<Network>>) -> App {
+    let mut app = App::new();
+    app.add_plugins(ClientPlugin::<MockCom, String, String>::default().with_rpc_timeout(TIMEOUT));
+    app.add_plugins(MinimalPlugins);
+    app.insert_resource(MockCom {
+        network: network.clone(),
+    });
+    app
+}
+
+/// Answers the requests sent since the last call, in reverse order.
+fn answer(network: &Arc<Mutex<Network>>) {
+    let mut network = network.lock().unwrap();
+    let requests = std::mem::take(&mut network.to_server);
+    for request in requests.into_iter().rev() {
+        if let Packet::Request {
+            id,
+            method,
+            payload,
+        } = serde_json::from_value::<Packet<String>>(request).unwrap()
+        {
+            assert_eq!(method, Double::METHOD);
+            let Double(n) = serde_json::from_value(payload).unwrap();
+            let response = Packet::<String>::Response {
+                id,
+                result: Ok(Value::from(2 * n)),
+            };
+            network
+                .to_client
+                .push(serde_json::to_value(response).unwrap());
+        }
+    }
+}
+
+#[test]
+fn responses_are_matched_to_their_request() {
+    let network = Arc::default();
+    let mut app = client(&network);
+    let mut rpc = app.world.resource_mut::<RpcClient>();
+    let one = rpc.request(&Double(1));
+    let two = rpc.request(&Double(2));
+    app.update();
+    answer(&network);
+    app.update();
+    let mut rpc = app.world.resource_mut::<RpcClient>();
+    assert_eq!(rpc.poll(&two), Some(Ok(4)));
+    assert_eq!(rpc.poll(&one), Some(Ok(2)));
+    // Only returned once.
+    assert_eq!(rpc.poll(&one), None);
+}
+
+#[test]
+fn unanswered_requests_time_out() {
+    let network = Arc::default();
+    let mut app = client(&network);
+    let handle = app.world.resource_mut::<RpcClient>().request(&Double(1));
+    app.update();
+    thread::sleep(TIMEOUT);
+    app.update();
+    let mut rpc = app.world.resource_mut::<RpcClient>();
+    assert_eq!(rpc.poll(&handle), Some(Err(RpcError::Timeout)));
+}
+
+#[test]
+fn results_not_polled_are_dropped() {
+    let network = Arc::default();
+    let mut app = client(&network);
+    let handle = app.world.resource_mut::<RpcClient>().request(&Double(1));
+    app.update();
+    answer(&network);
+    app.update();
+    thread::sleep(TIMEOUT);
+    app.update();
+    let mut rpc = app.world.resource_mut::<RpcClient>();
+    assert_eq!(rpc.poll(&handle), None);
+}
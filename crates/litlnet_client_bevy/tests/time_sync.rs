@@ -0,0 +1,63 @@
+use litlnet_client_bevy::ServerTime;
+
+fn assert_close(actual: Option<f64>, expected: f64) {
+    let actual = actual.unwrap();
+    assert!(
+        (actual - expected).abs() < 1e-6,
+        "{} != {}",
+        actual,
+        expected
+    );
+}
+
+fn rtt(server_time: &ServerTime) -> Option<f64> {
+    server_time.rtt.map(|rtt| rtt.as_secs_f64())
+}
+
+/// The server clock is 100 seconds ahead, the first exchange takes 400ms.
+fn synced() -> ServerTime {
+    let mut server_time = ServerTime::default();
+    server_time.add_sample(10.0, 110.2, 10.4);
+    server_time
+}
+
+#[test]
+fn first_exchange_sets_offset_and_rtt() {
+    let server_time = synced();
+    assert_close(server_time.offset, 100.0);
+    assert_close(rtt(&server_time), 0.4);
+    assert_close(server_time.to_local(200.0), 100.0);
+    assert_eq!(ServerTime::default().to_local(200.0), None);
+}
+
+#[test]
+fn slow_exchanges_do_not_move_offset() {
+    let mut server_time = synced();
+    // The request was queued for 1.8 seconds, the response took 0.2.
+    server_time.add_sample(20.0, 121.8, 22.0);
+    assert_close(server_time.offset, 100.0);
+    // The round trip time follows all exchanges, smoothed.
+    assert_close(rtt(&server_time), 0.6);
+}
+
+#[test]
+fn small_drifts_are_smoothed_and_jumps_applied() {
+    let mut server_time = synced();
+    // Faster than the first exchange.
+    server_time.add_sample(20.0, 120.5, 20.2);
+    assert_close(server_time.offset, 100.05);
+    // Like a server restart.
+    server_time.add_sample(30.0, 10.2, 30.1);
+    assert_close(server_time.offset, -19.85);
+}
+
+#[test]
+fn old_exchanges_are_forgotten() {
+    let mut server_time = synced();
+    for i in 0..8 {
+        let client_time = 20.0 + i as f64;
+        server_time.add_sample(client_time, client_time + 101.8, client_time + 2.0);
+    }
+    // The fast exchange is out of the window, the slow ones are all that's left.
+    assert!(server_time.offset.unwrap() > 100.0);
+}
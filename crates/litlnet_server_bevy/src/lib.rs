@@ -1,9 +1,38 @@
-use std::{collections::VecDeque, marker::PhantomData};
+mod rate_limit;
+mod replication;
+mod rpc;
+mod session;
+mod stats;
 
-use bevy::prelude::*;
-use litlnet_trait::{ClientId, Server};
+use std::{
+    collections::VecDeque,
+    marker::PhantomData,
+    time::{Duration, Instant},
+};
+
+use bevy::{prelude::*, utils::HashMap};
+use litlnet_trait::{real_now, ClientId, Packet, Server, Traffic};
 use serde::{de::DeserializeOwned, Serialize};
 
+pub use rate_limit::{
+    RateLimit, RateLimitAction, RateLimitExceeded, RateLimiter, REQUEST_CLASS, SNAPSHOT_ACK_CLASS,
+    TIME_REQUEST_CLASS,
+};
+pub use replication::{Replicated, ReplicationAppExt};
+pub use rpc::RpcAppExt;
+pub use session::{SessionEvent, Sessions};
+pub use stats::{
+    NetStats, BYTES_RECEIVED, BYTES_SENT, CLIENTS, DECODE_ERRORS, MESSAGES_RECEIVED, MESSAGES_SENT,
+};
+
+/// Sets of the systems exchanging messages, [`MessagesToRead`] is filled after `Receive`
+/// and [`MessagesToSend`] is sent in `Send`, before snapshots of the replicated state.
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ServerSet {
+    Receive,
+    Send,
+}
+
 #[derive(Resource)]
 pub struct RComServer<C: Server + Send + Sync + 'static> {
     pub server: C,
@@ -31,12 +60,31 @@ where
     fn send<T: Serialize>(&mut self, client_id: &ClientId, data: &T) {
         self.server.send(client_id, data)
     }
+
+    fn send_batch<T: Serialize>(&mut self, client_id: &ClientId, data: &[T]) {
+        self.server.send_batch(client_id, data)
+    }
+
+    fn disconnect(&mut self, client_id: &ClientId) {
+        self.server.disconnect(client_id)
+    }
+
+    fn clients(&self) -> Vec<ClientId> {
+        self.server.clients()
+    }
+
+    fn clients_traffic(&self) -> Vec<(ClientId, Traffic)> {
+        self.server.clients_traffic()
+    }
 }
 
 pub struct ServerPlugin<C: Server, S: Serialize, R: DeserializeOwned> {
     _phantom_c: Option<PhantomData<C>>,
     _phantom_s: Option<PhantomData<S>>,
     _phantom_r: Option<PhantomData<R>>,
+    send_config: SendConfig,
+    session_grace: Duration,
+    replication_interval: Duration,
 }
 impl<C: Server, S: Serialize, R: DeserializeOwned> Default for ServerPlugin<C, S, R> {
     fn default() -> Self {
@@ -44,6 +92,48 @@ impl<C: Server, S: Serialize, R: DeserializeOwned> Default for ServerPlugin<C, S
             _phantom_c: None,
             _phantom_s: None,
             _phantom_r: None,
+            send_config: SendConfig::default(),
+            session_grace: Duration::from_secs(30),
+            replication_interval: Duration::from_millis(100),
+        }
+    }
+}
+
+impl<C: Server, S: Serialize, R: DeserializeOwned> ServerPlugin<C, S, R> {
+    pub fn with_batching(mut self, batching: bool) -> Self {
+        self.send_config.batching = batching;
+        self
+    }
+    /// How long a disconnected session can be resumed, see [`Sessions`].
+    pub fn with_session_grace(mut self, grace: Duration) -> Self {
+        self.session_grace = grace;
+        self
+    }
+    /// How many messages are kept for a disconnected session, older ones are dropped.
+    pub fn with_max_pending_messages(mut self, max_pending_messages: usize) -> Self {
+        self.send_config.max_pending_messages = max_pending_messages;
+        self
+    }
+    /// Delay between two snapshots of replicated state, see [`ReplicationAppExt`].
+    pub fn with_replication_interval(mut self, interval: Duration) -> Self {
+        self.replication_interval = interval;
+        self
+    }
+}
+
+#[derive(Resource, Clone)]
+pub struct SendConfig {
+    /// Sends all messages of a tick for a client in a single frame.
+    pub batching: bool,
+    /// Messages kept for a disconnected session until it resumes, the latest ones.
+    pub max_pending_messages: usize,
+}
+
+impl Default for SendConfig {
+    fn default() -> Self {
+        Self {
+            batching: false,
+            max_pending_messages: 1024,
         }
     }
 }
@@ -91,9 +181,44 @@ impl<
     fn build(&self, app: &mut App) {
         app.insert_resource(MessagesToRead::<R>::default());
         app.insert_resource(MessagesToSend::<S>::default());
+        app.insert_resource(NetStats::default());
+        app.insert_resource(self.send_config.clone());
+        app.insert_resource(Sessions::new(self.session_grace));
+        app.init_resource::<replication::ReplicationRegistry>();
+        app.init_resource::<rpc::RpcServer>();
+        app.insert_resource(replication::ReplicationServer::new(
+            self.replication_interval,
+        ));
+        app.add_event::<RateLimitExceeded>();
+        app.add_event::<SessionEvent>();
+        stats::register_diagnostics(app);
         app.add_systems(Update, accept_connections::<C>);
-        app.add_systems(Update, receive_messages::<C, R>);
-        app.add_systems(Update, send_messages::<C, S>);
+        app.add_systems(
+            Update,
+            session::update_sessions::<C>.before(receive_messages::<C, R>),
+        );
+        app.add_systems(Update, receive_messages::<C, R>.in_set(ServerSet::Receive));
+        app.add_systems(Update, send_messages::<C, S>.in_set(ServerSet::Send));
+        // Messages pushed by handlers are sent in the same frame.
+        app.add_systems(
+            Update,
+            rpc::answer_requests::<C>
+                .in_set(ServerSet::Send)
+                .before(send_messages::<C, S>),
+        );
+        // Messages of a frame are received before the state they led to.
+        app.add_systems(
+            Update,
+            replication::send_snapshots::<C>
+                .in_set(ServerSet::Send)
+                .after(send_messages::<C, S>),
+        );
+        app.add_systems(
+            Update,
+            stats::update_stats::<C>
+                .after(receive_messages::<C, R>)
+                .after(send_messages::<C, S>),
+        );
     }
 }
 fn accept_connections<C: Resource + Server + Send + Sync + 'static>(
@@ -103,19 +228,126 @@ fn accept_connections<C: Resource + Server + Send + Sync + 'static>(
         com_to_read.accept_connections();
     }
 }
+#[allow(clippy::too_many_arguments)]
 fn receive_messages<
     C: Resource + Server + Send + Sync + 'static,
     R: DeserializeOwned + Send + Sync + 'static,
 >(
+    time: Res<Time<Real>>,
     mut com: Option<ResMut<C>>,
     mut messages_to_read: ResMut<MessagesToRead<R>>,
+    mut rate_limiter: Option<ResMut<RateLimiter<R>>>,
+    mut exceeded_events: EventWriter<RateLimitExceeded>,
+    mut sessions: ResMut<Sessions>,
+    mut session_events: EventWriter<SessionEvent>,
+    mut replication: ResMut<replication::ReplicationServer>,
+    mut rpc: ResMut<rpc::RpcServer>,
 ) {
     if let Some(com) = com.as_mut() {
-        com.receive_all(|id, messages| {
-            for message in messages {
-                messages_to_read.messages.push_back((id, message));
+        let now = Instant::now();
+        let mut to_disconnect = vec![];
+        // Only the latest time request of each client is answered, spamming them is useless.
+        let mut time_requests: HashMap<ClientId, f64> = HashMap::default();
+        let mut new_sessions = vec![];
+        let mut stale_connections = vec![];
+        com.receive_all(|connection, packets| {
+            let id = match sessions.session_of(&connection) {
+                Some(id) => id,
+                None => {
+                    let resumed = match packets.first() {
+                        Some(Packet::Resume { token }) => sessions.resume(token, connection),
+                        _ => None,
+                    };
+                    let id = match resumed {
+                        Some((id, previous_connection)) => {
+                            stale_connections.extend(previous_connection);
+                            session_events.send(SessionEvent::Resumed(id));
+                            id
+                        }
+                        None => {
+                            let id = sessions.start(connection);
+                            session_events.send(SessionEvent::Started(id));
+                            id
+                        }
+                    };
+                    new_sessions.push((connection, id, resumed.is_some()));
+                    id
+                }
+            };
+            for packet in packets {
+                if let Some(rate_limiter) = rate_limiter.as_mut() {
+                    if to_disconnect.contains(&connection) {
+                        break;
+                    }
+                    let exceeded = match &packet {
+                        Packet::Message(message) => rate_limiter.check(id, message, now),
+                        Packet::TimeRequest { .. } => {
+                            rate_limiter.check_class(id, TIME_REQUEST_CLASS, now)
+                        }
+                        Packet::SnapshotAck { .. } => {
+                            rate_limiter.check_class(id, SNAPSHOT_ACK_CLASS, now)
+                        }
+                        // Dropped ones time out on the client.
+                        Packet::Request { .. } => rate_limiter.check_class(id, REQUEST_CLASS, now),
+                        _ => None,
+                    };
+                    if let Some(class) = exceeded {
+                        let action = rate_limiter.action();
+                        exceeded_events.send(RateLimitExceeded {
+                            client_id: id,
+                            class,
+                            action,
+                        });
+                        if action == RateLimitAction::Disconnect {
+                            to_disconnect.push(connection);
+                        }
+                        continue;
+                    }
+                }
+                match packet {
+                    Packet::Message(message) => messages_to_read.messages.push_back((id, message)),
+                    Packet::TimeRequest { client_time } => {
+                        time_requests.insert(connection, client_time);
+                    }
+                    Packet::SnapshotAck { tick } => replication.ack(id, tick),
+                    Packet::Request {
+                        id: request_id,
+                        method,
+                        payload,
+                    } => rpc.requests.push((id, request_id, method, payload)),
+                    _ => {}
+                }
             }
         });
+        for connection in to_disconnect.into_iter().chain(stale_connections) {
+            time_requests.remove(&connection);
+            com.disconnect(&connection);
+        }
+        for (connection, id, resumed) in new_sessions {
+            if let Some(token) = sessions.token(&id) {
+                com.send(
+                    &connection,
+                    &Packet::<()>::Session {
+                        client_id: id,
+                        token: token.to_string(),
+                        resumed,
+                    },
+                );
+            }
+        }
+        let server_time = real_now(time.elapsed_seconds_f64(), time.last_update());
+        for (connection, client_time) in time_requests {
+            com.send(
+                &connection,
+                &Packet::<()>::TimeResponse {
+                    client_time,
+                    server_time,
+                },
+            );
+        }
+        if let Some(rate_limiter) = rate_limiter.as_mut() {
+            rate_limiter.prune(now);
+        }
     }
 }
 
@@ -125,11 +357,52 @@ fn send_messages<
 >(
     mut com: Option<ResMut<C>>,
     mut messages_to_send: ResMut<MessagesToSend<S>>,
+    send_config: Res<SendConfig>,
+    sessions: Res<Sessions>,
 ) {
     if let Some(com) = com.as_mut() {
-        for msg in messages_to_send.messages.iter() {
-            com.send(&msg.0, &msg.1);
+        // Sessions are only updated once per frame, their connection may have died since.
+        let connections = com.clients();
+        let connection_of = |id: &ClientId| {
+            sessions
+                .connection(id)
+                .filter(|connection| connections.contains(connection))
+        };
+        if send_config.batching {
+            let mut batches: HashMap<ClientId, Vec<Packet<&S>>> = HashMap::default();
+            for (id, msg) in messages_to_send.messages.iter() {
+                if let Some(connection) = connection_of(id) {
+                    batches
+                        .entry(connection)
+                        .or_default()
+                        .push(Packet::Message(msg));
+                }
+            }
+            for (connection, batch) in batches {
+                com.send_batch(&connection, &batch);
+            }
+        } else {
+            for (id, msg) in messages_to_send.messages.iter() {
+                if let Some(connection) = connection_of(id) {
+                    com.send(&connection, &Packet::Message(msg));
+                }
+            }
         }
-        messages_to_send.messages.clear();
+        // Disconnected sessions get their messages when they resume.
+        messages_to_send
+            .messages
+            .retain(|(id, _)| sessions.contains(id) && connection_of(id).is_none());
+        let mut pending: HashMap<ClientId, usize> = HashMap::default();
+        for (id, _) in messages_to_send.messages.iter() {
+            *pending.entry(*id).or_default() += 1;
+        }
+        messages_to_send.messages.retain(|(id, _)| {
+            let Some(left) = pending.get_mut(id) else {
+                return true;
+            };
+            let is_kept = *left <= send_config.max_pending_messages;
+            *left -= 1;
+            is_kept
+        });
     }
 }
@@ -0,0 +1,127 @@
+use std::{collections::HashMap, time::Instant};
+
+use bevy::prelude::*;
+use litlnet_trait::{ClientId, TokenBucket};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    pub per_second: f32,
+    pub burst: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RateLimitAction {
+    Drop,
+    Disconnect,
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct RateLimitExceeded {
+    pub client_id: ClientId,
+    pub class: &'static str,
+    pub action: RateLimitAction,
+}
+
+/// Class of the [`Packet::TimeRequest`](litlnet_trait::Packet::TimeRequest)s of the plugins.
+pub const TIME_REQUEST_CLASS: &str = "litlnet_time_request";
+/// Class of the [`Packet::SnapshotAck`](litlnet_trait::Packet::SnapshotAck)s of the plugins.
+pub const SNAPSHOT_ACK_CLASS: &str = "litlnet_snapshot_ack";
+/// Class of the [`Packet::Request`](litlnet_trait::Packet::Request)s of all methods, each runs a handler.
+pub const REQUEST_CLASS: &str = "litlnet_request";
+
+/// Token bucket rate limiting of received messages, per client and per message class.
+///
+/// Insert it as a resource to have [`crate::ServerPlugin`] enforce it.
+/// Messages of a class without a limit are never limited.
+///
+/// The packets of the plugins are limited too, under their own classes, with defaults
+/// well above what `litlnet_client_bevy` sends; [`RateLimiter::with_limit`] changes them.
+#[derive(Resource)]
+pub struct RateLimiter<R> {
+    classifier: Box<dyn Fn(&R) -> &'static str + Send + Sync>,
+    limits: HashMap<&'static str, RateLimit>,
+    action: RateLimitAction,
+    buckets: HashMap<(ClientId, &'static str), TokenBucket>,
+}
+
+impl<R> RateLimiter<R> {
+    pub fn new(classifier: impl Fn(&R) -> &'static str + Send + Sync + 'static) -> Self {
+        Self {
+            classifier: Box::new(classifier),
+            limits: HashMap::from([
+                (
+                    TIME_REQUEST_CLASS,
+                    RateLimit {
+                        per_second: 2f32,
+                        burst: 5f32,
+                    },
+                ),
+                (
+                    SNAPSHOT_ACK_CLASS,
+                    RateLimit {
+                        per_second: 30f32,
+                        burst: 30f32,
+                    },
+                ),
+                (
+                    REQUEST_CLASS,
+                    RateLimit {
+                        per_second: 5f32,
+                        burst: 10f32,
+                    },
+                ),
+            ]),
+            action: RateLimitAction::Drop,
+            buckets: HashMap::new(),
+        }
+    }
+
+    pub fn with_limit(mut self, class: &'static str, limit: RateLimit) -> Self {
+        self.limits.insert(class, limit);
+        self
+    }
+
+    pub fn with_action(mut self, action: RateLimitAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    pub fn action(&self) -> RateLimitAction {
+        self.action
+    }
+
+    /// Returns the class of the message if it exceeds its limit.
+    pub fn check(
+        &mut self,
+        client_id: ClientId,
+        message: &R,
+        now: Instant,
+    ) -> Option<&'static str> {
+        let class = (self.classifier)(message);
+        self.check_class(client_id, class, now)
+    }
+
+    /// Returns `class` if a packet of it exceeds its limit.
+    pub fn check_class(
+        &mut self,
+        client_id: ClientId,
+        class: &'static str,
+        now: Instant,
+    ) -> Option<&'static str> {
+        let limit = self.limits.get(class)?;
+        let bucket = self
+            .buckets
+            .entry((client_id, class))
+            .or_insert_with(|| TokenBucket::new(limit.per_second, limit.burst));
+        if bucket.try_take(now) {
+            None
+        } else {
+            Some(class)
+        }
+    }
+
+    /// Forgets buckets back to full, which also cleans up disconnected clients.
+    pub fn prune(&mut self, now: Instant) {
+        self.buckets.retain(|_, bucket| !bucket.is_full(now));
+    }
+}
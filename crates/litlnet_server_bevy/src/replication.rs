@@ -0,0 +1,194 @@
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::{ecs::world::EntityRef, prelude::*, utils::HashMap};
+use litlnet_trait::{replication::WorldState, ClientId, Packet, Replicable, Server};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::Sessions;
+
+/// Marks entities whose registered components are replicated to clients.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Replicated;
+
+/// States kept as bases of deltas, a client which acked an older one gets a full snapshot.
+const HISTORY: usize = 64;
+
+type SerializeComponent = fn(&EntityRef) -> Option<Value>;
+type SerializeResource = fn(&World) -> Option<Value>;
+
+#[derive(Resource, Default)]
+pub(crate) struct ReplicationRegistry {
+    components: Vec<(&'static str, SerializeComponent)>,
+    resources: Vec<(&'static str, SerializeResource)>,
+}
+
+impl ReplicationRegistry {
+    fn register(&mut self, name: &'static str) {
+        let is_registered = self
+            .components
+            .iter()
+            .map(|(registered, _)| registered)
+            .chain(self.resources.iter().map(|(registered, _)| registered))
+            .any(|registered| *registered == name);
+        assert!(!is_registered, "{} is already replicated", name);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.components.is_empty() && self.resources.is_empty()
+    }
+
+    fn collect(&self, world: &World) -> WorldState {
+        let mut state = WorldState::default();
+        for entity in world.iter_entities() {
+            if !entity.contains::<Replicated>() {
+                continue;
+            }
+            let components = self
+                .components
+                .iter()
+                .filter_map(|(name, serialize)| Some((name.to_string(), serialize(&entity)?)))
+                .collect();
+            state.entities.insert(entity.id().to_bits(), components);
+        }
+        state.resources = self
+            .resources
+            .iter()
+            .filter_map(|(name, serialize)| Some((name.to_string(), serialize(world)?)))
+            .collect();
+        state
+    }
+}
+
+fn serialize_component<T: Component + Serialize>(entity: &EntityRef) -> Option<Value> {
+    serde_json::to_value(entity.get::<T>()?).ok()
+}
+
+fn serialize_resource<T: Resource + Serialize>(world: &World) -> Option<Value> {
+    serde_json::to_value(world.get_resource::<T>()?).ok()
+}
+
+/// Registration of replicated types, the client must register the same ones.
+pub trait ReplicationAppExt {
+    /// Replicates `T` on entities marked [`Replicated`], registering a name twice panics.
+    fn replicate<T: Component + Serialize + Replicable>(&mut self) -> &mut Self;
+    fn replicate_resource<T: Resource + Serialize + Replicable>(&mut self) -> &mut Self;
+}
+
+impl ReplicationAppExt for App {
+    fn replicate<T: Component + Serialize + Replicable>(&mut self) -> &mut Self {
+        self.init_resource::<ReplicationRegistry>();
+        let mut registry = self.world.resource_mut::<ReplicationRegistry>();
+        registry.register(T::NAME);
+        registry
+            .components
+            .push((T::NAME, serialize_component::<T>));
+        self
+    }
+
+    fn replicate_resource<T: Resource + Serialize + Replicable>(&mut self) -> &mut Self {
+        self.init_resource::<ReplicationRegistry>();
+        let mut registry = self.world.resource_mut::<ReplicationRegistry>();
+        registry.register(T::NAME);
+        registry.resources.push((T::NAME, serialize_resource::<T>));
+        self
+    }
+}
+
+#[derive(Resource)]
+pub(crate) struct ReplicationServer {
+    tick: u64,
+    history: VecDeque<(u64, WorldState)>,
+    /// Latest snapshot applied by each session.
+    acks: HashMap<ClientId, u64>,
+    /// Latest snapshot sent to each session, with the connection it was sent on, so it's not
+    /// sent again while unchanged.
+    sent: HashMap<ClientId, (ClientId, u64)>,
+    timer: Timer,
+}
+
+impl ReplicationServer {
+    pub(crate) fn new(interval: Duration) -> Self {
+        Self {
+            tick: 0,
+            history: VecDeque::new(),
+            acks: HashMap::default(),
+            sent: HashMap::default(),
+            timer: Timer::new(interval, TimerMode::Repeating),
+        }
+    }
+
+    pub(crate) fn ack(&mut self, id: ClientId, tick: u64) {
+        if tick <= self.tick {
+            let ack = self.acks.entry(id).or_default();
+            *ack = tick.max(*ack);
+        }
+    }
+
+    fn record(&mut self, state: WorldState) {
+        if self.history.back().map(|(_, last)| last) == Some(&state) {
+            return;
+        }
+        self.tick += 1;
+        self.history.push_back((self.tick, state));
+        if self.history.len() > HISTORY {
+            self.history.pop_front();
+        }
+    }
+
+    /// `None` if the session already has, or is being sent, the latest state.
+    ///
+    /// Deltas are from the latest acked state, as a sent one may be lost with its connection,
+    /// and the state is sent full until one is acked.
+    fn snapshot_for(&mut self, id: &ClientId, connection: ClientId) -> Option<Packet<()>> {
+        let (tick, state) = self.history.back()?;
+        if self.sent.get(id) == Some(&(connection, *tick)) {
+            return None;
+        }
+        let acked = self.acks.get(id).copied();
+        if acked == Some(*tick) {
+            return None;
+        }
+        let base = acked.and_then(|acked| {
+            self.history
+                .iter()
+                .find(|(base_tick, _)| *base_tick == acked)
+                .map(|(base_tick, base)| (*base_tick, base))
+        });
+        let snapshot = Packet::Snapshot(state.delta(*tick, base));
+        self.sent.insert(*id, (connection, *tick));
+        Some(snapshot)
+    }
+}
+
+/// Sends connected sessions the changes since the last state they acknowledged.
+pub(crate) fn send_snapshots<C: Resource + Server + Send + Sync + 'static>(world: &mut World) {
+    let delta = world.resource::<Time<Real>>().delta();
+    let mut replication = world.resource_mut::<ReplicationServer>();
+    replication.timer.tick(delta);
+    if !replication.timer.just_finished() || !world.contains_resource::<C>() {
+        return;
+    }
+    let registry = world.resource::<ReplicationRegistry>();
+    if registry.is_empty() {
+        return;
+    }
+    let state = registry.collect(world);
+    world.resource_scope(|world, mut replication: Mut<ReplicationServer>| {
+        replication.record(state);
+        let sessions = world.resource::<Sessions>();
+        replication.acks.retain(|id, _| sessions.contains(id));
+        replication.sent.retain(|id, _| sessions.contains(id));
+        let snapshots: Vec<_> = sessions
+            .connected()
+            .filter_map(|id| {
+                let connection = sessions.connection(id)?;
+                Some((connection, replication.snapshot_for(id, connection)?))
+            })
+            .collect();
+        let mut com = world.resource_mut::<C>();
+        for (connection, snapshot) in snapshots {
+            com.send(&connection, &snapshot);
+        }
+    });
+}
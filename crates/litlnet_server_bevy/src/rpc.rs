@@ -0,0 +1,69 @@
+use bevy::{prelude::*, utils::HashMap};
+use litlnet_trait::{ClientId, Packet, Rpc, RpcError, Server};
+use serde_json::Value;
+
+use crate::Sessions;
+
+type Handler = Box<dyn Fn(&mut World, ClientId, Value) -> Result<Value, RpcError> + Send + Sync>;
+
+#[derive(Resource, Default)]
+pub(crate) struct RpcServer {
+    handlers: HashMap<&'static str, Handler>,
+    /// Session, request id, method and payload of requests not answered yet.
+    pub(crate) requests: Vec<(ClientId, u64, String, Value)>,
+}
+
+/// Registration of the systems answering requests, their input is the session and the request.
+pub trait RpcAppExt {
+    fn add_rpc_handler<T: Rpc, M>(
+        &mut self,
+        handler: impl IntoSystem<(ClientId, T), T::Response, M> + 'static,
+    ) -> &mut Self;
+}
+
+impl RpcAppExt for App {
+    fn add_rpc_handler<T: Rpc, M>(
+        &mut self,
+        handler: impl IntoSystem<(ClientId, T), T::Response, M> + 'static,
+    ) -> &mut Self {
+        let system = self.world.register_system(handler);
+        self.init_resource::<RpcServer>();
+        self.world.resource_mut::<RpcServer>().handlers.insert(
+            T::METHOD,
+            Box::new(move |world, id, payload| {
+                let request = serde_json::from_value::<T>(payload)
+                    .map_err(|e| RpcError::Invalid(e.to_string()))?;
+                let response = world
+                    .run_system_with_input(system, (id, request))
+                    .map_err(|e| RpcError::Internal(format!("{:?}", e)))?;
+                serde_json::to_value(response).map_err(|e| RpcError::Invalid(e.to_string()))
+            }),
+        );
+        self
+    }
+}
+
+/// Runs the handlers of received requests, responses to disconnected sessions are dropped.
+pub(crate) fn answer_requests<C: Resource + Server + Send + Sync + 'static>(world: &mut World) {
+    if world.resource::<RpcServer>().requests.is_empty() {
+        return;
+    }
+    world.resource_scope(|world, mut rpc: Mut<RpcServer>| {
+        for (id, request_id, method, payload) in std::mem::take(&mut rpc.requests) {
+            let result = match rpc.handlers.get(method.as_str()) {
+                Some(handler) => handler(world, id, payload),
+                None => Err(RpcError::UnknownMethod),
+            };
+            let connection = world.resource::<Sessions>().connection(&id);
+            if let (Some(connection), Some(mut com)) = (connection, world.get_resource_mut::<C>()) {
+                com.send(
+                    &connection,
+                    &Packet::<()>::Response {
+                        id: request_id,
+                        result,
+                    },
+                );
+            }
+        }
+    });
+}
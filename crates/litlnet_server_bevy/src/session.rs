@@ -0,0 +1,170 @@
+use std::time::{Duration, Instant};
+
+use bevy::{prelude::*, utils::HashMap};
+use litlnet_trait::{ClientId, Server};
+
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionEvent {
+    Started(ClientId),
+    /// A new connection presented the token of the session.
+    Resumed(ClientId),
+    /// Messages to the session are kept until it's resumed or its grace period ends.
+    Disconnected(ClientId),
+    Ended(ClientId),
+}
+
+struct Session {
+    token: String,
+    connection: Option<ClientId>,
+    disconnected_at: Option<Instant>,
+}
+
+/// Players as seen by the application.
+///
+/// [`ClientId`]s of [`crate::MessagesToRead`] and [`crate::MessagesToSend`] identify sessions,
+/// which survive reconnections, rather than connections of the server.
+#[derive(Resource)]
+pub struct Sessions {
+    sessions: HashMap<ClientId, Session>,
+    by_connection: HashMap<ClientId, ClientId>,
+    by_token: HashMap<String, ClientId>,
+    next_available_id: ClientId,
+    grace: Duration,
+}
+
+impl Sessions {
+    pub fn new(grace: Duration) -> Self {
+        Self {
+            sessions: HashMap::default(),
+            by_connection: HashMap::default(),
+            by_token: HashMap::default(),
+            next_available_id: ClientId(usize::MIN),
+            grace,
+        }
+    }
+
+    /// All sessions, including disconnected ones which may still be resumed.
+    pub fn iter(&self) -> impl Iterator<Item = &ClientId> + '_ {
+        self.sessions.keys()
+    }
+
+    pub fn connected(&self) -> impl Iterator<Item = &ClientId> + '_ {
+        self.sessions
+            .iter()
+            .filter(|(_, session)| session.connection.is_some())
+            .map(|(id, _)| id)
+    }
+
+    pub fn contains(&self, id: &ClientId) -> bool {
+        self.sessions.contains_key(id)
+    }
+
+    /// Id given by the server to the current connection of the session.
+    pub fn connection(&self, id: &ClientId) -> Option<ClientId> {
+        self.sessions.get(id).and_then(|session| session.connection)
+    }
+
+    pub(crate) fn session_of(&self, connection: &ClientId) -> Option<ClientId> {
+        self.by_connection.get(connection).copied()
+    }
+
+    pub(crate) fn token(&self, id: &ClientId) -> Option<&str> {
+        self.sessions.get(id).map(|session| session.token.as_str())
+    }
+
+    pub(crate) fn start(&mut self, connection: ClientId) -> ClientId {
+        let id = self.next_available_id;
+        self.next_available_id.0 = self.next_available_id.0.wrapping_add(1);
+        let token = new_token();
+        self.by_token.insert(token.clone(), id);
+        self.by_connection.insert(connection, id);
+        self.sessions.insert(
+            id,
+            Session {
+                token,
+                connection: Some(connection),
+                disconnected_at: None,
+            },
+        );
+        id
+    }
+
+    /// Binds the session of `token` to `connection`, returns it with its previous connection,
+    /// which may not have been noticed as dead yet.
+    pub(crate) fn resume(
+        &mut self,
+        token: &str,
+        connection: ClientId,
+    ) -> Option<(ClientId, Option<ClientId>)> {
+        let id = *self.by_token.get(token)?;
+        let session = self.sessions.get_mut(&id)?;
+        let previous = session.connection.replace(connection);
+        session.disconnected_at = None;
+        if let Some(previous) = previous {
+            self.by_connection.remove(&previous);
+        }
+        self.by_connection.insert(connection, id);
+        Some((id, previous))
+    }
+
+    /// Notices connections which are gone and ends sessions disconnected for too long.
+    pub(crate) fn update(
+        &mut self,
+        connections: &[ClientId],
+        now: Instant,
+        mut on_event: impl FnMut(SessionEvent),
+    ) {
+        for (id, session) in self.sessions.iter_mut() {
+            if let Some(connection) = session.connection {
+                if !connections.contains(&connection) {
+                    self.by_connection.remove(&connection);
+                    session.connection = None;
+                    session.disconnected_at = Some(now);
+                    on_event(SessionEvent::Disconnected(*id));
+                }
+            }
+        }
+        let grace = self.grace;
+        let by_token = &mut self.by_token;
+        self.sessions.retain(|id, session| {
+            let expired = session
+                .disconnected_at
+                .is_some_and(|at| now.duration_since(at) > grace);
+            if expired {
+                by_token.remove(&session.token);
+                on_event(SessionEvent::Ended(*id));
+            }
+            !expired
+        });
+    }
+}
+
+/// 128 bits from the OS random generator, as hex.
+fn new_token() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).expect("no OS random generator for session tokens");
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub(crate) fn update_sessions<C: Resource + Server + Send + Sync + 'static>(
+    com: Option<Res<C>>,
+    mut sessions: ResMut<Sessions>,
+    mut events: EventWriter<SessionEvent>,
+) {
+    let now = Instant::now();
+    // A new server reuses connection ids, they must not be mistaken for the previous ones.
+    let is_new_server = match com.as_ref() {
+        Some(com) => com.is_added(),
+        None => true,
+    };
+    if is_new_server {
+        sessions.update(&[], now, |event| {
+            events.send(event);
+        });
+    }
+    if let Some(com) = com.as_ref() {
+        sessions.update(&com.clients(), now, |event| {
+            events.send(event);
+        });
+    }
+}
@@ -0,0 +1,81 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+    utils::HashMap,
+};
+use litlnet_trait::{ClientId, Server, Traffic};
+
+pub const BYTES_SENT: DiagnosticPath = DiagnosticPath::const_new("litlnet/server/bytes_sent");
+pub const BYTES_RECEIVED: DiagnosticPath =
+    DiagnosticPath::const_new("litlnet/server/bytes_received");
+pub const MESSAGES_SENT: DiagnosticPath = DiagnosticPath::const_new("litlnet/server/messages_sent");
+pub const MESSAGES_RECEIVED: DiagnosticPath =
+    DiagnosticPath::const_new("litlnet/server/messages_received");
+pub const DECODE_ERRORS: DiagnosticPath = DiagnosticPath::const_new("litlnet/server/decode_errors");
+pub const CLIENTS: DiagnosticPath = DiagnosticPath::const_new("litlnet/server/clients");
+
+/// Traffic of the server, in total and for each connected client.
+#[derive(Resource, Debug)]
+pub struct NetStats {
+    pub total: Traffic,
+    /// Traffic during the last second.
+    pub per_second: Traffic,
+    /// Totals of currently connected clients.
+    pub per_client: HashMap<ClientId, Traffic>,
+    window: Traffic,
+    window_timer: Timer,
+}
+
+impl Default for NetStats {
+    fn default() -> Self {
+        Self {
+            total: Traffic::default(),
+            per_second: Traffic::default(),
+            per_client: HashMap::default(),
+            window: Traffic::default(),
+            window_timer: Timer::from_seconds(1f32, TimerMode::Repeating),
+        }
+    }
+}
+
+pub(crate) fn register_diagnostics(app: &mut App) {
+    app.register_diagnostic(Diagnostic::new(BYTES_SENT).with_suffix(" B/s"))
+        .register_diagnostic(Diagnostic::new(BYTES_RECEIVED).with_suffix(" B/s"))
+        .register_diagnostic(Diagnostic::new(MESSAGES_SENT).with_suffix(" msg/s"))
+        .register_diagnostic(Diagnostic::new(MESSAGES_RECEIVED).with_suffix(" msg/s"))
+        .register_diagnostic(Diagnostic::new(DECODE_ERRORS))
+        .register_diagnostic(Diagnostic::new(CLIENTS));
+}
+
+pub(crate) fn update_stats<C: Resource + Server + Send + Sync + 'static>(
+    time: Res<Time>,
+    com: Option<Res<C>>,
+    mut stats: ResMut<NetStats>,
+    mut diagnostics: Diagnostics,
+) {
+    if let Some(com) = com.as_ref() {
+        let mut per_client = HashMap::default();
+        let mut delta = Traffic::default();
+        for (id, current) in com.clients_traffic() {
+            let previous = stats.per_client.get(&id).copied().unwrap_or_default();
+            delta = delta + current.saturating_sub(previous);
+            per_client.insert(id, current);
+        }
+        stats.per_client = per_client;
+        stats.total = stats.total + delta;
+        stats.window = stats.window + delta;
+    }
+    stats.window_timer.tick(time.delta());
+    if !stats.window_timer.just_finished() {
+        return;
+    }
+    stats.per_second = stats.window;
+    stats.window = Traffic::default();
+    let per_second = stats.per_second;
+    diagnostics.add_measurement(&BYTES_SENT, || per_second.bytes_sent as f64);
+    diagnostics.add_measurement(&BYTES_RECEIVED, || per_second.bytes_received as f64);
+    diagnostics.add_measurement(&MESSAGES_SENT, || per_second.messages_sent as f64);
+    diagnostics.add_measurement(&MESSAGES_RECEIVED, || per_second.messages_received as f64);
+    diagnostics.add_measurement(&DECODE_ERRORS, || stats.total.decode_errors as f64);
+    diagnostics.add_measurement(&CLIENTS, || stats.per_client.len() as f64);
+}
@@ -0,0 +1,523 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bevy::{ecs::event::Events, prelude::*};
+use litlnet_server_bevy::{
+    MessagesToRead, MessagesToSend, RComServer, RateLimit, RateLimitAction, RateLimitExceeded,
+    RateLimiter, Replicated, ReplicationAppExt, RpcAppExt, SendConfig, ServerPlugin, SessionEvent,
+    Sessions, REQUEST_CLASS, TIME_REQUEST_CLASS,
+};
+use litlnet_trait::{replication::Snapshot, ClientId, Packet, Replicable, Rpc, Server};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+/// Packets in flight between the test and [`MockServer`], by connection.
+#[derive(Default)]
+struct Network {
+    connected: Vec<ClientId>,
+    to_server: Vec<(ClientId, Value)>,
+    to_clients: Vec<(ClientId, Value)>,
+}
+
+/// Only implements what [`Server`] requires, and `disconnect` to see it.
+struct MockServer {
+    network: Arc<Mutex<Network>>,
+}
+
+impl Server for MockServer {
+    fn bind(_: &str) -> Result<Self, std::io::Error> {
+        Err(std::io::Error::other("bound by the test"))
+    }
+
+    fn accept_connections(&mut self) {}
+
+    fn receive_all<T: DeserializeOwned>(
+        &mut self,
+        mut read_callback: impl FnMut(ClientId, Vec<T>),
+    ) {
+        let packets = std::mem::take(&mut self.network.lock().unwrap().to_server);
+        for (connection, packet) in packets {
+            read_callback(connection, vec![serde_json::from_value(packet).unwrap()]);
+        }
+    }
+
+    fn send<T: Serialize>(&mut self, client_id: &ClientId, data: &T) {
+        let packet = serde_json::to_value(data).unwrap();
+        self.network
+            .lock()
+            .unwrap()
+            .to_clients
+            .push((*client_id, packet));
+    }
+
+    fn disconnect(&mut self, client_id: &ClientId) {
+        self.network
+            .lock()
+            .unwrap()
+            .connected
+            .retain(|connection| connection != client_id);
+    }
+
+    fn clients(&self) -> Vec<ClientId> {
+        self.network.lock().unwrap().connected.clone()
+    }
+}
+
+type Plugin = ServerPlugin<RComServer<MockServer>, String, String>;
+
+fn server(network: &Arc<Mutex<Network>>) -> App {
+    server_with(network, Plugin::default())
+}
+
+fn server_with(network: &Arc<Mutex<Network>>, plugin: Plugin) -> App {
+    let mut app = App::new();
+    app.add_plugins(plugin);
+    app.add_plugins(MinimalPlugins);
+    app.insert_resource(RComServer {
+        server: MockServer {
+            network: network.clone(),
+        },
+    });
+    app
+}
+
+fn send(network: &Arc<Mutex<Network>>, connection: ClientId, packet: Packet<String>) {
+    let mut network = network.lock().unwrap();
+    if !network.connected.contains(&connection) {
+        network.connected.push(connection);
+    }
+    network
+        .to_server
+        .push((connection, serde_json::to_value(packet).unwrap()));
+}
+
+/// Packets sent to `connection` since the last call.
+fn received(network: &Arc<Mutex<Network>>, connection: ClientId) -> Vec<Packet<String>> {
+    let mut network = network.lock().unwrap();
+    let (to_connection, others) = std::mem::take(&mut network.to_clients)
+        .into_iter()
+        .partition(|(to, _)| *to == connection);
+    network.to_clients = others;
+    to_connection
+        .into_iter()
+        .map(|(_, packet)| serde_json::from_value(packet).unwrap())
+        .collect()
+}
+
+/// Starts a session on `connection`, returns its id and token.
+///
+/// The packets sent with the session are left to be received.
+fn start_session(
+    app: &mut App,
+    network: &Arc<Mutex<Network>>,
+    connection: ClientId,
+) -> (ClientId, String) {
+    send(network, connection, Packet::Message("hi".to_string()));
+    app.update();
+    let mut packets = received(network, connection);
+    let session = match packets.first() {
+        Some(Packet::Session {
+            client_id,
+            token,
+            resumed: false,
+        }) => (*client_id, token.clone()),
+        _ => panic!("no session: {:?}", packets),
+    };
+    let others = packets
+        .drain(1..)
+        .map(|packet| (connection, serde_json::to_value(packet).unwrap()));
+    network.lock().unwrap().to_clients.extend(others);
+    session
+}
+
+fn drop_connection(app: &mut App, network: &Arc<Mutex<Network>>, connection: ClientId) {
+    network
+        .lock()
+        .unwrap()
+        .connected
+        .retain(|connected| *connected != connection);
+    app.update();
+}
+
+fn session_events(app: &App) -> Vec<SessionEvent> {
+    let events = app.world.resource::<Events<SessionEvent>>();
+    events.get_reader().read(events).copied().collect()
+}
+
+fn exceeded(app: &App) -> Vec<RateLimitExceeded> {
+    let events = app.world.resource::<Events<RateLimitExceeded>>();
+    events.get_reader().read(events).cloned().collect()
+}
+
+fn read_messages(app: &mut App) -> Vec<String> {
+    let mut messages = app.world.resource_mut::<MessagesToRead<String>>();
+    std::iter::from_fn(|| messages.pop().map(|(_, message)| message)).collect()
+}
+
+#[test]
+fn messages_over_their_limit_are_dropped() {
+    let network = Arc::default();
+    let mut app = server(&network);
+    app.insert_resource(RateLimiter::new(|_: &String| "chat").with_limit(
+        "chat",
+        RateLimit {
+            per_second: 1f32,
+            burst: 2f32,
+        },
+    ));
+    for i in 0..3 {
+        send(&network, ClientId(0), Packet::Message(i.to_string()));
+    }
+    app.update();
+    assert_eq!(read_messages(&mut app), ["0", "1"]);
+    let exceeded = exceeded(&app);
+    assert_eq!(exceeded.len(), 1);
+    assert_eq!(exceeded[0].class, "chat");
+}
+
+#[test]
+fn time_requests_are_limited_too() {
+    let network = Arc::default();
+    let mut app = server(&network);
+    app.insert_resource(RateLimiter::new(|_: &String| "chat"));
+    for _ in 0..20 {
+        send(
+            &network,
+            ClientId(0),
+            Packet::TimeRequest { client_time: 0f64 },
+        );
+    }
+    app.update();
+    let exceeded = exceeded(&app);
+    assert_eq!(exceeded.len(), 15);
+    assert!(exceeded
+        .iter()
+        .all(|event| event.class == TIME_REQUEST_CLASS));
+}
+
+#[derive(Serialize, Deserialize)]
+struct Double(u32);
+
+impl Rpc for Double {
+    type Response = u32;
+    const METHOD: &'static str = "double";
+}
+
+#[test]
+fn requests_are_limited_too() {
+    let network = Arc::default();
+    let mut app = server(&network);
+    app.insert_resource(RateLimiter::new(|_: &String| "chat"));
+    app.add_rpc_handler(|In((_, Double(n))): In<(ClientId, Double)>| 2 * n);
+    for id in 0..12 {
+        send(
+            &network,
+            ClientId(0),
+            Packet::Request {
+                id,
+                method: Double::METHOD.to_string(),
+                payload: Value::from(id),
+            },
+        );
+    }
+    app.update();
+    app.update();
+    let exceeded = exceeded(&app);
+    assert_eq!(exceeded.len(), 2);
+    assert!(exceeded.iter().all(|event| event.class == REQUEST_CLASS));
+    let responses: Vec<_> = received(&network, ClientId(0))
+        .into_iter()
+        .filter_map(|packet| match packet {
+            Packet::Response { id, result } => Some((id, result.unwrap())),
+            _ => None,
+        })
+        .collect();
+    let expected: Vec<_> = (0..10).map(|id| (id, Value::from(2 * id))).collect();
+    assert_eq!(responses, expected);
+}
+
+#[test]
+fn flooding_client_is_disconnected() {
+    let network = Arc::default();
+    let mut app = server(&network);
+    app.insert_resource(
+        RateLimiter::new(|_: &String| "chat")
+            .with_limit(
+                "chat",
+                RateLimit {
+                    per_second: 1f32,
+                    burst: 1f32,
+                },
+            )
+            .with_action(RateLimitAction::Disconnect),
+    );
+    for connection in [ClientId(0), ClientId(0), ClientId(1)] {
+        send(&network, connection, Packet::Message("hi".to_string()));
+    }
+    app.update();
+    assert_eq!(network.lock().unwrap().connected, [ClientId(1)]);
+    assert_eq!(read_messages(&mut app).len(), 2);
+}
+
+#[test]
+fn session_is_resumed_with_its_token() {
+    let network = Arc::default();
+    let mut app = server(&network);
+    let (id, token) = start_session(&mut app, &network, ClientId(0));
+    drop_connection(&mut app, &network, ClientId(0));
+    app.world
+        .resource_mut::<MessagesToSend<String>>()
+        .push((id, "while away".to_string()));
+    app.update();
+    send(
+        &network,
+        ClientId(1),
+        Packet::Resume {
+            token: token.clone(),
+        },
+    );
+    app.update();
+    app.update();
+    assert_eq!(
+        received(&network, ClientId(1)),
+        [
+            Packet::Session {
+                client_id: id,
+                token,
+                resumed: true,
+            },
+            Packet::Message("while away".to_string()),
+        ]
+    );
+    assert_eq!(
+        app.world.resource::<Sessions>().connection(&id),
+        Some(ClientId(1))
+    );
+    assert!(session_events(&app).contains(&SessionEvent::Resumed(id)));
+}
+
+#[test]
+fn session_expires_after_its_grace_period() {
+    let network = Arc::default();
+    let mut app = server(&network);
+    app.insert_resource(Sessions::new(Duration::ZERO));
+    let (id, token) = start_session(&mut app, &network, ClientId(0));
+    drop_connection(&mut app, &network, ClientId(0));
+    app.update();
+    assert!(session_events(&app).contains(&SessionEvent::Ended(id)));
+    assert!(!app.world.resource::<Sessions>().contains(&id));
+    send(&network, ClientId(1), Packet::Resume { token });
+    app.update();
+    match received(&network, ClientId(1)).as_slice() {
+        [Packet::Session {
+            client_id,
+            resumed: false,
+            ..
+        }] => assert_ne!(*client_id, id),
+        packets => panic!("no new session: {:?}", packets),
+    }
+}
+
+#[test]
+fn wrong_token_starts_a_new_session() {
+    let network = Arc::default();
+    let mut app = server(&network);
+    let (id, token) = start_session(&mut app, &network, ClientId(0));
+    drop_connection(&mut app, &network, ClientId(0));
+    send(
+        &network,
+        ClientId(1),
+        Packet::Resume {
+            token: "0".repeat(token.len()),
+        },
+    );
+    app.update();
+    match received(&network, ClientId(1)).as_slice() {
+        [Packet::Session {
+            client_id,
+            token: new_token,
+            resumed: false,
+        }] => {
+            assert_ne!(*client_id, id);
+            assert_ne!(*new_token, token);
+        }
+        packets => panic!("no new session: {:?}", packets),
+    }
+    // The session is still waiting for its player.
+    assert_eq!(app.world.resource::<Sessions>().connection(&id), None);
+    assert!(app.world.resource::<Sessions>().contains(&id));
+}
+
+#[test]
+fn tokens_are_128_random_bits() {
+    let network = Arc::default();
+    let mut app = server(&network);
+    let tokens: Vec<String> = (0..8)
+        .map(|connection| start_session(&mut app, &network, ClientId(connection)).1)
+        .collect();
+    for (i, token) in tokens.iter().enumerate() {
+        assert_eq!(token.len(), 32);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert!(!tokens[i + 1..].contains(token));
+    }
+}
+
+#[test]
+fn only_the_latest_messages_are_kept_for_a_disconnected_session() {
+    let network = Arc::default();
+    let mut app = server(&network);
+    app.insert_resource(SendConfig {
+        max_pending_messages: 2,
+        ..Default::default()
+    });
+    let (id, token) = start_session(&mut app, &network, ClientId(0));
+    drop_connection(&mut app, &network, ClientId(0));
+    for i in 0..5 {
+        app.world
+            .resource_mut::<MessagesToSend<String>>()
+            .push((id, i.to_string()));
+    }
+    app.update();
+    send(&network, ClientId(1), Packet::Resume { token });
+    app.update();
+    app.update();
+    let messages: Vec<_> = received(&network, ClientId(1))
+        .into_iter()
+        .filter_map(|packet| match packet {
+            Packet::Message(message) => Some(message),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(messages, ["3", "4"]);
+}
+
+#[derive(Component, Resource, Serialize, Clone, Copy)]
+struct Counter(u32);
+
+impl Replicable for Counter {
+    const NAME: &'static str = "counter";
+}
+
+#[derive(Component, Serialize, Clone, Copy)]
+struct Position(u32);
+
+impl Replicable for Position {
+    const NAME: &'static str = "position";
+}
+
+/// Replicates [`Counter`] on every update.
+fn replicated_server(network: &Arc<Mutex<Network>>) -> App {
+    let mut app = server_with(
+        network,
+        Plugin::default().with_replication_interval(Duration::ZERO),
+    );
+    app.replicate_resource::<Counter>();
+    app.insert_resource(Counter(0));
+    app
+}
+
+fn snapshots(network: &Arc<Mutex<Network>>, connection: ClientId) -> Vec<Snapshot> {
+    received(network, connection)
+        .into_iter()
+        .filter_map(|packet| match packet {
+            Packet::Snapshot(snapshot) => Some(snapshot),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn full_snapshots_are_sent_until_acked_then_deltas_from_the_ack() {
+    let network = Arc::default();
+    let mut app = replicated_server(&network);
+    start_session(&mut app, &network, ClientId(0));
+    for _ in 0..5 {
+        app.update();
+    }
+    let first = match snapshots(&network, ClientId(0)).as_slice() {
+        [first] => first.clone(),
+        snapshots => panic!("not a single snapshot: {:?}", snapshots),
+    };
+    assert_eq!(first.base, None);
+    assert_eq!(first.resources["counter"], 0);
+
+    // Not acked yet, so it may never have been received.
+    app.insert_resource(Counter(1));
+    app.update();
+    let full = match snapshots(&network, ClientId(0)).as_slice() {
+        [full] => full.clone(),
+        snapshots => panic!("not a single snapshot: {:?}", snapshots),
+    };
+    assert_eq!(full.base, None);
+    assert_eq!(full.resources["counter"], 1);
+
+    send(
+        &network,
+        ClientId(0),
+        Packet::SnapshotAck { tick: full.tick },
+    );
+    app.update();
+    app.insert_resource(Counter(2));
+    app.update();
+    match snapshots(&network, ClientId(0)).as_slice() {
+        [delta] => {
+            assert_eq!(delta.base, Some(full.tick));
+            assert_eq!(delta.resources["counter"], 2);
+        }
+        snapshots => panic!("not a single snapshot: {:?}", snapshots),
+    }
+}
+
+#[test]
+fn resumed_session_gets_deltas_from_its_ack() {
+    let network = Arc::default();
+    let mut app = replicated_server(&network);
+    let (_, token) = start_session(&mut app, &network, ClientId(0));
+    app.update();
+    let full = snapshots(&network, ClientId(0)).remove(0);
+    // Snapshots in flight on the lost connection may never arrive.
+    app.insert_resource(Counter(1));
+    app.update();
+    send(
+        &network,
+        ClientId(0),
+        Packet::SnapshotAck { tick: full.tick },
+    );
+    app.update();
+    drop_connection(&mut app, &network, ClientId(0));
+
+    send(&network, ClientId(1), Packet::Resume { token });
+    app.update();
+    app.update();
+    match snapshots(&network, ClientId(1)).as_slice() {
+        [delta] => {
+            assert_eq!(delta.base, Some(full.tick));
+            assert_eq!(delta.resources["counter"], 1);
+        }
+        snapshots => panic!("not a single snapshot: {:?}", snapshots),
+    }
+}
+
+#[test]
+fn replicated_entities_are_keyed_by_registered_name() {
+    let network = Arc::default();
+    let mut app = replicated_server(&network);
+    app.replicate::<Position>();
+    app.world.spawn((Replicated, Position(3)));
+    start_session(&mut app, &network, ClientId(0));
+    app.update();
+    let full = snapshots(&network, ClientId(0)).remove(0);
+    assert_eq!(full.entities.len(), 1);
+    assert_eq!(full.entities[0].changed["position"], 3);
+}
+
+#[test]
+#[should_panic(expected = "counter is already replicated")]
+fn names_are_registered_once() {
+    let network = Arc::default();
+    let mut app = replicated_server(&network);
+    app.replicate::<Counter>();
+    app.replicate_resource::<Counter>();
+}
@@ -0,0 +1,84 @@
+//! Compares frames and bytes sent with and without batching, for a few messages per tick.
+//!
+//! Run with `cargo bench -p litlnet_tcp`.
+
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+
+use litlnet_tcp::{Communication, TcpClient};
+use serde::{Deserialize, Serialize};
+
+const TICKS: usize = 200;
+
+#[derive(Serialize, Deserialize)]
+enum Sample {
+    Spawn { id: usize, position: (f32, f32) },
+    Dead { mole_id: usize, killer_id: usize },
+}
+
+fn sample(i: usize) -> Sample {
+    match i % 2 {
+        0 => Sample::Spawn {
+            id: i,
+            position: (i as f32 * 1.5, -(i as f32)),
+        },
+        _ => Sample::Dead {
+            mole_id: i - 1,
+            killer_id: i % 7,
+        },
+    }
+}
+
+fn pair() -> (TcpClient, TcpClient) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let sender = TcpClient::connect(&listener.local_addr().unwrap().to_string()).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+    (sender, TcpClient::from_stream(stream).unwrap())
+}
+
+fn run(messages_per_tick: usize, batching: bool) -> (u64, u64, Duration) {
+    let (mut sender, mut receiver) = pair();
+    let start = Instant::now();
+    let mut received = 0;
+    for tick in 0..TICKS {
+        let messages: Vec<Sample> = (0..messages_per_tick)
+            .map(|i| sample(tick * messages_per_tick + i))
+            .collect();
+        if batching {
+            sender.send_batch(&messages).unwrap();
+        } else {
+            for message in &messages {
+                sender.send(message).unwrap();
+            }
+        }
+        while let Ok(Some(messages)) = receiver.receive::<Sample>() {
+            received += messages.len();
+        }
+    }
+    while received < TICKS * messages_per_tick {
+        if let Some(messages) = receiver.receive::<Sample>().unwrap() {
+            received += messages.len();
+        }
+    }
+    let traffic = sender.traffic();
+    (traffic.frames_sent, traffic.bytes_sent, start.elapsed())
+}
+
+fn main() {
+    println!("{} ticks", TICKS);
+    println!("msg/tick | frames (single / batch) | bytes (single / batch) | time (single / batch)");
+    for messages_per_tick in [1, 5, 20, 50] {
+        let (single_frames, single_bytes, single_time) = run(messages_per_tick, false);
+        let (batch_frames, batch_bytes, batch_time) = run(messages_per_tick, true);
+        println!(
+            "{:>8} | {:>10} / {:<10} | {:>10} / {:<10} | {:>9.2?} / {:.2?}",
+            messages_per_tick,
+            single_frames,
+            batch_frames,
+            single_bytes,
+            batch_bytes,
+            single_time,
+            batch_time
+        );
+    }
+}
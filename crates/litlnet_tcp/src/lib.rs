@@ -1,6 +1,6 @@
 pub use litlnet_trait::Communication;
+use litlnet_trait::{framing, Compression, DecodeLimits, Traffic};
 use serde::{de::DeserializeOwned, Serialize};
-use serde_json::Deserializer;
 use std::{
     io::{Read, Write},
     net::TcpStream,
@@ -8,40 +8,122 @@ use std::{
 
 pub struct TcpClient {
     stream: TcpStream,
+    limits: DecodeLimits,
+    /// Bytes received but not yet decoded, a frame can be split across reads.
+    pending: Vec<u8>,
+    traffic: Traffic,
+    compression: Option<Compression>,
+    /// Whether the peer sent [`framing::COMPRESSION_OFFER`].
+    is_compression_offered: bool,
 }
 
 impl TcpClient {
     pub fn connect(remote_addr: &str) -> Result<Self, std::io::Error> {
         let stream = TcpStream::connect(remote_addr)?;
-        stream.set_nonblocking(true)?;
-        Ok(Self { stream })
+        Self::from_stream(stream)
     }
     pub fn from_stream(stream: TcpStream) -> Result<Self, std::io::Error> {
         stream.set_nonblocking(true)?;
-        Ok(Self { stream })
+        Ok(Self {
+            stream,
+            limits: DecodeLimits::default(),
+            pending: vec![],
+            traffic: Traffic::default(),
+            compression: None,
+            is_compression_offered: false,
+        })
+    }
+    pub fn set_limits(&mut self, limits: DecodeLimits) {
+        self.limits = limits;
+    }
+    /// Offers compression to the peer with [`framing::COMPRESSION_OFFER`],
+    /// frames are sent compressed once the peer offered it too.
+    ///
+    /// Compressed frames are always accepted when receiving.
+    pub fn set_compression(&mut self, compression: Option<Compression>) -> std::io::Result<()> {
+        if compression.is_some() && self.compression.is_none() {
+            self.stream.write_all(&[framing::COMPRESSION_OFFER])?;
+            self.traffic.bytes_sent += 1;
+        }
+        self.compression = compression;
+        Ok(())
+    }
+
+    fn write_frame(&mut self, buf: Vec<u8>, messages: usize) -> std::io::Result<()> {
+        let buf = match self.compression {
+            Some(compression) if self.is_compression_offered => {
+                framing::compress(buf, &compression)?
+            }
+            _ => buf,
+        };
+        self.stream.write_all(buf.as_slice())?;
+        self.traffic.bytes_sent += buf.len() as u64;
+        self.traffic.frames_sent += 1;
+        self.traffic.messages_sent += messages as u64;
+        Ok(())
+    }
+
+    fn decode_pending<T: DeserializeOwned>(&mut self) -> Result<Vec<T>, std::io::Error> {
+        let mut res = vec![];
+        let mut offset = 0;
+        loop {
+            let rest = &self.pending[offset..];
+            let whitespaces = rest.iter().take_while(|b| b.is_ascii_whitespace()).count();
+            offset += whitespaces;
+            let rest = &rest[whitespaces..];
+            if rest.is_empty() {
+                break;
+            }
+            if rest[0] == framing::COMPRESSION_OFFER {
+                self.is_compression_offered = true;
+                offset += 1;
+                continue;
+            }
+            match framing::frame_len(rest) {
+                Some(len) if len <= rest.len() => {
+                    let decoded = framing::decode_frame(&rest[..len], &self.limits)?;
+                    self.traffic.frames_received += 1;
+                    self.traffic.decode_errors += decoded.errors;
+                    res.extend(decoded.messages);
+                    offset += len;
+                }
+                // The size limit also covers an incomplete frame waiting for more bytes.
+                Some(len) => {
+                    self.limits.check_size(len)?;
+                    break;
+                }
+                None => {
+                    self.limits.check_size(rest.len())?;
+                    break;
+                }
+            }
+        }
+        self.pending.drain(0..offset);
+        Ok(res)
     }
 }
 
 impl Communication for TcpClient {
     fn receive<T: DeserializeOwned>(&mut self) -> Result<Option<Vec<T>>, std::io::Error> {
-        let mut buff = vec![0; 256];
+        let mut buff = vec![0; 4096];
         match self.stream.read(&mut buff) {
+            Ok(0) => Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionAborted,
+                "connection closed",
+            )),
             Ok(amt) => {
-                let de = Deserializer::from_slice(&buff[0..amt]);
-
-                let stream = de.into_iter::<T>();
-                let mut res = vec![];
-                for v in stream {
-                    match v {
-                        Err(e) => {
-                            dbg!(e);
-                        }
-                        Ok(v) => {
-                            res.push(v);
-                        }
+                self.traffic.bytes_received += amt as u64;
+                self.pending.extend_from_slice(&buff[0..amt]);
+                match self.decode_pending::<T>() {
+                    Ok(res) => {
+                        self.traffic.messages_received += res.len() as u64;
+                        Ok(Some(res))
+                    }
+                    Err(e) => {
+                        self.traffic.decode_errors += 1;
+                        Err(e)
                     }
                 }
-                Ok(Some(res))
             }
             Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(None),
             Err(e) => Err(e),
@@ -49,9 +131,22 @@ impl Communication for TcpClient {
     }
 
     fn send<T: Serialize>(&mut self, message: &T) -> std::io::Result<()> {
-        let mut buf = Vec::new();
-        message.serialize(&mut serde_json::Serializer::new(&mut buf))?;
-        self.stream.write_all(buf.as_slice())?;
-        Ok(())
+        let buf = framing::encode(message)?;
+        self.write_frame(buf, 1)
+    }
+
+    fn send_batch<T: Serialize>(&mut self, messages: &[T]) -> std::io::Result<()> {
+        match messages {
+            [] => return Ok(()),
+            // The batch header would only be overhead.
+            [message] => return self.send(message),
+            _ => {}
+        }
+        let buf = framing::encode_batch(messages)?;
+        self.write_frame(buf, messages.len())
+    }
+
+    fn traffic(&self) -> Traffic {
+        self.traffic
     }
 }
@@ -0,0 +1,66 @@
+use std::{
+    net::{TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+use litlnet_tcp::{Communication, TcpClient};
+use litlnet_trait::Compression;
+
+const ALWAYS: Compression = Compression {
+    min_size: 0,
+    level: 6,
+};
+
+fn pair() -> (TcpClient, TcpClient) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (server, _) = listener.accept().unwrap();
+    (
+        TcpClient::from_stream(client).unwrap(),
+        TcpClient::from_stream(server).unwrap(),
+    )
+}
+
+fn message() -> String {
+    "mole ".repeat(200)
+}
+
+/// Sends a message from `from` to `to`, returning how many bytes it took.
+fn exchange(from: &mut TcpClient, to: &mut TcpClient) -> u64 {
+    let sent = from.traffic().bytes_sent;
+    from.send(&message()).unwrap();
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(5) {
+        if let Some(messages) = to.receive::<String>().unwrap() {
+            if !messages.is_empty() {
+                assert_eq!(messages, [message()]);
+                return from.traffic().bytes_sent - sent;
+            }
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    panic!("nothing received");
+}
+
+#[test]
+fn compressed_once_both_sides_offered_it() {
+    let (mut client, mut server) = pair();
+    client.set_compression(Some(ALWAYS)).unwrap();
+    server.set_compression(Some(ALWAYS)).unwrap();
+    // The server's offer isn't received yet.
+    assert!(exchange(&mut client, &mut server) > message().len() as u64);
+    assert!(exchange(&mut server, &mut client) < message().len() as u64 / 10);
+    assert!(exchange(&mut client, &mut server) < message().len() as u64 / 10);
+}
+
+#[test]
+fn not_compressed_for_a_peer_which_did_not_offer_it() {
+    let (mut client, mut server) = pair();
+    client.set_compression(Some(ALWAYS)).unwrap();
+    for _ in 0..2 {
+        assert!(exchange(&mut client, &mut server) > message().len() as u64);
+        assert!(exchange(&mut server, &mut client) > message().len() as u64);
+    }
+    assert_eq!(server.traffic().decode_errors, 0);
+}
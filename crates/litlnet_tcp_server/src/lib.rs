@@ -1,11 +1,15 @@
 use litlnet_tcp::{Communication, TcpClient};
-use litlnet_trait::{ClientId, Server};
+use litlnet_trait::{
+    ClientId, Compression, ConnectionLimiter, ConnectionLimits, DecodeLimits, Server, Traffic,
+};
 use serde::{de::DeserializeOwned, Serialize};
 use std::collections::HashMap;
-use std::net::TcpListener;
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpListener};
+use std::time::Instant;
 
 struct Client {
     com: TcpClient,
+    ip: IpAddr,
 }
 
 pub struct ComServer {
@@ -13,32 +17,68 @@ pub struct ComServer {
     clients: HashMap<ClientId, Client>,
     next_available_id: ClientId,
     to_be_removed: Vec<ClientId>,
+    limiter: ConnectionLimiter,
+    decode_limits: DecodeLimits,
+    compression: Option<Compression>,
 }
 
 impl ComServer {
     pub fn iter(&self) -> impl Iterator<Item = &ClientId> + '_ {
         self.clients.keys()
     }
+    /// The bound address, to know the port when bound to port 0.
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+    pub fn limits(&self) -> &ConnectionLimits {
+        self.limiter.limits()
+    }
+    pub fn set_limits(&mut self, limits: ConnectionLimits) {
+        self.limiter.set_limits(limits);
+    }
+    /// Applies to clients connecting after this call.
+    pub fn set_decode_limits(&mut self, decode_limits: DecodeLimits) {
+        self.decode_limits = decode_limits;
+    }
+    /// Applies to clients connecting after this call, see [`TcpClient::set_compression`].
+    pub fn set_compression(&mut self, compression: Option<Compression>) {
+        self.compression = compression;
+    }
 }
 
 impl Server for ComServer {
     fn bind(addr: &str) -> Result<Self, std::io::Error> {
-        let listener = TcpListener::bind(addr).unwrap();
+        let listener = TcpListener::bind(addr)?;
         listener.set_nonblocking(true)?;
         Ok(Self {
             listener,
             clients: HashMap::new(),
             next_available_id: ClientId(usize::MIN),
             to_be_removed: vec![],
+            limiter: ConnectionLimiter::new(ConnectionLimits::default()),
+            decode_limits: DecodeLimits::default(),
+            compression: None,
         })
     }
     fn accept_connections(&mut self) {
-        for stream in self.listener.incoming() {
-            match stream {
-                Ok(stream) => {
-                    let client = Client {
-                        com: TcpClient::from_stream(stream).expect("failed to create ComClient"),
-                    };
+        while self.limiter.can_accept(Instant::now()) {
+            match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    if let Err(rejection) = self.limiter.admit(addr.ip(), self.clients.len()) {
+                        // No handshake to answer in raw TCP, closing is the rejection.
+                        println!("Rejected {}: {}", addr, rejection);
+                        let _ = stream.shutdown(Shutdown::Both);
+                        continue;
+                    }
+                    let mut com =
+                        TcpClient::from_stream(stream).expect("failed to create ComClient");
+                    com.set_limits(self.decode_limits);
+                    if let Err(e) = com.set_compression(self.compression) {
+                        dbg!(e);
+                        self.limiter.release(addr.ip());
+                        continue;
+                    }
+                    let client = Client { com, ip: addr.ip() };
                     self.clients.insert(self.next_available_id, client);
                     self.next_available_id.0 = self.next_available_id.0.wrapping_add(1);
                 }
@@ -67,20 +107,46 @@ impl Server for ComServer {
                 }
             }
         }
-        for to_clean in &self.to_be_removed {
-            self.clients.remove(to_clean);
+        for to_clean in self.to_be_removed.drain(..) {
+            if let Some(client) = self.clients.remove(&to_clean) {
+                self.limiter.release(client.ip);
+            }
         }
     }
     fn send<T: Serialize>(&mut self, client_id: &ClientId, data: &T) {
-        match self.clients.get_mut(client_id) {
-            Some(client) => match client.com.send::<T>(data) {
+        if let Some(client) = self.clients.get_mut(client_id) {
+            match client.com.send::<T>(data) {
                 Ok(()) => {}
                 Err(e) => {
                     dbg!(e);
                     self.to_be_removed.push(*client_id);
                 }
-            },
-            None => {}
+            }
         }
     }
+    fn send_batch<T: Serialize>(&mut self, client_id: &ClientId, data: &[T]) {
+        if let Some(client) = self.clients.get_mut(client_id) {
+            match client.com.send_batch::<T>(data) {
+                Ok(()) => {}
+                Err(e) => {
+                    dbg!(e);
+                    self.to_be_removed.push(*client_id);
+                }
+            }
+        }
+    }
+    fn disconnect(&mut self, client_id: &ClientId) {
+        if let Some(client) = self.clients.remove(client_id) {
+            self.limiter.release(client.ip);
+        }
+    }
+    fn clients(&self) -> Vec<ClientId> {
+        self.clients.keys().copied().collect()
+    }
+    fn clients_traffic(&self) -> Vec<(ClientId, Traffic)> {
+        self.clients
+            .iter()
+            .map(|(id, client)| (*id, client.com.traffic()))
+            .collect()
+    }
 }
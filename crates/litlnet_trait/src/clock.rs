@@ -0,0 +1,7 @@
+use std::time::Instant;
+
+/// Time since startup, precise to when it's called rather than to the start of the frame:
+/// `elapsed` is the time since startup at `last_update`, as engines give it for the frame.
+pub fn real_now(elapsed: f64, last_update: Option<Instant>) -> f64 {
+    elapsed + last_update.map_or(0f64, |last_update| last_update.elapsed().as_secs_f64())
+}
@@ -0,0 +1,127 @@
+/// Limits applied by receivers before deserializing anything.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// Maximum size in bytes of a single message (or WebSocket frame).
+    pub max_message_size: usize,
+    /// Maximum nesting of JSON arrays and objects.
+    pub max_depth: usize,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_message_size: 256 * 1024,
+            max_depth: 32,
+        }
+    }
+}
+
+impl DecodeLimits {
+    pub fn check_size(&self, len: usize) -> Result<(), std::io::Error> {
+        if len > self.max_message_size {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "message of {} bytes exceeds {} bytes",
+                    len, self.max_message_size
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks a JSON message against the limits, without allocating.
+    pub fn check(&self, bytes: &[u8]) -> Result<(), std::io::Error> {
+        self.check_size(bytes.len())?;
+        if json_depth(bytes) > self.max_depth {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("message nesting exceeds {}", self.max_depth),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Maximum nesting of arrays and objects in (possibly several concatenated or invalid) JSON values.
+pub fn json_depth(bytes: &[u8]) -> usize {
+    let mut depth = 0usize;
+    let mut max_depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for b in bytes {
+        if in_string {
+            match (escaped, b) {
+                (true, _) => escaped = false,
+                (false, b'\\') => escaped = true,
+                (false, b'"') => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+                max_depth = max_depth.max(depth);
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    max_depth
+}
+
+/// Length of the first JSON value of `bytes`, or `None` if it's not complete yet.
+///
+/// Only delimits the value, it may still be invalid JSON.
+pub fn json_value_len(bytes: &[u8]) -> Option<usize> {
+    match bytes.first()? {
+        b'{' | b'[' => {
+            let mut depth = 0usize;
+            let mut in_string = false;
+            let mut escaped = false;
+            for (i, b) in bytes.iter().enumerate() {
+                if in_string {
+                    match (escaped, b) {
+                        (true, _) => escaped = false,
+                        (false, b'\\') => escaped = true,
+                        (false, b'"') => in_string = false,
+                        _ => {}
+                    }
+                    continue;
+                }
+                match b {
+                    b'"' => in_string = true,
+                    b'[' | b'{' => depth += 1,
+                    b']' | b'}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(i + 1);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            None
+        }
+        b'"' => {
+            let mut escaped = false;
+            for (i, b) in bytes.iter().enumerate().skip(1) {
+                match (escaped, b) {
+                    (true, _) => escaped = false,
+                    (false, b'\\') => escaped = true,
+                    (false, b'"') => return Some(i + 1),
+                    _ => {}
+                }
+            }
+            None
+        }
+        // Numbers and literals end with whatever is not part of them,
+        // an unexpected byte is its own (invalid) value.
+        _ => bytes
+            .iter()
+            .position(|b| !(b.is_ascii_alphanumeric() || matches!(b, b'-' | b'+' | b'.')))
+            .map(|len| len.max(1)),
+    }
+}
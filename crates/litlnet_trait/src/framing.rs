@@ -0,0 +1,166 @@
+use std::io::{Read, Write};
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{decode::json_value_len, DecodeLimits};
+
+/// First byte of a batch frame, a JSON message never starts with it.
+pub const BATCH_MARKER: u8 = 0x01;
+/// First byte of a compressed frame: `[COMPRESSED_MARKER][length: u32 BE][deflate of a frame]`.
+pub const COMPRESSED_MARKER: u8 = 0x02;
+/// A frame of this single byte tells the peer it may send compressed frames,
+/// for streams without a handshake to negotiate it.
+pub const COMPRESSION_OFFER: u8 = 0x03;
+const HEADER_LEN: usize = 5;
+const LEN_LEN: usize = 4;
+
+fn invalid_data(reason: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, reason.to_string())
+}
+
+fn read_len(bytes: &[u8]) -> Option<usize> {
+    let len: [u8; LEN_LEN] = bytes.get(..LEN_LEN)?.try_into().ok()?;
+    Some(u32::from_be_bytes(len) as usize)
+}
+
+/// A frame holding a single message, as JSON.
+pub fn encode<T: Serialize>(message: &T) -> std::io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    message.serialize(&mut serde_json::Serializer::new(&mut buf))?;
+    Ok(buf)
+}
+
+/// A frame holding all `messages`:
+/// `[BATCH_MARKER][body length: u32 BE]` then `[length: u32 BE][JSON]` for each message.
+pub fn encode_batch<T: Serialize>(messages: &[T]) -> std::io::Result<Vec<u8>> {
+    let mut buf = vec![BATCH_MARKER, 0, 0, 0, 0];
+    for message in messages {
+        let start = buf.len();
+        buf.extend_from_slice(&[0; LEN_LEN]);
+        message.serialize(&mut serde_json::Serializer::new(&mut buf))?;
+        let len = (buf.len() - start - LEN_LEN) as u32;
+        buf[start..start + LEN_LEN].copy_from_slice(&len.to_be_bytes());
+    }
+    let body_len = (buf.len() - HEADER_LEN) as u32;
+    buf[1..HEADER_LEN].copy_from_slice(&body_len.to_be_bytes());
+    Ok(buf)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Compression {
+    /// Smaller frames are sent as is.
+    pub min_size: usize,
+    /// From 0 (none) to 9 (best).
+    pub level: u32,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self {
+            min_size: 256,
+            level: 6,
+        }
+    }
+}
+
+/// Deflates `frame` if it's big enough to be worth it.
+pub fn compress(frame: Vec<u8>, compression: &Compression) -> std::io::Result<Vec<u8>> {
+    if frame.len() < compression.min_size {
+        return Ok(frame);
+    }
+    let mut encoder = DeflateEncoder::new(
+        vec![COMPRESSED_MARKER, 0, 0, 0, 0],
+        flate2::Compression::new(compression.level),
+    );
+    encoder.write_all(&frame)?;
+    let mut buf = encoder.finish()?;
+    let body_len = (buf.len() - HEADER_LEN) as u32;
+    buf[1..HEADER_LEN].copy_from_slice(&body_len.to_be_bytes());
+    Ok(buf)
+}
+
+/// Length of the first frame of a byte stream, as soon as it's known.
+///
+/// It can be greater than `bytes.len()` while the frame is being received.
+pub fn frame_len(bytes: &[u8]) -> Option<usize> {
+    match bytes.first()? {
+        &BATCH_MARKER | &COMPRESSED_MARKER => {
+            read_len(&bytes[1..]).map(|body_len| HEADER_LEN + body_len)
+        }
+        &COMPRESSION_OFFER => Some(1),
+        _ => json_value_len(bytes),
+    }
+}
+
+pub struct Decoded<T> {
+    pub messages: Vec<T>,
+    /// Messages which could not be deserialized, they are skipped.
+    pub errors: u64,
+}
+
+/// Decodes a complete frame, exceeding `limits` or a malformed batch is an error.
+pub fn decode_frame<T: DeserializeOwned>(
+    frame: &[u8],
+    limits: &DecodeLimits,
+) -> std::io::Result<Decoded<T>> {
+    if frame.first() == Some(&COMPRESSED_MARKER) {
+        let frame = decompress(frame, limits)?;
+        if frame.first() == Some(&COMPRESSED_MARKER) {
+            return Err(invalid_data("nested compression"));
+        }
+        return decode_frame(&frame, limits);
+    }
+    let mut decoded = Decoded {
+        messages: vec![],
+        errors: 0,
+    };
+    if frame.first() != Some(&BATCH_MARKER) {
+        decode_json(frame, limits, &mut decoded)?;
+        return Ok(decoded);
+    }
+    limits.check_size(frame.len())?;
+    if frame_len(frame) != Some(frame.len()) {
+        return Err(invalid_data("batch length mismatch"));
+    }
+    let mut body = &frame[HEADER_LEN..];
+    while !body.is_empty() {
+        let len = read_len(body).ok_or_else(|| invalid_data("truncated batch"))?;
+        let message = body
+            .get(LEN_LEN..LEN_LEN + len)
+            .ok_or_else(|| invalid_data("truncated batch"))?;
+        decode_json(message, limits, &mut decoded)?;
+        body = &body[LEN_LEN + len..];
+    }
+    Ok(decoded)
+}
+
+fn decompress(frame: &[u8], limits: &DecodeLimits) -> std::io::Result<Vec<u8>> {
+    limits.check_size(frame.len())?;
+    if frame_len(frame) != Some(frame.len()) {
+        return Err(invalid_data("compressed length mismatch"));
+    }
+    // Reading one byte over the limit is enough to tell a decompression bomb.
+    let mut res = vec![];
+    DeflateDecoder::new(&frame[HEADER_LEN..])
+        .take(limits.max_message_size as u64 + 1)
+        .read_to_end(&mut res)?;
+    limits.check_size(res.len())?;
+    Ok(res)
+}
+
+fn decode_json<T: DeserializeOwned>(
+    bytes: &[u8],
+    limits: &DecodeLimits,
+    decoded: &mut Decoded<T>,
+) -> std::io::Result<()> {
+    limits.check(bytes)?;
+    match serde_json::from_slice(bytes) {
+        Ok(message) => decoded.messages.push(message),
+        Err(e) => {
+            dbg!(e);
+            decoded.errors += 1;
+        }
+    }
+    Ok(())
+}
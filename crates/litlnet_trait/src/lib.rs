@@ -1,6 +1,22 @@
-use serde::{de::DeserializeOwned, Serialize};
+mod clock;
+mod decode;
+pub mod framing;
+mod limits;
+mod packet;
+pub mod replication;
+mod rpc;
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+pub use clock::real_now;
+pub use decode::{json_depth, json_value_len, DecodeLimits};
+pub use framing::Compression;
+pub use limits::{ConnectionLimiter, ConnectionLimits, Rejection, TokenBucket};
+pub use packet::Packet;
+pub use replication::Replicable;
+pub use rpc::{Rpc, RpcError};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub struct ClientId(pub usize);
 
 impl From<ClientId> for usize {
@@ -9,10 +25,64 @@ impl From<ClientId> for usize {
     }
 }
 
+/// Cumulative counters of a connection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Traffic {
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// A frame holds one message, or several with batching.
+    pub frames_sent: u64,
+    pub frames_received: u64,
+    pub messages_sent: u64,
+    pub messages_received: u64,
+    pub decode_errors: u64,
+}
+
+impl std::ops::Add for Traffic {
+    type Output = Traffic;
+
+    fn add(self, rhs: Traffic) -> Traffic {
+        Traffic {
+            bytes_sent: self.bytes_sent + rhs.bytes_sent,
+            bytes_received: self.bytes_received + rhs.bytes_received,
+            frames_sent: self.frames_sent + rhs.frames_sent,
+            frames_received: self.frames_received + rhs.frames_received,
+            messages_sent: self.messages_sent + rhs.messages_sent,
+            messages_received: self.messages_received + rhs.messages_received,
+            decode_errors: self.decode_errors + rhs.decode_errors,
+        }
+    }
+}
+
+impl Traffic {
+    pub fn saturating_sub(self, rhs: Traffic) -> Traffic {
+        Traffic {
+            bytes_sent: self.bytes_sent.saturating_sub(rhs.bytes_sent),
+            bytes_received: self.bytes_received.saturating_sub(rhs.bytes_received),
+            frames_sent: self.frames_sent.saturating_sub(rhs.frames_sent),
+            frames_received: self.frames_received.saturating_sub(rhs.frames_received),
+            messages_sent: self.messages_sent.saturating_sub(rhs.messages_sent),
+            messages_received: self.messages_received.saturating_sub(rhs.messages_received),
+            decode_errors: self.decode_errors.saturating_sub(rhs.decode_errors),
+        }
+    }
+}
+
 pub trait Communication {
     fn receive<T: DeserializeOwned>(&mut self) -> Result<Option<Vec<T>>, std::io::Error>;
     fn send<T: Serialize>(&mut self, message: &T) -> std::io::Result<()>;
+    /// Sends all `messages` in a single frame, `receive` unpacks them transparently.
+    fn send_batch<T: Serialize>(&mut self, messages: &[T]) -> std::io::Result<()> {
+        for message in messages {
+            self.send(message)?;
+        }
+        Ok(())
+    }
+    fn traffic(&self) -> Traffic {
+        Traffic::default()
+    }
 }
+/// A transport accepting clients.
 pub trait Server {
     fn bind(addr: &str) -> Result<Self, std::io::Error>
     where
@@ -20,4 +90,17 @@ pub trait Server {
     fn accept_connections(&mut self);
     fn receive_all<T: DeserializeOwned>(&mut self, read_callback: impl FnMut(ClientId, Vec<T>));
     fn send<T: Serialize>(&mut self, client_id: &ClientId, data: &T);
+    fn send_batch<T: Serialize>(&mut self, client_id: &ClientId, data: &[T]) {
+        for message in data {
+            self.send(client_id, message);
+        }
+    }
+    /// Closes the connection of `client_id`, servers which can't keep it until it drops.
+    fn disconnect(&mut self, _client_id: &ClientId) {}
+    /// The `ClientId`s of the currently connected clients, which `litlnet_server_bevy` tracks
+    /// sessions with.
+    fn clients(&self) -> Vec<ClientId>;
+    fn clients_traffic(&self) -> Vec<(ClientId, Traffic)> {
+        vec![]
+    }
 }
@@ -0,0 +1,144 @@
+use std::{collections::HashMap, fmt, net::IpAddr, time::Instant};
+
+/// Limits applied by servers when accepting new connections.
+///
+/// `None` means unlimited, which is the default.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionLimits {
+    pub max_clients: Option<usize>,
+    pub max_connections_per_ip: Option<usize>,
+    /// Connections above this rate are left in the listener backlog until the next accept.
+    /// Must be above 0, or no connection is accepted once the first one took the token.
+    pub max_accepts_per_second: Option<f32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    ServerFull,
+    TooManyConnectionsFromIp,
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::ServerFull => write!(f, "server is full"),
+            Rejection::TooManyConnectionsFromIp => write!(f, "too many connections from this ip"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TokenBucket {
+    refill_per_second: f32,
+    capacity: f32,
+    tokens: f32,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(refill_per_second: f32, capacity: f32) -> Self {
+        Self {
+            refill_per_second,
+            capacity,
+            tokens: capacity,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f32();
+        self.tokens = (self.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Returns true if a token would be available at `now`, without consuming it.
+    pub fn has_token(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= 1f32
+    }
+
+    /// A full bucket behaves exactly like a new one, so it can be dropped.
+    pub fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.capacity
+    }
+
+    pub fn try_take(&mut self, now: Instant) -> bool {
+        if self.has_token(now) {
+            self.tokens -= 1f32;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Bookkeeping shared by the litlnet servers to enforce [`ConnectionLimits`].
+pub struct ConnectionLimiter {
+    limits: ConnectionLimits,
+    per_ip: HashMap<IpAddr, usize>,
+    accept_bucket: Option<TokenBucket>,
+}
+
+impl ConnectionLimiter {
+    pub fn new(limits: ConnectionLimits) -> Self {
+        let mut res = Self {
+            limits: ConnectionLimits::default(),
+            per_ip: HashMap::new(),
+            accept_bucket: None,
+        };
+        res.set_limits(limits);
+        res
+    }
+
+    pub fn limits(&self) -> &ConnectionLimits {
+        &self.limits
+    }
+
+    /// Changes the limits, already connected clients are kept even if over the new limits.
+    pub fn set_limits(&mut self, limits: ConnectionLimits) {
+        self.accept_bucket = limits
+            .max_accepts_per_second
+            .map(|rate| TokenBucket::new(rate, rate.max(1f32)));
+        self.limits = limits;
+    }
+
+    /// Returns false while the accept rate is exceeded.
+    pub fn can_accept(&mut self, now: Instant) -> bool {
+        match self.accept_bucket.as_mut() {
+            Some(bucket) => bucket.has_token(now),
+            None => true,
+        }
+    }
+
+    /// Registers a new connection from `ip`, `connected_clients` being the current number of clients.
+    ///
+    /// On success, [`ConnectionLimiter::release`] must be called when the client goes away.
+    pub fn admit(&mut self, ip: IpAddr, connected_clients: usize) -> Result<(), Rejection> {
+        if let Some(bucket) = self.accept_bucket.as_mut() {
+            bucket.try_take(Instant::now());
+        }
+        if let Some(max_clients) = self.limits.max_clients {
+            if connected_clients >= max_clients {
+                return Err(Rejection::ServerFull);
+            }
+        }
+        let count = self.per_ip.entry(ip).or_insert(0);
+        if let Some(max_per_ip) = self.limits.max_connections_per_ip {
+            if *count >= max_per_ip {
+                return Err(Rejection::TooManyConnectionsFromIp);
+            }
+        }
+        *count += 1;
+        Ok(())
+    }
+
+    pub fn release(&mut self, ip: IpAddr) {
+        if let Some(count) = self.per_ip.get_mut(&ip) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                self.per_ip.remove(&ip);
+            }
+        }
+    }
+}
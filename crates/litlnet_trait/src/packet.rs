@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{replication::Snapshot, ClientId, RpcError};
+
+/// What the bevy plugins exchange: the application's messages, or their own.
+///
+/// Times are in seconds, as `Time<Real>::elapsed_seconds_f64` of the sender.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Packet<T> {
+    Message(T),
+    TimeRequest {
+        client_time: f64,
+    },
+    /// Echoes `client_time`, `server_time` is when the request was handled.
+    TimeResponse {
+        client_time: f64,
+        server_time: f64,
+    },
+    /// Answers the first packet of a connection, `resumed` tells if it was an accepted `Resume`.
+    Session {
+        client_id: ClientId,
+        token: String,
+        resumed: bool,
+    },
+    /// Only honored as the first packet of a connection.
+    Resume {
+        token: String,
+    },
+    Snapshot(Snapshot),
+    /// The client applied the snapshot of `tick`, it can be the base of the next ones.
+    SnapshotAck {
+        tick: u64,
+    },
+    /// `id` is chosen by the client, and echoed by the `Response`.
+    Request {
+        id: u64,
+        method: String,
+        payload: Value,
+    },
+    Response {
+        id: u64,
+        result: Result<Value, RpcError>,
+    },
+}
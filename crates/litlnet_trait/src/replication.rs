@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A replicated component or resource, registered on the server and the clients.
+pub trait Replicable {
+    /// Identifies the type in snapshots, the same on the server and the clients.
+    const NAME: &'static str;
+}
+
+/// Replicated components of each entity, and replicated resources, by registered name.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WorldState {
+    pub entities: BTreeMap<u64, BTreeMap<String, Value>>,
+    pub resources: BTreeMap<String, Value>,
+}
+
+/// Changes from the `base` state to the one of `tick`, a full state if there is no `base`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Snapshot {
+    pub tick: u64,
+    pub base: Option<u64>,
+    pub entities: Vec<EntityDelta>,
+    pub despawned: Vec<u64>,
+    pub resources: BTreeMap<String, Value>,
+    pub removed_resources: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct EntityDelta {
+    pub entity: u64,
+    pub changed: BTreeMap<String, Value>,
+    pub removed: Vec<String>,
+}
+
+impl WorldState {
+    pub fn delta(&self, tick: u64, base: Option<(u64, &WorldState)>) -> Snapshot {
+        let empty = WorldState::default();
+        let base_state = base.map_or(&empty, |(_, state)| state);
+        let mut snapshot = Snapshot {
+            tick,
+            base: base.map(|(base_tick, _)| base_tick),
+            ..Default::default()
+        };
+        for (entity, components) in &self.entities {
+            let base_components = base_state.entities.get(entity);
+            let delta = EntityDelta {
+                entity: *entity,
+                changed: changed(components, base_components),
+                removed: removed(components, base_components),
+            };
+            // A new entity is sent even without components, so it's spawned.
+            if base_components.is_none() || !delta.changed.is_empty() || !delta.removed.is_empty() {
+                snapshot.entities.push(delta);
+            }
+        }
+        snapshot.despawned = base_state
+            .entities
+            .keys()
+            .filter(|entity| !self.entities.contains_key(entity))
+            .copied()
+            .collect();
+        snapshot.resources = changed(&self.resources, Some(&base_state.resources));
+        snapshot.removed_resources = removed(&self.resources, Some(&base_state.resources));
+        snapshot
+    }
+
+    /// The state of `snapshot.tick`, `self` being the state of `snapshot.base`.
+    pub fn apply(&self, snapshot: &Snapshot) -> WorldState {
+        let mut state = self.clone();
+        for entity in &snapshot.despawned {
+            state.entities.remove(entity);
+        }
+        for delta in &snapshot.entities {
+            let components = state.entities.entry(delta.entity).or_default();
+            for name in &delta.removed {
+                components.remove(name);
+            }
+            components.extend(delta.changed.clone());
+        }
+        for name in &snapshot.removed_resources {
+            state.resources.remove(name);
+        }
+        state.resources.extend(snapshot.resources.clone());
+        state
+    }
+}
+
+fn changed(
+    current: &BTreeMap<String, Value>,
+    base: Option<&BTreeMap<String, Value>>,
+) -> BTreeMap<String, Value> {
+    current
+        .iter()
+        .filter(|(name, value)| base.and_then(|base| base.get(*name)) != Some(*value))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect()
+}
+
+fn removed(
+    current: &BTreeMap<String, Value>,
+    base: Option<&BTreeMap<String, Value>>,
+) -> Vec<String> {
+    base.map_or(vec![], |base| {
+        base.keys()
+            .filter(|name| !current.contains_key(*name))
+            .cloned()
+            .collect()
+    })
+}
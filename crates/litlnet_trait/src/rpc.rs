@@ -0,0 +1,36 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// A request the client sends, answered by a handler registered on the server.
+pub trait Rpc: Serialize + DeserializeOwned + Send + Sync + 'static {
+    type Response: Serialize + DeserializeOwned + Send + Sync + 'static;
+    /// Identifies the request on the wire, the same on the client and the server.
+    const METHOD: &'static str;
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum RpcError {
+    /// The server has no handler for the method.
+    UnknownMethod,
+    /// The request or the response could not be decoded.
+    Invalid(String),
+    /// The handler of the method could not run on the server.
+    Internal(String),
+    /// No response before the timeout of the client.
+    Timeout,
+    /// The connection was lost with the request in flight.
+    Disconnected,
+}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RpcError::UnknownMethod => write!(f, "unknown method"),
+            RpcError::Invalid(e) => write!(f, "invalid payload: {}", e),
+            RpcError::Internal(e) => write!(f, "server error: {}", e),
+            RpcError::Timeout => write!(f, "timed out"),
+            RpcError::Disconnected => write!(f, "disconnected"),
+        }
+    }
+}
+
+impl std::error::Error for RpcError {}
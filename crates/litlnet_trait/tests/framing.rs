@@ -0,0 +1,150 @@
+use litlnet_trait::{
+    framing::{self, Compression, BATCH_MARKER, COMPRESSED_MARKER},
+    json_depth, json_value_len, DecodeLimits,
+};
+
+const ALWAYS: Compression = Compression {
+    min_size: 0,
+    level: 6,
+};
+
+fn decode(frame: &[u8], limits: &DecodeLimits) -> std::io::Result<(Vec<String>, u64)> {
+    framing::decode_frame::<String>(frame, limits).map(|decoded| (decoded.messages, decoded.errors))
+}
+
+#[test]
+fn value_len_delimits_the_first_value() {
+    assert_eq!(json_value_len(br#"{"a":[1,{"b":2}]}{"c":3}"#), Some(17));
+    assert_eq!(json_value_len(b"[[],[[]]]1"), Some(9));
+    assert_eq!(json_value_len(b"12.5e+3]"), Some(7));
+    assert_eq!(json_value_len(b"true,"), Some(4));
+    assert_eq!(json_value_len(br#""ab"cd"#), Some(4));
+    // An unexpected byte is a value of its own, so a stream never stalls on it.
+    assert_eq!(json_value_len(b"]]"), Some(1));
+}
+
+#[test]
+fn value_len_waits_for_truncated_values() {
+    assert_eq!(json_value_len(b""), None);
+    assert_eq!(json_value_len(br#"{"a":[1,2"#), None);
+    assert_eq!(json_value_len(br#""abc"#), None);
+    // A number may go on in the next bytes.
+    assert_eq!(json_value_len(b"123"), None);
+}
+
+#[test]
+fn value_len_skips_brackets_and_quotes_in_strings() {
+    assert_eq!(json_value_len(br#"{"a":"}]\"{"}x"#), Some(13));
+    assert_eq!(json_value_len(br#""\\"x"#), Some(4));
+    assert_eq!(json_value_len(br#""\"""#), Some(4));
+    assert_eq!(json_value_len(br#"["\\\"]"]"#), Some(9));
+    // The closing quote is escaped, the string isn't over.
+    assert_eq!(json_value_len(br#"["\"]"#), None);
+}
+
+#[test]
+fn depth_ignores_strings() {
+    assert_eq!(json_depth(br#"[{"a":[]}]"#), 3);
+    assert_eq!(json_depth(br#"["[[[\"[[["]"#), 1);
+    // Unbalanced input doesn't underflow.
+    assert_eq!(json_depth(b"]]]]["), 1);
+}
+
+#[test]
+fn single_message_round_trips() {
+    let frame = framing::encode(&"hi \"you\" [".to_string()).unwrap();
+    let (messages, errors) = decode(&frame, &DecodeLimits::default()).unwrap();
+    assert_eq!(messages, ["hi \"you\" ["]);
+    assert_eq!(errors, 0);
+    assert_eq!(framing::frame_len(&frame), Some(frame.len()));
+}
+
+#[test]
+fn batch_round_trips_and_skips_bad_messages() {
+    let mut frame = framing::encode_batch(&["a", "b\\", "c"]).unwrap();
+    assert_eq!(frame[0], BATCH_MARKER);
+    assert_eq!(framing::frame_len(&frame), Some(frame.len()));
+    let (messages, errors) = decode(&frame, &DecodeLimits::default()).unwrap();
+    assert_eq!(messages, ["a", "b\\", "c"]);
+    assert_eq!(errors, 0);
+    // Same length, not a string anymore.
+    let last = frame.len() - 3;
+    frame[last..].copy_from_slice(b"123");
+    let (messages, errors) = decode(&frame, &DecodeLimits::default()).unwrap();
+    assert_eq!(messages, ["a", "b\\"]);
+    assert_eq!(errors, 1);
+}
+
+#[test]
+fn truncated_batches_are_rejected() {
+    let frame = framing::encode_batch(&["a", "b"]).unwrap();
+    for len in 1..frame.len() {
+        assert!(decode(&frame[..len], &DecodeLimits::default()).is_err());
+    }
+    // Header agrees with the frame, a message's length doesn't.
+    let mut frame = frame;
+    frame[5..9].copy_from_slice(&100u32.to_be_bytes());
+    assert!(decode(&frame, &DecodeLimits::default()).is_err());
+}
+
+#[test]
+fn oversized_frames_are_rejected() {
+    let limits = DecodeLimits {
+        max_message_size: 16,
+        max_depth: 32,
+    };
+    let long = "x".repeat(32);
+    assert!(decode(&framing::encode(&long).unwrap(), &limits).is_err());
+    assert!(decode(
+        &framing::encode_batch(&[long.as_str(), "a"]).unwrap(),
+        &limits
+    )
+    .is_err());
+    // Each message fits, the frame doesn't.
+    assert!(decode(&framing::encode_batch(&["abc"; 4]).unwrap(), &limits).is_err());
+}
+
+#[test]
+fn deeply_nested_messages_are_rejected() {
+    let limits = DecodeLimits {
+        max_message_size: 1024,
+        max_depth: 4,
+    };
+    let nested = format!("{}{}", "[".repeat(5), "]".repeat(5));
+    assert!(decode(nested.as_bytes(), &limits).is_err());
+    // Brackets in strings don't count.
+    let (_, errors) = decode(br#""[[[[[[[[""#, &limits).unwrap();
+    assert_eq!(errors, 0);
+}
+
+#[test]
+fn compressed_frames_round_trip() {
+    let frame = framing::encode_batch(&["a".repeat(100), "b".repeat(100)]).unwrap();
+    let compressed = framing::compress(frame, &ALWAYS).unwrap();
+    assert_eq!(compressed[0], COMPRESSED_MARKER);
+    assert_eq!(framing::frame_len(&compressed), Some(compressed.len()));
+    let (messages, _) = decode(&compressed, &DecodeLimits::default()).unwrap();
+    assert_eq!(messages, ["a".repeat(100), "b".repeat(100)]);
+    // Small frames aren't worth it.
+    let small = framing::encode(&"a").unwrap();
+    assert_eq!(
+        framing::compress(small.clone(), &Compression::default()).unwrap(),
+        small
+    );
+}
+
+#[test]
+fn decompression_bombs_and_nested_compression_are_rejected() {
+    let limits = DecodeLimits {
+        max_message_size: 1024,
+        max_depth: 32,
+    };
+    let bomb = framing::encode(&"a".repeat(100_000)).unwrap();
+    let bomb = framing::compress(bomb, &ALWAYS).unwrap();
+    assert!(bomb.len() < limits.max_message_size);
+    assert!(decode(&bomb, &limits).is_err());
+
+    let frame = framing::compress(framing::encode(&"a").unwrap(), &ALWAYS).unwrap();
+    let nested = framing::compress(frame, &ALWAYS).unwrap();
+    assert!(decode(&nested, &limits).is_err());
+}
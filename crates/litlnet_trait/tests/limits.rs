@@ -0,0 +1,72 @@
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    time::{Duration, Instant},
+};
+
+use litlnet_trait::{ConnectionLimiter, ConnectionLimits, Rejection, TokenBucket};
+
+const ALICE: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+const BOB: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+#[test]
+fn bucket_allows_a_burst_then_refills() {
+    let start = Instant::now();
+    let mut bucket = TokenBucket::new(2f32, 3f32);
+    assert!(bucket.is_full(start));
+    for _ in 0..3 {
+        assert!(bucket.try_take(start));
+    }
+    assert!(!bucket.try_take(start));
+    assert!(!bucket.has_token(start + Duration::from_millis(400)));
+    assert!(bucket.try_take(start + Duration::from_millis(500)));
+    assert!(!bucket.try_take(start + Duration::from_millis(500)));
+    // Never over its capacity.
+    assert!(bucket.is_full(start + Duration::from_secs(60)));
+    for _ in 0..3 {
+        assert!(bucket.try_take(start + Duration::from_secs(60)));
+    }
+    assert!(!bucket.has_token(start + Duration::from_secs(60)));
+}
+
+#[test]
+fn limiter_rejects_over_the_limits_until_released() {
+    let mut limiter = ConnectionLimiter::new(ConnectionLimits {
+        max_clients: Some(3),
+        max_connections_per_ip: Some(2),
+        max_accepts_per_second: None,
+    });
+    assert_eq!(limiter.admit(ALICE, 0), Ok(()));
+    assert_eq!(limiter.admit(ALICE, 1), Ok(()));
+    assert_eq!(
+        limiter.admit(ALICE, 2),
+        Err(Rejection::TooManyConnectionsFromIp)
+    );
+    assert_eq!(limiter.admit(BOB, 2), Ok(()));
+    assert_eq!(limiter.admit(BOB, 3), Err(Rejection::ServerFull));
+    limiter.release(ALICE);
+    assert_eq!(limiter.admit(ALICE, 2), Ok(()));
+}
+
+#[test]
+fn limiter_throttles_accepts() {
+    let mut limiter = ConnectionLimiter::new(ConnectionLimits {
+        max_accepts_per_second: Some(2f32),
+        ..Default::default()
+    });
+    let start = Instant::now();
+    for clients in 0..2 {
+        assert!(limiter.can_accept(start));
+        assert_eq!(limiter.admit(ALICE, clients), Ok(()));
+    }
+    assert!(!limiter.can_accept(start));
+    assert!(limiter.can_accept(start + Duration::from_secs(1)));
+}
+
+#[test]
+fn unlimited_by_default() {
+    let mut limiter = ConnectionLimiter::new(ConnectionLimits::default());
+    for clients in 0..1000 {
+        assert!(limiter.can_accept(Instant::now()));
+        assert_eq!(limiter.admit(ALICE, clients), Ok(()));
+    }
+}
@@ -0,0 +1,235 @@
+//! WebSocket permessage-deflate (RFC 7692), which tungstenite doesn't implement.
+//!
+//! Every message is deflated on its own (no context takeover both ways), so no state is kept
+//! between messages and any message can be left uncompressed.
+use std::{
+    collections::VecDeque,
+    io::{Read, Write},
+};
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder};
+use litlnet_trait::DecodeLimits;
+use tungstenite::http::HeaderMap;
+
+pub(crate) const EXTENSIONS_HEADER: &str = "Sec-WebSocket-Extensions";
+const EXTENSION: &str = "permessage-deflate";
+/// Sent by the client, and by the server to agree to it.
+pub(crate) const NEGOTIATED: &str =
+    "permessage-deflate; server_no_context_takeover; client_no_context_takeover";
+/// Ends every sync flushed message, it's not sent.
+const TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+/// An empty final block, without it flate2 takes the message for a truncated stream.
+const FINAL_BLOCK: [u8; 2] = [0x03, 0x00];
+const RSV1: u8 = 0x40;
+const HANDSHAKE_END: &[u8] = b"\r\n\r\n";
+
+fn invalid_data(reason: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, reason.to_string())
+}
+
+/// Extension names with their parameters, from all the `Sec-WebSocket-Extensions` headers.
+fn extensions(headers: &HeaderMap) -> impl Iterator<Item = (&str, Vec<(&str, Option<&str>)>)> {
+    headers
+        .get_all(EXTENSIONS_HEADER)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|extension| {
+            let mut parts = extension.split(';').map(str::trim);
+            let name = parts.next().unwrap_or_default();
+            let params = parts
+                .map(|param| match param.split_once('=') {
+                    Some((key, value)) => (key.trim(), Some(value.trim().trim_matches('"'))),
+                    None => (param, None),
+                })
+                .collect();
+            (name, params)
+        })
+}
+
+/// Whether the client offered permessage-deflate with parameters the server can agree to.
+pub(crate) fn accepts_offer(headers: &HeaderMap) -> bool {
+    extensions(headers).any(|(name, params)| {
+        name == EXTENSION
+            && params.iter().all(|(key, value)| match *key {
+                "server_no_context_takeover" | "client_no_context_takeover" => value.is_none(),
+                // Only bounds the window of the client, any window is inflated.
+                "client_max_window_bits" => true,
+                // flate2 always deflates with the largest window.
+                "server_max_window_bits" => *value == Some("15"),
+                _ => false,
+            })
+    })
+}
+
+/// Whether the server agreed to [`NEGOTIATED`], agreeing to anything else is an error.
+pub(crate) fn is_agreed(headers: &HeaderMap) -> std::io::Result<bool> {
+    let mut agreed = false;
+    for (name, params) in extensions(headers) {
+        if name != EXTENSION {
+            return Err(invalid_data(
+                "server agreed to an extension which wasn't offered",
+            ));
+        }
+        let mut server_no_context_takeover = false;
+        for (key, _) in params {
+            match key {
+                "server_no_context_takeover" => server_no_context_takeover = true,
+                // Any window is inflated.
+                "client_no_context_takeover" | "server_max_window_bits" => {}
+                _ => return Err(invalid_data("unexpected permessage-deflate parameter")),
+            }
+        }
+        // Messages are inflated on their own.
+        if !server_no_context_takeover {
+            return Err(invalid_data("server keeps its permessage-deflate context"));
+        }
+        agreed = true;
+    }
+    Ok(agreed)
+}
+
+/// A message payload, compressed as is sent with RSV1 set.
+pub(crate) fn deflate(payload: &[u8], level: u32) -> std::io::Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(vec![], flate2::Compression::new(level));
+    encoder.write_all(payload)?;
+    // A sync flush, it ends with `TAIL` instead of a final block.
+    encoder.flush()?;
+    let mut buf = std::mem::take(encoder.get_mut());
+    if buf.ends_with(&TAIL) {
+        buf.truncate(buf.len() - TAIL.len());
+    }
+    Ok(buf)
+}
+
+/// Reverts [`deflate`], inflating more than `limits` is an error.
+pub(crate) fn inflate(payload: &[u8], limits: &DecodeLimits) -> std::io::Result<Vec<u8>> {
+    // Reading one byte over the limit is enough to tell a decompression bomb.
+    let mut res = vec![];
+    DeflateDecoder::new(payload.chain(&TAIL[..]).chain(&FINAL_BLOCK[..]))
+        .take(limits.max_message_size as u64 + 1)
+        .read_to_end(&mut res)?;
+    limits.check_size(res.len())?;
+    Ok(res)
+}
+
+#[derive(Debug)]
+enum ReadState {
+    /// How much of [`HANDSHAKE_END`] was read, frames start after it.
+    Handshake(usize),
+    /// The frame header read so far.
+    Header(Vec<u8>),
+    Payload(u64),
+}
+
+/// Clears RSV1 from the data frames read through it, tungstenite fails the connection on it,
+/// and remembers which messages had it.
+#[derive(Debug)]
+pub(crate) struct Rsv1Stream<S> {
+    stream: S,
+    state: ReadState,
+    /// Whether each message not yet read by tungstenite is compressed.
+    compressed: VecDeque<bool>,
+}
+
+impl<S> Rsv1Stream<S> {
+    /// Wraps `stream` before the handshake.
+    pub(crate) fn new(stream: S) -> Self {
+        Self {
+            stream,
+            state: ReadState::Handshake(0),
+            compressed: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Whether the next text or binary message tungstenite returns was compressed.
+    pub(crate) fn next_is_compressed(&mut self) -> bool {
+        self.compressed.pop_front().unwrap_or_default()
+    }
+
+    fn track(&mut self, bytes: &mut [u8]) {
+        let mut i = 0;
+        while i < bytes.len() {
+            match &mut self.state {
+                ReadState::Handshake(matched) => {
+                    *matched = match bytes[i] {
+                        b if b == HANDSHAKE_END[*matched] => *matched + 1,
+                        b'\r' => 1,
+                        _ => 0,
+                    };
+                    if *matched == HANDSHAKE_END.len() {
+                        self.state = ReadState::Header(vec![]);
+                    }
+                    i += 1;
+                }
+                ReadState::Payload(left) => {
+                    let skipped = (*left).min((bytes.len() - i) as u64);
+                    *left -= skipped;
+                    i += skipped as usize;
+                    if *left == 0 {
+                        self.state = ReadState::Header(vec![]);
+                    }
+                }
+                ReadState::Header(header) => {
+                    if header.is_empty() {
+                        // Only the first frame of a text or binary message has it.
+                        let is_message_start = matches!(bytes[i] & 0x0f, 0x1 | 0x2);
+                        if is_message_start {
+                            self.compressed.push_back(bytes[i] & RSV1 != 0);
+                            bytes[i] &= !RSV1;
+                        }
+                    }
+                    header.push(bytes[i]);
+                    i += 1;
+                    if let Some(len) = payload_len(header) {
+                        self.state = match len {
+                            0 => ReadState::Header(vec![]),
+                            len => ReadState::Payload(len),
+                        };
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Payload length of a frame, once its whole `header` is known.
+fn payload_len(header: &[u8]) -> Option<u64> {
+    let len = *header.get(1)? & 0x7f;
+    let is_masked = header[1] & 0x80 != 0;
+    let extended_len = match len {
+        126 => 2,
+        127 => 8,
+        _ => 0,
+    };
+    if header.len() < 2 + extended_len + if is_masked { 4 } else { 0 } {
+        return None;
+    }
+    Some(match len {
+        126 => u16::from_be_bytes([header[2], header[3]]) as u64,
+        127 => u64::from_be_bytes(header[2..10].try_into().ok()?),
+        len => len as u64,
+    })
+}
+
+impl<S: Read> Read for Rsv1Stream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.stream.read(buf)?;
+        self.track(&mut buf[..len]);
+        Ok(len)
+    }
+}
+
+impl<S: Write> Write for Rsv1Stream<S> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.stream.flush()
+    }
+}
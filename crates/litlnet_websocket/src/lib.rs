@@ -1,94 +1,318 @@
+mod deflate;
+
+use deflate::Rsv1Stream;
 pub use litlnet_trait::Communication;
+use litlnet_trait::{framing, Compression, DecodeLimits, Traffic};
 use serde::{de::DeserializeOwned, Serialize};
-use serde_json::Deserializer;
-use std::net::TcpStream;
-use tungstenite::{stream::MaybeTlsStream, Message, WebSocket};
+use std::{
+    io::Write,
+    net::{Shutdown, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use tungstenite::{
+    client::IntoClientRequest,
+    handshake::{
+        server::{Callback, ErrorResponse, Request, Response, ServerHandshake},
+        HandshakeError, MidHandshake,
+    },
+    http::HeaderValue,
+    protocol::{
+        frame::{
+            coding::{Data, OpCode},
+            Frame,
+        },
+        WebSocketConfig,
+    },
+    Message, WebSocket,
+};
 
-trait LitlWebsocket {
-    fn write_all(&mut self, buf: Vec<u8>) -> Result<(), tungstenite::Error>;
-    fn read(&mut self) -> Result<Message, tungstenite::Error>;
+pub struct WebsocketClient {
+    websocket: WebSocket<Rsv1Stream<TcpStream>>,
+    limits: DecodeLimits,
+    traffic: Traffic,
+    /// Only set once the peer agreed to permessage-deflate.
+    compression: Option<Compression>,
 }
 
-impl LitlWebsocket for WebSocket<MaybeTlsStream<TcpStream>> {
-    fn write_all(&mut self, buf: Vec<u8>) -> Result<(), tungstenite::Error> {
-        self.send(Message::Binary(buf))
-    }
+/// A connection whose handshake isn't done yet, see [`WebsocketClient::accept_with_compression`].
+pub struct PendingClient {
+    handshake: MidHandshake<ServerHandshake<Rsv1Stream<TcpStream>, Negotiation>>,
+    negotiated: Arc<AtomicBool>,
+    limits: DecodeLimits,
+    compression: Option<Compression>,
+}
 
-    fn read(&mut self) -> Result<Message, tungstenite::Error> {
-        self.read()
+impl PendingClient {
+    /// Goes on with the handshake, as far as the peer sent it.
+    pub fn resume(self) -> Result<Handshake, std::io::Error> {
+        finish_handshake(
+            self.handshake.handshake(),
+            self.negotiated,
+            self.limits,
+            self.compression,
+        )
     }
 }
-impl LitlWebsocket for WebSocket<TcpStream> {
-    fn write_all(&mut self, buf: Vec<u8>) -> Result<(), tungstenite::Error> {
-        self.send(Message::Binary(buf))
+
+pub enum Handshake {
+    Done(WebsocketClient),
+    Pending(PendingClient),
+}
+
+/// Agrees to permessage-deflate if the client offers it and `compression` is set.
+struct Negotiation {
+    compression: Option<Compression>,
+    /// Read once the handshake is done.
+    negotiated: Arc<AtomicBool>,
+}
+
+impl Callback for Negotiation {
+    fn on_request(
+        self,
+        request: &Request,
+        mut response: Response,
+    ) -> Result<Response, ErrorResponse> {
+        if self.compression.is_some() && deflate::accepts_offer(request.headers()) {
+            response.headers_mut().insert(
+                deflate::EXTENSIONS_HEADER,
+                HeaderValue::from_static(deflate::NEGOTIATED),
+            );
+            self.negotiated.store(true, Ordering::Relaxed);
+        }
+        Ok(response)
     }
+}
+
+type ServerHandshakeResult = Result<
+    WebSocket<Rsv1Stream<TcpStream>>,
+    HandshakeError<ServerHandshake<Rsv1Stream<TcpStream>, Negotiation>>,
+>;
 
-    fn read(&mut self) -> Result<Message, tungstenite::Error> {
-        self.read()
+fn finish_handshake(
+    result: ServerHandshakeResult,
+    negotiated: Arc<AtomicBool>,
+    limits: DecodeLimits,
+    compression: Option<Compression>,
+) -> Result<Handshake, std::io::Error> {
+    match result {
+        Ok(websocket) => Ok(Handshake::Done(WebsocketClient {
+            websocket,
+            limits,
+            traffic: Traffic::default(),
+            compression: compression.filter(|_| negotiated.load(Ordering::Relaxed)),
+        })),
+        Err(HandshakeError::Interrupted(handshake)) => Ok(Handshake::Pending(PendingClient {
+            handshake,
+            negotiated,
+            limits,
+            compression,
+        })),
+        Err(HandshakeError::Failure(e)) => Err(std::io::Error::new(
+            std::io::ErrorKind::ConnectionAborted,
+            e.to_string(),
+        )),
     }
 }
 
-pub struct WebsocketClient {
-    websocket: Box<dyn LitlWebsocket + Send + Sync + 'static>,
+fn websocket_config(limits: &DecodeLimits) -> WebSocketConfig {
+    WebSocketConfig {
+        max_message_size: Some(limits.max_message_size),
+        max_frame_size: Some(limits.max_message_size),
+        ..Default::default()
+    }
 }
 
 impl WebsocketClient {
     pub fn connect(remote_addr: &str) -> Result<WebsocketClient, std::io::Error> {
-        let (mut websocket, _) = match tungstenite::connect(url::Url::parse(remote_addr).unwrap()) {
+        Self::connect_with_limits(remote_addr, DecodeLimits::default())
+    }
+    pub fn connect_with_limits(
+        remote_addr: &str,
+        limits: DecodeLimits,
+    ) -> Result<WebsocketClient, std::io::Error> {
+        Self::connect_with_compression(remote_addr, limits, None)
+    }
+    /// Offers permessage-deflate, messages are sent compressed only if the server agrees to it.
+    pub fn connect_with_compression(
+        remote_addr: &str,
+        limits: DecodeLimits,
+        compression: Option<Compression>,
+    ) -> Result<WebsocketClient, std::io::Error> {
+        let url = url::Url::parse(remote_addr)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        let stream = TcpStream::connect(&*url.socket_addrs(|| Some(80))?)?;
+        let mut request = url
+            .into_client_request()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        if compression.is_some() {
+            request.headers_mut().insert(
+                deflate::EXTENSIONS_HEADER,
+                HeaderValue::from_static(deflate::NEGOTIATED),
+            );
+        }
+        let (websocket, response) = match tungstenite::client::client_with_config(
+            request,
+            Rsv1Stream::new(stream),
+            Some(websocket_config(&limits)),
+        ) {
             Ok(it) => it,
             Err(err) => {
                 return Err(std::io::Error::new(
                     std::io::ErrorKind::ConnectionAborted,
-                    dbg!(err),
+                    dbg!(err).to_string(),
                 ))
             }
         };
-        if let MaybeTlsStream::Plain(s) = websocket.get_mut() {
-            s.set_nonblocking(true)?
-        }
+        let is_agreed = deflate::is_agreed(response.headers())?;
+        websocket.get_ref().get_ref().set_nonblocking(true)?;
 
         Ok(WebsocketClient {
-            websocket: Box::new(websocket),
+            websocket,
+            limits,
+            traffic: Traffic::default(),
+            compression: compression.filter(|_| is_agreed),
         })
     }
     pub fn from_stream(stream: std::net::TcpStream) -> Result<WebsocketClient, std::io::Error> {
-        match tungstenite::accept(stream) {
-            Ok(mut websocket) => {
-                websocket.get_mut().set_nonblocking(true)?;
-                Ok(Self {
-                    websocket: Box::new(websocket),
-                })
+        Self::from_stream_with_limits(stream, DecodeLimits::default())
+    }
+    pub fn from_stream_with_limits(
+        stream: std::net::TcpStream,
+        limits: DecodeLimits,
+    ) -> Result<WebsocketClient, std::io::Error> {
+        Self::from_stream_with_compression(stream, limits, None)
+    }
+    /// Agrees to permessage-deflate if the client offers it and `compression` is set.
+    pub fn from_stream_with_compression(
+        stream: std::net::TcpStream,
+        limits: DecodeLimits,
+        compression: Option<Compression>,
+    ) -> Result<WebsocketClient, std::io::Error> {
+        let negotiated = Arc::new(AtomicBool::new(false));
+        let callback = Negotiation {
+            compression,
+            negotiated: negotiated.clone(),
+        };
+        let result = tungstenite::accept_hdr_with_config(
+            Rsv1Stream::new(stream),
+            callback,
+            Some(websocket_config(&limits)),
+        );
+        match finish_handshake(result, negotiated, limits, compression)? {
+            Handshake::Done(client) => {
+                client.websocket.get_ref().get_ref().set_nonblocking(true)?;
+                Ok(client)
             }
-            Err(e) => Err(std::io::Error::new(
-                std::io::ErrorKind::ConnectionAborted,
-                dbg!(e),
-            )),
+            // Only on a non-blocking stream.
+            Handshake::Pending(_) => Err(std::io::ErrorKind::WouldBlock.into()),
         }
     }
+
+    /// Like [`Self::from_stream_with_compression`], without waiting for a peer slow to send its
+    /// request: the handshake goes on with [`PendingClient::resume`].
+    pub fn accept_with_compression(
+        stream: std::net::TcpStream,
+        limits: DecodeLimits,
+        compression: Option<Compression>,
+    ) -> Result<Handshake, std::io::Error> {
+        stream.set_nonblocking(true)?;
+        let negotiated = Arc::new(AtomicBool::new(false));
+        let callback = Negotiation {
+            compression,
+            negotiated: negotiated.clone(),
+        };
+        let result = tungstenite::accept_hdr_with_config(
+            Rsv1Stream::new(stream),
+            callback,
+            Some(websocket_config(&limits)),
+        );
+        finish_handshake(result, negotiated, limits, compression)
+    }
+
+    fn send_frame(&mut self, buf: Vec<u8>, messages: usize) -> std::io::Result<()> {
+        let (message, len) = match self.compression {
+            Some(compression) if buf.len() >= compression.min_size => {
+                let payload = deflate::deflate(&buf, compression.level)?;
+                let len = payload.len() as u64;
+                let mut frame = Frame::message(payload, OpCode::Data(Data::Binary), true);
+                frame.header_mut().rsv1 = true;
+                (Message::Frame(frame), len)
+            }
+            _ => {
+                let len = buf.len() as u64;
+                (Message::Binary(buf), len)
+            }
+        };
+        match self.websocket.send(message) {
+            Ok(()) => {
+                self.traffic.bytes_sent += len;
+                self.traffic.frames_sent += 1;
+                self.traffic.messages_sent += messages as u64;
+            }
+            Err(tungstenite::Error::Io(e)) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+            Err(e) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::ConnectionAborted,
+                    dbg!(e),
+                ))
+            }
+        };
+        Ok(())
+    }
+}
+
+/// Answers `stream` with an HTTP 503, with `reason` as body, and closes it.
+///
+/// The handshake isn't read: a client which never sends it can't stall the server.
+pub fn reject_stream(mut stream: TcpStream, reason: &str) {
+    if stream.set_nonblocking(true).is_err() {
+        return;
+    }
+    let response = format!(
+        "HTTP/1.1 503 Service Unavailable\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        reason.len(),
+        reason
+    );
+    // Fits in the socket buffer of a new connection, or the client only sees it closed.
+    let _ = stream.write_all(response.as_bytes());
+    let _ = stream.shutdown(Shutdown::Both);
 }
 
 impl Communication for WebsocketClient {
     fn receive<T: DeserializeOwned>(&mut self) -> Result<Option<Vec<T>>, std::io::Error> {
         match self.websocket.read() {
             Ok(Message::Binary(msg)) => {
-                let de = Deserializer::from_slice(msg.as_slice());
-
-                // FIXME: a stream deserializer is useful for TCP as we can get multiple messages in the same packet, but websocket handles that so we can just deserialize 1 message.
-                let stream = de.into_iter::<T>();
-                let mut res = vec![];
-                for v in stream {
-                    match v {
-                        Err(e) => {
-                            dbg!(e);
-                        }
-                        Ok(v) => {
-                            res.push(v);
-                        }
+                self.traffic.bytes_received += msg.len() as u64;
+                self.traffic.frames_received += 1;
+                let is_compressed = self.websocket.get_mut().next_is_compressed();
+                let msg = match (is_compressed, self.compression) {
+                    (false, _) => Ok(msg),
+                    (true, Some(_)) => deflate::inflate(&msg, &self.limits),
+                    (true, None) => Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "compressed message without permessage-deflate",
+                    )),
+                };
+                let decoded = msg.and_then(|msg| framing::decode_frame::<T>(&msg, &self.limits));
+                match decoded {
+                    Ok(decoded) => {
+                        self.traffic.decode_errors += decoded.errors;
+                        self.traffic.messages_received += decoded.messages.len() as u64;
+                        Ok(Some(decoded.messages))
+                    }
+                    Err(e) => {
+                        self.traffic.decode_errors += 1;
+                        Err(e)
                     }
                 }
-                Ok(Some(res))
             }
             Ok(data) => {
+                if data.is_text() {
+                    self.websocket.get_mut().next_is_compressed();
+                }
                 dbg!(data);
                 Ok(None)
             }
@@ -103,18 +327,22 @@ impl Communication for WebsocketClient {
     }
 
     fn send<T: Serialize>(&mut self, message: &T) -> std::io::Result<()> {
-        let mut buf = Vec::new();
-        message.serialize(&mut serde_json::Serializer::new(&mut buf))?;
-        match self.websocket.write_all(buf) {
-            Ok(it) => it,
-            Err(tungstenite::Error::Io(e)) if e.kind() == std::io::ErrorKind::WouldBlock => {}
-            Err(e) => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::ConnectionAborted,
-                    dbg!(e),
-                ))
-            }
-        };
-        Ok(())
+        let buf = framing::encode(message)?;
+        self.send_frame(buf, 1)
+    }
+
+    fn send_batch<T: Serialize>(&mut self, messages: &[T]) -> std::io::Result<()> {
+        match messages {
+            [] => return Ok(()),
+            // The batch header would only be overhead.
+            [message] => return self.send(message),
+            _ => {}
+        }
+        let buf = framing::encode_batch(messages)?;
+        self.send_frame(buf, messages.len())
+    }
+
+    fn traffic(&self) -> Traffic {
+        self.traffic
     }
 }
@@ -0,0 +1,199 @@
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+use flate2::{write::DeflateEncoder, Decompress, FlushDecompress};
+use litlnet_trait::{Compression, DecodeLimits};
+use litlnet_websocket::{Communication, WebsocketClient};
+
+const ALWAYS: Compression = Compression {
+    min_size: 0,
+    level: 6,
+};
+
+fn message() -> String {
+    "mole ".repeat(200)
+}
+
+fn receive(com: &mut WebsocketClient) -> String {
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(5) {
+        if let Some(message) = com
+            .receive::<String>()
+            .unwrap()
+            .and_then(|m| m.into_iter().next())
+        {
+            return message;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    panic!("nothing received");
+}
+
+/// A server on another thread, answering with what it receives.
+fn echo_server(compression: Option<Compression>) -> (String, thread::JoinHandle<WebsocketClient>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut com = WebsocketClient::from_stream_with_compression(
+            stream,
+            DecodeLimits::default(),
+            compression,
+        )
+        .unwrap();
+        let message = receive(&mut com);
+        com.send(&message).unwrap();
+        com
+    });
+    (url, server)
+}
+
+#[test]
+fn negotiated_messages_round_trip_compressed() {
+    let (url, server) = echo_server(Some(ALWAYS));
+    let mut client =
+        WebsocketClient::connect_with_compression(&url, DecodeLimits::default(), Some(ALWAYS))
+            .unwrap();
+    client.send(&message()).unwrap();
+    assert_eq!(receive(&mut client), message());
+    let server = server.join().unwrap();
+    for traffic in [client.traffic(), server.traffic()] {
+        assert!(traffic.bytes_sent < message().len() as u64 / 10);
+    }
+}
+
+#[test]
+fn not_compressed_unless_both_sides_want_it() {
+    for (client_compression, server_compression) in [(None, Some(ALWAYS)), (Some(ALWAYS), None)] {
+        let (url, server) = echo_server(server_compression);
+        let mut client = WebsocketClient::connect_with_compression(
+            &url,
+            DecodeLimits::default(),
+            client_compression,
+        )
+        .unwrap();
+        client.send(&message()).unwrap();
+        assert_eq!(receive(&mut client), message());
+        let server = server.join().unwrap();
+        for traffic in [client.traffic(), server.traffic()] {
+            assert!(traffic.bytes_sent > message().len() as u64);
+        }
+    }
+}
+
+/// Reads an unmasked frame from the server, with whether RSV1 is set.
+fn read_frame(stream: &mut TcpStream) -> (bool, Vec<u8>) {
+    let mut header = [0; 2];
+    stream.read_exact(&mut header).unwrap();
+    let len = match header[1] & 0x7f {
+        126 => {
+            let mut len = [0; 2];
+            stream.read_exact(&mut len).unwrap();
+            u16::from_be_bytes(len) as usize
+        }
+        127 => panic!("unexpected frame size"),
+        len => len as usize,
+    };
+    let mut payload = vec![0; len];
+    stream.read_exact(&mut payload).unwrap();
+    (header[0] & 0x40 != 0, payload)
+}
+
+#[test]
+fn speaks_rfc_7692_with_other_peers() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut com = WebsocketClient::from_stream_with_compression(
+            stream,
+            DecodeLimits::default(),
+            Some(ALWAYS),
+        )
+        .unwrap();
+        com.send(&message()).unwrap();
+        receive(&mut com)
+    });
+    // As browsers offer it.
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "GET / HTTP/1.1\r\nHost: {}\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n\
+         Sec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+         Sec-WebSocket-Extensions: permessage-deflate; client_max_window_bits\r\n\r\n",
+        addr
+    )
+    .unwrap();
+    let mut response = vec![];
+    while !response.ends_with(b"\r\n\r\n") {
+        let mut byte = [0];
+        stream.read_exact(&mut byte).unwrap();
+        response.push(byte[0]);
+    }
+    let response = String::from_utf8(response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 101"), "{}", response);
+    assert!(
+        response
+            .contains("permessage-deflate; server_no_context_takeover; client_no_context_takeover"),
+        "{}",
+        response
+    );
+
+    let (is_compressed, payload) = read_frame(&mut stream);
+    assert!(is_compressed);
+    let mut inflated = Vec::with_capacity(2 * message().len());
+    Decompress::new(false)
+        .decompress_vec(
+            &[payload, vec![0x00, 0x00, 0xff, 0xff]].concat(),
+            &mut inflated,
+            FlushDecompress::Sync,
+        )
+        .unwrap();
+    assert_eq!(inflated, serde_json::to_vec(&message()).unwrap());
+
+    let mut encoder = DeflateEncoder::new(vec![], flate2::Compression::default());
+    encoder.write_all(b"\"hello\"").unwrap();
+    encoder.flush().unwrap();
+    let mut payload = std::mem::take(encoder.get_mut());
+    payload.truncate(payload.len() - 4);
+    // FIN, RSV1, binary; masked with a zero key.
+    let mut frame = vec![0xc2, 0x80 | payload.len() as u8, 0, 0, 0, 0];
+    frame.extend(payload);
+    stream.write_all(&frame).unwrap();
+    assert_eq!(server.join().unwrap(), "hello");
+}
+
+#[test]
+fn decompression_bombs_are_rejected() {
+    let limits = DecodeLimits {
+        max_message_size: 1024,
+        max_depth: 32,
+    };
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut com =
+            WebsocketClient::from_stream_with_compression(stream, limits, Some(ALWAYS)).unwrap();
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(5) {
+            match com.receive::<String>() {
+                Ok(Some(messages)) if !messages.is_empty() => return Ok(messages),
+                Err(e) => return Err(e),
+                _ => thread::sleep(Duration::from_millis(5)),
+            }
+        }
+        panic!("nothing received");
+    });
+    let mut client =
+        WebsocketClient::connect_with_compression(&url, DecodeLimits::default(), Some(ALWAYS))
+            .unwrap();
+    // Fits in the limit once compressed.
+    client.send(&"a".repeat(100_000)).unwrap();
+    assert!(client.traffic().bytes_sent < 1024);
+    assert!(server.join().unwrap().is_err());
+}
@@ -1,48 +1,122 @@
-use litlnet_trait::{ClientId, Server};
+use litlnet_trait::{
+    ClientId, Compression, ConnectionLimiter, ConnectionLimits, DecodeLimits, Server, Traffic,
+};
 use litlnet_websocket::Communication;
+use litlnet_websocket::{Handshake, PendingClient};
 use serde::{de::DeserializeOwned, Serialize};
 use std::collections::HashMap;
-use std::net::TcpListener;
+use std::net::{IpAddr, SocketAddr, TcpListener};
+use std::time::{Duration, Instant};
+
+/// Connections still in their handshake after it are dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
 
 struct Client {
     com: litlnet_websocket::WebsocketClient,
+    ip: IpAddr,
+}
+
+/// Admitted, its handshake goes on at each [`Server::accept_connections`].
+struct Pending {
+    client: PendingClient,
+    ip: IpAddr,
+    since: Instant,
 }
 
 pub struct ComServer {
     listener: TcpListener,
     clients: HashMap<ClientId, Client>,
+    pending: Vec<Pending>,
     next_available_id: ClientId,
     to_be_removed: Vec<ClientId>,
+    limiter: ConnectionLimiter,
+    decode_limits: DecodeLimits,
+    compression: Option<Compression>,
 }
 
 impl ComServer {
     pub fn iter(&self) -> impl Iterator<Item = &ClientId> + '_ {
         self.clients.keys()
     }
+    /// The bound address, to know the port when bound to port 0.
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+    pub fn limits(&self) -> &ConnectionLimits {
+        self.limiter.limits()
+    }
+    pub fn set_limits(&mut self, limits: ConnectionLimits) {
+        self.limiter.set_limits(limits);
+    }
+    /// Applies to clients connecting after this call.
+    pub fn set_decode_limits(&mut self, decode_limits: DecodeLimits) {
+        self.decode_limits = decode_limits;
+    }
+    /// Applies to clients connecting after this call, which offered permessage-deflate.
+    pub fn set_compression(&mut self, compression: Option<Compression>) {
+        self.compression = compression;
+    }
+
+    /// Adds the client once its handshake is done, keeps it pending until [`HANDSHAKE_TIMEOUT`].
+    fn finish_handshake(
+        &mut self,
+        handshake: std::io::Result<Handshake>,
+        ip: IpAddr,
+        since: Instant,
+        now: Instant,
+    ) {
+        match handshake {
+            Ok(Handshake::Done(com)) => {
+                self.clients
+                    .insert(self.next_available_id, Client { com, ip });
+                self.next_available_id.0 = self.next_available_id.0.wrapping_add(1);
+            }
+            Ok(Handshake::Pending(client)) if now - since < HANDSHAKE_TIMEOUT => {
+                self.pending.push(Pending { client, ip, since });
+            }
+            Ok(Handshake::Pending(_)) | Err(_) => {
+                self.limiter.release(ip);
+                println!("Failed to create client");
+            }
+        }
+    }
 }
 
 impl Server for ComServer {
     fn bind(addr: &str) -> Result<Self, std::io::Error> {
-        let listener = TcpListener::bind(addr).unwrap();
+        let listener = TcpListener::bind(addr)?;
         listener.set_nonblocking(true)?;
         Ok(Self {
             listener,
             clients: HashMap::new(),
+            pending: vec![],
             next_available_id: ClientId(usize::MIN),
             to_be_removed: vec![],
+            limiter: ConnectionLimiter::new(ConnectionLimits::default()),
+            decode_limits: DecodeLimits::default(),
+            compression: None,
         })
     }
     fn accept_connections(&mut self) {
-        for stream in self.listener.incoming() {
-            match stream {
-                Ok(stream) => {
-                    if let Ok(com) = litlnet_websocket::WebsocketClient::from_stream(stream) {
-                        let client = Client { com };
-                        self.clients.insert(self.next_available_id, client);
-                        self.next_available_id.0 = self.next_available_id.0.wrapping_add(1);
-                    } else {
-                        println!("Failed to create client");
+        let now = Instant::now();
+        for pending in std::mem::take(&mut self.pending) {
+            self.finish_handshake(pending.client.resume(), pending.ip, pending.since, now);
+        }
+        while self.limiter.can_accept(now) {
+            match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    let connected = self.clients.len() + self.pending.len();
+                    if let Err(rejection) = self.limiter.admit(addr.ip(), connected) {
+                        println!("Rejected {}: {}", addr, rejection);
+                        litlnet_websocket::reject_stream(stream, &rejection.to_string());
+                        continue;
                     }
+                    let handshake = litlnet_websocket::WebsocketClient::accept_with_compression(
+                        stream,
+                        self.decode_limits,
+                        self.compression,
+                    );
+                    self.finish_handshake(handshake, addr.ip(), now, now);
                 }
                 Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
                     break;
@@ -69,21 +143,48 @@ impl Server for ComServer {
                 }
             }
         }
-        for to_clean in &self.to_be_removed {
-            self.clients.remove(to_clean);
+        for to_clean in self.to_be_removed.drain(..) {
+            if let Some(client) = self.clients.remove(&to_clean) {
+                self.limiter.release(client.ip);
+            }
         }
     }
     fn send<T: Serialize>(&mut self, client_id: &ClientId, data: &T) {
-        match self.clients.get_mut(client_id) {
-            Some(client) => match client.com.send::<T>(data) {
+        if let Some(client) = self.clients.get_mut(client_id) {
+            match client.com.send::<T>(data) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                Err(e) => {
+                    dbg!(e);
+                    self.to_be_removed.push(*client_id);
+                }
+            }
+        }
+    }
+    fn send_batch<T: Serialize>(&mut self, client_id: &ClientId, data: &[T]) {
+        if let Some(client) = self.clients.get_mut(client_id) {
+            match client.com.send_batch::<T>(data) {
                 Ok(()) => {}
                 Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
                 Err(e) => {
                     dbg!(e);
                     self.to_be_removed.push(*client_id);
                 }
-            },
-            None => {}
+            }
         }
     }
+    fn disconnect(&mut self, client_id: &ClientId) {
+        if let Some(client) = self.clients.remove(client_id) {
+            self.limiter.release(client.ip);
+        }
+    }
+    fn clients(&self) -> Vec<ClientId> {
+        self.clients.keys().copied().collect()
+    }
+    fn clients_traffic(&self) -> Vec<(ClientId, Traffic)> {
+        self.clients
+            .iter()
+            .map(|(id, client)| (*id, client.com.traffic()))
+            .collect()
+    }
 }
@@ -0,0 +1,52 @@
+use std::{
+    io::Read,
+    net::TcpStream,
+    time::{Duration, Instant},
+};
+
+use litlnet_trait::{ConnectionLimits, Server};
+use litlnet_websocket::WebsocketClient;
+use litlnet_websocket_server::ComServer;
+
+#[test]
+fn silent_rejected_client_does_not_stall_accepts() {
+    let mut server = ComServer::bind("127.0.0.1:0").unwrap();
+    server.set_limits(ConnectionLimits {
+        max_clients: Some(0),
+        ..Default::default()
+    });
+    // Never sends its handshake.
+    let mut client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+    client.set_nonblocking(true).unwrap();
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(1) && server.clients().is_empty() {
+        server.accept_connections();
+        let mut response = String::new();
+        if client.read_to_string(&mut response).is_ok() {
+            assert!(response.starts_with("HTTP/1.1 503"), "{}", response);
+            assert!(response.ends_with("server is full"), "{}", response);
+            assert!(start.elapsed() < Duration::from_millis(200));
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    panic!("not rejected");
+}
+
+#[test]
+fn silent_client_does_not_stall_the_server() {
+    let mut server = ComServer::bind("127.0.0.1:0").unwrap();
+    let address = server.local_addr().unwrap();
+    // Never sends its handshake.
+    let _silent = TcpStream::connect(address).unwrap();
+    let connecting =
+        std::thread::spawn(move || WebsocketClient::connect(&format!("ws://{}", address)));
+    let start = Instant::now();
+    while server.clients().is_empty() {
+        assert!(start.elapsed() < Duration::from_secs(2), "stalled");
+        server.accept_connections();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert!(connecting.join().unwrap().is_ok());
+    assert_eq!(server.clients().len(), 1);
+}
@@ -2,11 +2,11 @@ use std::borrow::BorrowMut;
 use std::sync::Mutex;
 use std::sync::Once;
 
-use litlnet_trait::Communication;
+use litlnet_trait::{framing, Communication, Compression, DecodeLimits, Traffic};
 use serde::{de::DeserializeOwned, Serialize};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use web_sys::{ErrorEvent, MessageEvent, WebSocket};
+use web_sys::{CloseEvent, ErrorEvent, MessageEvent, WebSocket};
 // https://www.sitepoint.com/rust-global-variables/
 
 static mut WEBSOCKET: Option<Mutex<Option<WebSocket>>> = None;
@@ -28,30 +28,94 @@ fn global_recv_packets<'a>() -> &'a Mutex<Vec<Vec<u8>>> {
     unsafe { RECV_PACKETS.as_ref().unwrap() }
 }
 
-pub struct WebsocketClient {}
+pub struct WebsocketClient {
+    limits: DecodeLimits,
+    traffic: Traffic,
+    /// Frames sent before the websocket opened, with their count of messages.
+    queued: Vec<(Vec<u8>, usize)>,
+}
 
 impl WebsocketClient {
     pub fn connect(remote_addr: &str) -> Result<Self, std::io::Error> {
+        Self::connect_with_limits(remote_addr, DecodeLimits::default())
+    }
+    pub fn connect_with_limits(
+        remote_addr: &str,
+        limits: DecodeLimits,
+    ) -> Result<Self, std::io::Error> {
+        Self::connect_with_compression(remote_addr, limits, None)
+    }
+    /// Browsers negotiate permessage-deflate on their own, `compression` is only there to match
+    /// the native client.
+    pub fn connect_with_compression(
+        remote_addr: &str,
+        limits: DecodeLimits,
+        _compression: Option<Compression>,
+    ) -> Result<Self, std::io::Error> {
         match start_websocket(remote_addr) {
             Ok(websocket) => {
                 if let Ok(mut ws) = global_websocket().lock() {
                     if ws.is_none() {
                         *ws = Some(websocket);
-                        return Ok(Self {});
+                        return Ok(Self {
+                            limits,
+                            traffic: Traffic::default(),
+                            queued: vec![],
+                        });
                     }
                 }
-                return Err(std::io::Error::new(
+                let _ = websocket.close();
+                Err(std::io::Error::new(
                     std::io::ErrorKind::ConnectionAborted,
                     format!(
                         "connect to {} succeeded but then internal failure",
                         remote_addr
                     ),
-                ));
+                ))
             }
-            err => {
-                todo!("connect failure to {}\nerr: {:?}", remote_addr, err);
+            Err(err) => Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionRefused,
+                format!("connect to {} failed: {:?}", remote_addr, err),
+            )),
+        }
+    }
+
+    fn send_frame(&mut self, buf: Vec<u8>, messages: usize) -> std::io::Result<()> {
+        let ws = global_websocket()
+            .lock()
+            .map_err(|_| std::io::Error::other("cannot lock websocket global"))?;
+        let Some(ref ws) = *ws else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotConnected,
+                "no websocket",
+            ));
+        };
+        self.queued.push((buf, messages));
+        if ws.ready_state() == WebSocket::CONNECTING {
+            return Ok(());
+        }
+        for (buf, messages) in self.queued.drain(..) {
+            ws.send_with_u8_array(&buf)
+                .map_err(|err| std::io::Error::other(format!("{:?}", err)))?;
+            self.traffic.bytes_sent += buf.len() as u64;
+            self.traffic.frames_sent += 1;
+            self.traffic.messages_sent += messages as u64;
+        }
+        Ok(())
+    }
+}
+
+/// Frees the global websocket for the next connection.
+impl Drop for WebsocketClient {
+    fn drop(&mut self) {
+        if let Ok(mut ws) = global_websocket().lock() {
+            if let Some(ws) = ws.take() {
+                let _ = ws.close();
             }
         }
+        if let Ok(mut recv) = global_recv_packets().lock() {
+            recv.clear();
+        }
     }
 }
 
@@ -64,45 +128,45 @@ impl Communication for WebsocketClient {
                 }
                 let mut res = vec![];
                 for m in recv.iter() {
-                    if let Ok(de) = serde_json::from_slice::<T>(m) {
-                        res.push(de);
+                    self.traffic.bytes_received += m.len() as u64;
+                    self.traffic.frames_received += 1;
+                    match framing::decode_frame::<T>(m, &self.limits) {
+                        Ok(decoded) => {
+                            self.traffic.decode_errors += decoded.errors;
+                            res.extend(decoded.messages);
+                        }
+                        Err(e) => {
+                            dbg!(e);
+                            self.traffic.decode_errors += 1;
+                        }
                     }
                 }
+                self.traffic.messages_received += res.len() as u64;
                 recv.clear();
-                return Ok(Some(res));
+                Ok(Some(res))
             }
-            Err(e) => todo!("receive failure {}", e),
+            Err(e) => Err(std::io::Error::other(format!("receive failure {}", e))),
         }
     }
 
     fn send<T: Serialize>(&mut self, message: &T) -> std::io::Result<()> {
-        match global_websocket().lock() {
-            Ok(ws) => match *ws {
-                Some(ref ws) => {
-                    let mut buf = Vec::new();
-                    if message
-                        .serialize(&mut serde_json::Serializer::new(&mut buf))
-                        .is_ok()
-                    {
-                        match ws.send_with_u8_array(&buf) {
-                            Ok(_) => {
-                                dbg!("binary message successfully sent");
-                            }
-                            Err(err) => {
-                                dbg!("error sending message: {:?}", err);
-                            }
-                        }
-                    }
-                }
-                None => {
-                    todo!("no socket at this point ?")
-                }
-            },
-            Err(_) => {
-                todo!("cannot lock websocket global")
-            }
+        let buf = framing::encode(message)?;
+        self.send_frame(buf, 1)
+    }
+
+    fn send_batch<T: Serialize>(&mut self, messages: &[T]) -> std::io::Result<()> {
+        match messages {
+            [] => return Ok(()),
+            // The batch header would only be overhead.
+            [message] => return self.send(message),
+            _ => {}
         }
-        Ok(())
+        let buf = framing::encode_batch(messages)?;
+        self.send_frame(buf, messages.len())
+    }
+
+    fn traffic(&self) -> Traffic {
+        self.traffic
     }
 }
 
@@ -136,7 +200,18 @@ fn start_websocket(remote_addr: &str) -> Result<WebSocket, JsValue> {
     ws.set_onerror(Some(onerror_callback.as_ref().unchecked_ref()));
     onerror_callback.forget();
 
-    let _cloned_ws = ws.clone();
+    let closed_ws = ws.clone();
+    let onclose_callback = Closure::wrap(Box::new(move |_: CloseEvent| {
+        // Sends then fail, so the client is dropped.
+        if let Ok(mut ws) = global_websocket().lock() {
+            if ws.as_ref() == Some(&closed_ws) {
+                *ws = None;
+            }
+        }
+    }) as Box<dyn FnMut(CloseEvent)>);
+    ws.set_onclose(Some(onclose_callback.as_ref().unchecked_ref()));
+    onclose_callback.forget();
+
     let onopen_callback = Closure::wrap(Box::new(move |_| {
         // ?
     }) as Box<dyn FnMut(JsValue)>);
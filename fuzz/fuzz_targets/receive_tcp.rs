@@ -0,0 +1,25 @@
+#![no_main]
+
+use std::io::Write;
+use std::net::{Shutdown, TcpListener, TcpStream};
+
+use example_server::ServerConfig;
+use example_shared::ClientMessage;
+use libfuzzer_sys::fuzz_target;
+use litlnet_tcp::{Communication, TcpClient};
+use litlnet_trait::Packet;
+
+fuzz_target!(|data: &[u8]| {
+    // A new connection for each input keeps the runs reproducible.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut writer = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+    // As `example_server` receives them.
+    let mut receiver = TcpClient::from_stream(stream).unwrap();
+    receiver.set_limits(ServerConfig::default().decode_limits());
+
+    writer.write_all(data).unwrap();
+    writer.shutdown(Shutdown::Write).unwrap();
+    // Receive until the closed connection (or a limit) is reported.
+    while receiver.receive::<Packet<ClientMessage>>().is_ok() {}
+});
@@ -0,0 +1,56 @@
+#![no_main]
+
+use std::io::Write;
+use std::net::{Shutdown, TcpListener, TcpStream};
+
+use example_server::ServerConfig;
+use example_shared::ClientMessage;
+use libfuzzer_sys::fuzz_target;
+use litlnet_trait::Packet;
+use litlnet_websocket::{Communication, WebsocketClient};
+
+const HANDSHAKE: &[u8] = b"GET / HTTP/1.1\r\n\
+Host: localhost\r\n\
+Connection: Upgrade\r\n\
+Upgrade: websocket\r\n\
+Sec-WebSocket-Version: 13\r\n\
+Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n";
+
+/// Wraps `payload` in a single masked binary frame, as a client would send it.
+fn binary_frame(payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0x82];
+    match payload.len() {
+        len if len < 126 => frame.push(0x80 | len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(0x80 | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    // A zero mask leaves the payload as is.
+    frame.extend_from_slice(&[0, 0, 0, 0]);
+    frame.extend_from_slice(payload);
+    frame
+}
+
+fuzz_target!(|data: &[u8]| {
+    // A new connection for each input keeps the runs reproducible.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut writer = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+
+    // The handshake request is already buffered when the server side reads it.
+    writer.write_all(HANDSHAKE).unwrap();
+    // As `example_server` receives them.
+    let mut receiver =
+        WebsocketClient::from_stream_with_limits(stream, ServerConfig::default().decode_limits())
+            .unwrap();
+
+    writer.write_all(&binary_frame(data)).unwrap();
+    writer.shutdown(Shutdown::Write).unwrap();
+    // Receive until the closed connection (or a limit) is reported.
+    while receiver.receive::<Packet<ClientMessage>>().is_ok() {}
+});